@@ -1,28 +1,48 @@
 use std::boxed::Box;
+use std::env;
 use std::error::Error;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Read;
 use std::io::Write;
+use std::path::Path;
 
 use glob::glob;
-use rayon::prelude::*;
 
-// Example custom build script.
+// Compiles every shader under src into `OUT_DIR`, keeping its path relative to
+// src, so that `src/material/pbr_material.frag` can be picked up with
+// `wgpu::include_spirv!(concat!(env!("OUT_DIR"), "/material/pbr_material.frag.spv"))`.
 fn main() -> Result<(), Box<dyn Error>> {
     let mut compiler = shaderc::Compiler::new().unwrap();
+    let out_dir = env::var("OUT_DIR")?;
 
-    for path in glob("./src/**/*.vert")? {
-        let path = path?;
-        let mut source = String::new();
-        let mut file = File::open(&path)?;
-        file.read_to_string(&mut source)?;
-        let binary = compiler.compile_into_spirv(
-            &mut source,
-            shaderc::ShaderKind::Vertex,
-            path.to_str().unwrap(),
-            "main",
-            None,
-        )?;
+    let shader_kinds = [
+        ("vert", shaderc::ShaderKind::Vertex),
+        ("frag", shaderc::ShaderKind::Fragment),
+        ("comp", shaderc::ShaderKind::Compute),
+    ];
+
+    for (extension, shader_kind) in shader_kinds.iter() {
+        for path in glob(&format!("./src/**/*.{}", extension))? {
+            let path = path?;
+            println!("cargo:rerun-if-changed={}", path.display());
+
+            let mut source = String::new();
+            let mut file = File::open(&path)?;
+            file.read_to_string(&mut source)?;
+            let binary = compiler.compile_into_spirv(
+                &source,
+                *shader_kind,
+                path.to_str().unwrap(),
+                "main",
+                None,
+            )?;
+
+            let relative = path.strip_prefix("src")?;
+            let output_path = Path::new(&out_dir).join(format!("{}.spv", relative.display()));
+            fs::create_dir_all(output_path.parent().unwrap())?;
+            let mut output = File::create(output_path)?;
+            output.write_all(binary.as_binary_u8())?;
+        }
     }
 
     Ok(())
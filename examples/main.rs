@@ -1,7 +1,7 @@
-use cgmath::{prelude::One, Matrix4};
 use futures::executor::block_on;
 use transform3d::{
-    BaseGeometry, Camera, Component, Group, Mesh, RenderCamera, Scene, SolidColorMaterial, State,
+    BaseGeometry, Component, Group, Mesh, Projection, RenderCamera, RenderWindow, Scene,
+    SolidColorMaterial, State,
 };
 use winit::{
     event::*,
@@ -20,11 +20,16 @@ fn main() {
     let state = block_on(State::new());
     let scene = Scene::new_ref();
 
-    let camera = RenderCamera::new_ref(Matrix4::<f32>::one(), &window, &state);
+    let render_window = RenderWindow::new_ref(&window, &state);
+    let camera = RenderCamera::new_ref(
+        Projection::orthographic(2.0, -1.0, 1.0),
+        render_window.clone(),
+        &state,
+    );
 
     let geometry = BaseGeometry::new(&state);
     let material = SolidColorMaterial::new(cgmath::Vector3::new(0.0, 1.0, 0.0), &state);
-    let mesh = Mesh::new_ref(geometry, material, &state);
+    let mesh = Mesh::new_ref(geometry, material);
 
     let root = Group::new_ref(&scene);
 
@@ -41,7 +46,7 @@ fn main() {
     event_loop.run(move |event, _, control_flow| match event {
         Event::RedrawRequested(_) => {
             //state.update();
-            camera.borrow_mut().render();
+            RenderCamera::render_all(&[camera.clone()]);
         }
         Event::MainEventsCleared => {
             window.request_redraw();
@@ -62,10 +67,10 @@ fn main() {
                         _ => {}
                     },
                     WindowEvent::Resized(physical_size) => {
-                        camera.borrow_mut().resize(*physical_size);
+                        render_window.borrow_mut().resize(*physical_size);
                     }
                     WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                        camera.borrow_mut().resize(**new_inner_size);
+                        render_window.borrow_mut().resize(**new_inner_size);
                     }
                     _ => {}
                 }
@@ -0,0 +1,263 @@
+use std::{collections::HashMap, rc::Rc};
+
+use cgmath::{Matrix4, SquareMatrix, Vector4};
+
+use crate::{CubeTexture, DepthState, RenderTargetFormat, State, Texture, DEFAULT_COLOR_FORMAT};
+
+/// What a camera draws behind the scene.
+#[derive(Clone)]
+pub enum Background {
+    /// Keeps what cameras with a lower order drew, for overlays.
+    None,
+    Color(wgpu::Color),
+    /// Blends from `top` at the top of the viewport to `bottom`.
+    Gradient {
+        top: wgpu::Color,
+        bottom: wgpu::Color,
+    },
+    /// A cube map in world space, seen from the camera's orientation.
+    Skybox(Rc<CubeTexture>),
+    /// An equirectangular panorama in world space, laid out like the images
+    /// `Environment::load` takes.
+    Panorama(Rc<Texture>),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+enum BackgroundKind {
+    Gradient,
+    Skybox,
+    Panorama,
+}
+
+impl Background {
+    fn kind(&self) -> Option<BackgroundKind> {
+        match self {
+            Background::None => None,
+            Background::Color(_) | Background::Gradient { .. } => Some(BackgroundKind::Gradient),
+            Background::Skybox(_) => Some(BackgroundKind::Skybox),
+            Background::Panorama(_) => Some(BackgroundKind::Panorama),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct BackgroundConstants {
+    inverse_view_projection: Matrix4<f32>,
+    top_color: Vector4<f32>,
+    bottom_color: Vector4<f32>,
+}
+
+unsafe impl bytemuck::Zeroable for BackgroundConstants {}
+unsafe impl bytemuck::Pod for BackgroundConstants {}
+
+fn color_vector(color: wgpu::Color) -> Vector4<f32> {
+    Vector4::new(
+        color.r as f32,
+        color.g as f32,
+        color.b as f32,
+        color.a as f32,
+    )
+}
+
+/// Pipelines and bind group drawing a camera's background as a full screen
+/// triangle at the start of its pass.
+pub(crate) struct BackgroundRenderer {
+    pipelines: HashMap<(BackgroundKind, RenderTargetFormat), wgpu::RenderPipeline>,
+    cube_layout: wgpu::BindGroupLayout,
+    panorama_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    /// Bind group of the textured background, cleared when it changes.
+    bind_group: Option<wgpu::BindGroup>,
+}
+
+impl BackgroundRenderer {
+    pub fn new(state: &State) -> BackgroundRenderer {
+        let layout = |dimension, label| {
+            state
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStage::FRAGMENT,
+                            ty: wgpu::BindingType::SampledTexture {
+                                dimension,
+                                component_type: wgpu::TextureComponentType::Float,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStage::FRAGMENT,
+                            ty: wgpu::BindingType::Sampler { comparison: false },
+                            count: None,
+                        },
+                    ],
+                    label: Some(label),
+                })
+        };
+        let sampler = state.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("background_sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        BackgroundRenderer {
+            pipelines: HashMap::new(),
+            cube_layout: layout(wgpu::TextureViewDimension::Cube, "skybox_bind_group_layout"),
+            panorama_layout: layout(wgpu::TextureViewDimension::D2, "panorama_bind_group_layout"),
+            sampler,
+            bind_group: None,
+        }
+    }
+
+    /// Call when the background changes.
+    pub fn invalidate(&mut self) {
+        self.bind_group = None;
+    }
+
+    /// Builds what drawing `background` into `target` needs.
+    pub fn prepare(&mut self, background: &Background, target: RenderTargetFormat, state: &State) {
+        let kind = match background.kind() {
+            Some(kind) => kind,
+            None => return,
+        };
+
+        let view = match background {
+            Background::Skybox(texture) => Some((&texture.view, &self.cube_layout)),
+            Background::Panorama(texture) => Some((&texture.view, &self.panorama_layout)),
+            _ => None,
+        };
+        if let (None, Some((view, layout))) = (&self.bind_group, view) {
+            self.bind_group = Some(state.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                ],
+                label: Some("background_bind_group"),
+            }));
+        }
+
+        if self.pipelines.contains_key(&(kind, target)) {
+            return;
+        }
+        let (bind_group_layouts, fragment_stage) = match kind {
+            BackgroundKind::Gradient => (
+                vec![],
+                wgpu::include_spirv!(concat!(
+                    env!("OUT_DIR"),
+                    "/camera/background_gradient.frag.spv"
+                )),
+            ),
+            BackgroundKind::Skybox => (
+                vec![&self.cube_layout],
+                wgpu::include_spirv!(concat!(
+                    env!("OUT_DIR"),
+                    "/camera/background_skybox.frag.spv"
+                )),
+            ),
+            BackgroundKind::Panorama => (
+                vec![&self.panorama_layout],
+                wgpu::include_spirv!(concat!(
+                    env!("OUT_DIR"),
+                    "/camera/background_panorama.frag.spv"
+                )),
+            ),
+        };
+        let device = &state.device;
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            bind_group_layouts: &bind_group_layouts,
+            push_constant_ranges: &[wgpu::PushConstantRange {
+                stages: wgpu::ShaderStage::FRAGMENT,
+                range: 0..std::mem::size_of::<BackgroundConstants>() as u32,
+            }],
+            label: Some("background_pipeline_layout"),
+        });
+        let vs_module = device.create_shader_module(wgpu::include_spirv!(concat!(
+            env!("OUT_DIR"),
+            "/camera/background.vert.spv"
+        )));
+        let fs_module = device.create_shader_module(fragment_stage);
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            layout: Some(&layout),
+            vertex_stage: wgpu::ProgrammableStageDescriptor {
+                module: &vs_module,
+                entry_point: "main",
+            },
+            fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+                module: &fs_module,
+                entry_point: "main",
+            }),
+            rasterization_state: Some(wgpu::RasterizationStateDescriptor::default()),
+            color_states: &[wgpu::ColorStateDescriptor {
+                format: target.color_format.unwrap_or(DEFAULT_COLOR_FORMAT),
+                color_blend: wgpu::BlendDescriptor::REPLACE,
+                alpha_blend: wgpu::BlendDescriptor::REPLACE,
+                write_mask: wgpu::ColorWrite::ALL,
+            }],
+            primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+            depth_stencil_state: DepthState::disabled().descriptor(&target),
+            vertex_state: wgpu::VertexStateDescriptor {
+                index_format: wgpu::IndexFormat::Uint16,
+                vertex_buffers: &[],
+            },
+            sample_count: target.sample_count,
+            sample_mask: !0,
+            alpha_to_coverage_enabled: false,
+            label: Some("background_pipeline"),
+        });
+        self.pipelines.insert((kind, target), pipeline);
+    }
+
+    /// Draws a prepared background. `view_projection` is the OpenGL style
+    /// projection times the view matrix without its translation.
+    pub fn draw<'a>(
+        &'a self,
+        background: &Background,
+        target: RenderTargetFormat,
+        view_projection: &Matrix4<f32>,
+        render_pass: &mut wgpu::RenderPass<'a>,
+    ) {
+        let pipeline = match background
+            .kind()
+            .and_then(|kind| self.pipelines.get(&(kind, target)))
+        {
+            Some(pipeline) => pipeline,
+            None => return,
+        };
+        let (top_color, bottom_color) = match *background {
+            Background::Color(color) => (color, color),
+            Background::Gradient { top, bottom } => (top, bottom),
+            _ => (wgpu::Color::BLACK, wgpu::Color::BLACK),
+        };
+        let constants = BackgroundConstants {
+            inverse_view_projection: view_projection.invert().unwrap_or_else(Matrix4::identity),
+            top_color: color_vector(top_color),
+            bottom_color: color_vector(bottom_color),
+        };
+
+        render_pass.set_pipeline(pipeline);
+        if let Some(ref bind_group) = self.bind_group {
+            render_pass.set_bind_group(0, bind_group, &[]);
+        }
+        render_pass.set_push_constants(
+            wgpu::ShaderStage::FRAGMENT,
+            0,
+            bytemuck::cast_slice(&[constants]),
+        );
+        render_pass.draw(0..3, 0..1);
+    }
+}
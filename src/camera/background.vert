@@ -0,0 +1,10 @@
+#version 450
+
+layout(location=0) out vec2 v_clip;
+
+// A triangle covering the screen; viewport and scissor limit it to the camera.
+void main() {
+  vec2 position = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+  v_clip = position * 2.0 - 1.0;
+  gl_Position = vec4(v_clip, 0.0, 1.0);
+}
@@ -0,0 +1,15 @@
+#version 450
+
+layout(push_constant) uniform ConstData {
+  mat4 inverse_view_projection;
+  vec4 top_color;
+  vec4 bottom_color;
+} PushConstant;
+
+layout(location=0) in vec2 v_clip;
+
+layout(location=0) out vec4 f_color;
+
+void main() {
+  f_color = mix(PushConstant.bottom_color, PushConstant.top_color, v_clip.y * 0.5 + 0.5);
+}
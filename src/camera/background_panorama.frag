@@ -0,0 +1,34 @@
+#version 450
+
+const float PI = 3.14159265359;
+
+layout(push_constant) uniform ConstData {
+  mat4 inverse_view_projection;
+  vec4 top_color;
+  vec4 bottom_color;
+} PushConstant;
+
+layout(location=0) in vec2 v_clip;
+
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2D panorama;
+layout(set=0, binding=1) uniform sampler panorama_sampler;
+
+// World space direction through the fragment, from two depths so that
+// orthographic and infinite projections work too.
+vec3 view_direction() {
+  vec4 near = PushConstant.inverse_view_projection * vec4(v_clip, -1.0, 1.0);
+  vec4 far = PushConstant.inverse_view_projection * vec4(v_clip, 0.0, 1.0);
+  return normalize(far.xyz / far.w - near.xyz / near.w);
+}
+
+// Same layout as `Environment::from_equirectangular`.
+void main() {
+  vec3 direction = view_direction();
+  vec2 uv = vec2(
+    0.5 + atan(direction.x, -direction.z) / (2.0 * PI),
+    acos(clamp(direction.y, -1.0, 1.0)) / PI
+  );
+  f_color = vec4(texture(sampler2D(panorama, panorama_sampler), uv).rgb, 1.0);
+}
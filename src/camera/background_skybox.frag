@@ -0,0 +1,26 @@
+#version 450
+
+layout(push_constant) uniform ConstData {
+  mat4 inverse_view_projection;
+  vec4 top_color;
+  vec4 bottom_color;
+} PushConstant;
+
+layout(location=0) in vec2 v_clip;
+
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform textureCube skybox;
+layout(set=0, binding=1) uniform sampler skybox_sampler;
+
+// World space direction through the fragment, from two depths so that
+// orthographic and infinite projections work too.
+vec3 view_direction() {
+  vec4 near = PushConstant.inverse_view_projection * vec4(v_clip, -1.0, 1.0);
+  vec4 far = PushConstant.inverse_view_projection * vec4(v_clip, 0.0, 1.0);
+  return normalize(far.xyz / far.w - near.xyz / near.w);
+}
+
+void main() {
+  f_color = vec4(texture(samplerCube(skybox, skybox_sampler), view_direction()).rgb, 1.0);
+}
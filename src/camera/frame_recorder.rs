@@ -0,0 +1,69 @@
+use std::path::{Path, PathBuf};
+
+use crate::RenderCamera;
+
+/// Writes a numbered PNG sequence of a camera at a fixed frame rate in
+/// simulated time: advance the scene by `frame_duration` before each `record`,
+/// however long rendering and saving take in real time. Automatic exposure of
+/// the camera adapts by `frame_duration` per frame as well.
+pub struct FrameRecorder {
+    directory: PathBuf,
+    frame_rate: f32,
+    frame: u32,
+}
+
+impl FrameRecorder {
+    /// Creates `directory` if needed.
+    pub fn new<P: AsRef<Path>>(directory: P, frame_rate: f32) -> std::io::Result<FrameRecorder> {
+        std::fs::create_dir_all(&directory)?;
+        Ok(FrameRecorder {
+            directory: directory.as_ref().to_path_buf(),
+            frame_rate,
+            frame: 0,
+        })
+    }
+
+    /// Number of the next frame recorded.
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Seconds of simulated time per frame.
+    pub fn frame_duration(&self) -> f32 {
+        1.0 / self.frame_rate
+    }
+
+    /// Simulated time of the next frame in seconds.
+    pub fn time(&self) -> f32 {
+        self.frame as f32 / self.frame_rate
+    }
+
+    pub fn frame_path(&self, frame: u32) -> PathBuf {
+        self.directory.join(format!("frame_{:05}.png", frame))
+    }
+
+    /// Captures the camera into the next file of the sequence and returns its path.
+    pub fn record(&mut self, camera: &mut RenderCamera) -> image::ImageResult<PathBuf> {
+        let path = self.frame_path(self.frame);
+        camera.set_exposure_time_step(self.frame_duration());
+        camera.screenshot(&path)?;
+        self.frame += 1;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_timing() {
+        let directory =
+            std::env::temp_dir().join(format!("transform3d_frame_recorder_{}", std::process::id()));
+        let recorder = FrameRecorder::new(&directory, 30.0).unwrap();
+        assert_eq!(recorder.time(), 0.0);
+        assert!((recorder.frame_duration() - 1.0 / 30.0).abs() < 1e-9);
+        assert_eq!(recorder.frame_path(42), directory.join("frame_00042.png"));
+        std::fs::remove_dir(&directory).unwrap();
+    }
+}
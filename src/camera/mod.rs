@@ -1 +1,5 @@
+pub mod background;
+pub mod frame_recorder;
+pub mod post_stack;
 pub mod render_camera;
+pub mod tonemapper;
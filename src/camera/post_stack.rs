@@ -0,0 +1,231 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    FrameAttachment, PostEffect, PostOutput, RenderTargetFormat, State, Texture,
+    DEFAULT_COLOR_FORMAT,
+};
+
+/// The post effects of a camera, applied in order to the rendered scene. The
+/// scene and every effect but the last draw into one of two textures of the
+/// size of the target, the next effect reads it and the last draws onto the
+/// target itself.
+pub struct PostStack {
+    effects: Vec<(Rc<RefCell<dyn PostEffect>>, bool)>,
+    textures: Option<PostTextures>,
+}
+
+struct PostTextures {
+    format: RenderTargetFormat,
+    ping_pong: [Texture; 2],
+    /// Multisampled scene attachment resolving into the first texture.
+    msaa_texture: Option<Texture>,
+}
+
+impl PostStack {
+    pub fn new() -> PostStack {
+        PostStack {
+            effects: Vec::new(),
+            textures: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.effects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+
+    /// Whether an effect is enabled, so that the camera draws through the
+    /// stack.
+    pub fn is_active(&self) -> bool {
+        self.effects.iter().any(|(_, enabled)| *enabled)
+    }
+
+    /// Appends an enabled effect, applied after the others.
+    pub fn push(&mut self, effect: Rc<RefCell<dyn PostEffect>>) {
+        self.effects.push((effect, true));
+    }
+
+    pub fn insert(&mut self, index: usize, effect: Rc<RefCell<dyn PostEffect>>) {
+        self.effects.insert(index, (effect, true));
+    }
+
+    pub fn remove(&mut self, index: usize) -> Rc<RefCell<dyn PostEffect>> {
+        self.effects.remove(index).0
+    }
+
+    /// Moves the effect at `from` to `to`, shifting those in between.
+    pub fn move_effect(&mut self, from: usize, to: usize) {
+        let effect = self.effects.remove(from);
+        self.effects.insert(to, effect);
+    }
+
+    pub fn effect(&self, index: usize) -> &Rc<RefCell<dyn PostEffect>> {
+        &self.effects[index].0
+    }
+
+    pub fn is_enabled(&self, index: usize) -> bool {
+        self.effects[index].1
+    }
+
+    /// Disabled effects are skipped without losing their place.
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        self.effects[index].1 = enabled;
+    }
+
+    /// Creates the textures for a target of `size` in the color format of
+    /// `format` and a scene drawn with its sample count.
+    pub(crate) fn prepare(&mut self, size: [u32; 2], format: RenderTargetFormat, state: &State) {
+        if let Some(ref textures) = self.textures {
+            let texture_size = textures.ping_pong[0].size;
+            if textures.format == format
+                && [texture_size.width, texture_size.height] == [size[0].max(1), size[1].max(1)]
+            {
+                return;
+            }
+        }
+
+        let color_format = format.color_format.unwrap_or(DEFAULT_COLOR_FORMAT);
+        let create = || {
+            Texture::new(
+                size[0].max(1),
+                size[1].max(1),
+                color_format,
+                wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+                Some("Post Texture"),
+                state,
+            )
+        };
+        let msaa_texture = if format.sample_count > 1 {
+            Some(Texture::new_multisampled(
+                size[0].max(1),
+                size[1].max(1),
+                color_format,
+                wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+                format.sample_count,
+                Some("Post MSAA Texture"),
+                state,
+            ))
+        } else {
+            None
+        };
+        self.textures = Some(PostTextures {
+            format,
+            ping_pong: [create(), create()],
+            msaa_texture,
+        });
+    }
+
+    /// Format of the textures between effects.
+    pub(crate) fn texture_format(&self) -> RenderTargetFormat {
+        let format = self.textures.as_ref().unwrap().format;
+        RenderTargetFormat {
+            depth_format: None,
+            reversed_z: false,
+            sample_count: 1,
+            ..format
+        }
+    }
+
+    /// Where the scene is drawn, with the sample count given to `prepare`.
+    pub(crate) fn attachment(&self) -> FrameAttachment<'_> {
+        let textures = self.textures.as_ref().unwrap();
+        match textures.msaa_texture {
+            Some(ref texture) => FrameAttachment {
+                view: &texture.view,
+                resolve_target: Some(&textures.ping_pong[0].view),
+                first: true,
+            },
+            None => FrameAttachment {
+                view: &textures.ping_pong[0].view,
+                resolve_target: None,
+                first: true,
+            },
+        }
+    }
+
+    /// The single sampled texture the scene ends up in.
+    pub(crate) fn source(&self) -> &Texture {
+        &self.textures.as_ref().unwrap().ping_pong[0]
+    }
+
+    /// Applies the enabled effects to the scene, the last one drawing into
+    /// `output`.
+    pub(crate) fn render(
+        &self,
+        output: &PostOutput,
+        encoder: &mut wgpu::CommandEncoder,
+        state: &State,
+    ) {
+        let textures = self.textures.as_ref().unwrap();
+        let enabled: Vec<_> = self
+            .effects
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(effect, _)| effect)
+            .collect();
+
+        for (i, effect) in enabled.iter().enumerate() {
+            let input = &textures.ping_pong[i % 2];
+            if i + 1 == enabled.len() {
+                effect.borrow_mut().render(input, output, encoder, state);
+            } else {
+                let intermediate = PostOutput {
+                    view: &textures.ping_pong[(i + 1) % 2].view,
+                    resolve_target: None,
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    format: self.texture_format(),
+                    blend: false,
+                    size: output.size,
+                    viewport: output.viewport,
+                    scissor: output.scissor,
+                };
+                effect
+                    .borrow_mut()
+                    .render(input, &intermediate, encoder, state);
+            }
+        }
+    }
+}
+
+impl Default for PostStack {
+    fn default() -> Self {
+        PostStack::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NoEffect;
+
+    impl PostEffect for NoEffect {
+        fn render(&mut self, _: &Texture, _: &PostOutput, _: &mut wgpu::CommandEncoder, _: &State) {
+        }
+    }
+
+    #[test]
+    fn test_order_and_toggle() {
+        let effects: Vec<Rc<RefCell<dyn PostEffect>>> = (0..3)
+            .map(|_| Rc::new(RefCell::new(NoEffect)) as Rc<RefCell<dyn PostEffect>>)
+            .collect();
+        let mut stack = PostStack::new();
+        assert!(!stack.is_active());
+        for effect in effects.iter() {
+            stack.push(effect.clone());
+        }
+        stack.move_effect(0, 2);
+        assert!(Rc::ptr_eq(stack.effect(0), &effects[1]));
+        assert!(Rc::ptr_eq(stack.effect(2), &effects[0]));
+
+        for i in 0..stack.len() {
+            stack.set_enabled(i, false);
+        }
+        assert!(!stack.is_active());
+        stack.set_enabled(1, true);
+        assert!(stack.is_active());
+    }
+}
@@ -1,14 +1,21 @@
 use std::{
     cell::{Ref, RefCell},
+    cmp::Ordering,
+    error::Error,
+    fmt,
+    path::Path,
     rc::{Rc, Weak},
 };
 
-use winit::window::Window;
-
 use cgmath::{prelude::SquareMatrix, Matrix4, Vector3, Vector4};
 
+use crate::camera::{background::BackgroundRenderer, tonemapper::Tonemapper};
 use crate::{
-    Camera, CameraUniform, Component, Light, Mesh, RenderPassWrapper, Scene, State, Transformation,
+    Background, Camera, CameraUniform, ClusterGrid, Component, DebugView, Exposure, Frustum,
+    Geometry, Light, LightArray, LightUniform, Material, Mesh, PostOutput, PostStack, Projection,
+    RenderPassWrapper, RenderTarget, RenderTargetFormat, RenderTexture, Scene, State, Texture,
+    Tonemapping, Transformation, Viewport, DEFAULT_DEPTH_FORMAT, DEFAULT_RENDER_TEXTURE_FORMAT,
+    HDR_FORMAT,
 };
 
 #[cfg_attr(rustfmt, rustfmt_skip)]
@@ -19,17 +26,61 @@ pub const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
     0.0, 0.0, 0.5, 1.0,
 );
 
+/// A sample count wgpu cannot render with: 0, above 32 or not a power of two.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InvalidSampleCount(pub u32);
+
+impl fmt::Display for InvalidSampleCount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid sample count {}, expected a power of two up to 32",
+            self.0
+        )
+    }
+}
+
+impl Error for InvalidSampleCount {}
+
+/// Background of the viewport unless a camera sets its own.
+pub const DEFAULT_CLEAR_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.1,
+    g: 0.2,
+    b: 0.3,
+    a: 1.0,
+};
+
 pub struct RenderCamera {
     state: State,
-    surface: wgpu::Surface,
-    size: winit::dpi::PhysicalSize<u32>,
-    sc_desc: wgpu::SwapChainDescriptor,
-    swap_chain: wgpu::SwapChain,
+    target: Rc<RefCell<dyn RenderTarget>>,
+    /// Size of the target the attachments were created for.
+    size: [u32; 2],
+    viewport: Viewport,
+    scissor: Option<Viewport>,
+    background: Background,
+    order: i32,
 
+    projection: Projection,
+    reversed_z: bool,
+    depth_format: Option<wgpu::TextureFormat>,
+    sample_count: u32,
+    /// Recreated when the target is resized.
+    depth_texture: Option<Texture>,
+    background_renderer: BackgroundRenderer,
+    /// Present while rendering in HDR.
+    tonemapper: Option<Tonemapper>,
+    tonemapping: Tonemapping,
+    exposure: Exposure,
+    exposure_time_step: f32,
+    post_effects: PostStack,
+    /// OpenGL style matrix of `projection` at the current aspect ratio.
     projection_matrix: Matrix4<f32>,
 
     data: CameraUniform,
 
+    debug_view: Option<DebugView>,
+    lights: LightArray,
+
     has_parent: bool,
     scene: Weak<RefCell<Scene>>,
     children: Vec<Rc<RefCell<dyn Component>>>,
@@ -38,36 +89,47 @@ pub struct RenderCamera {
 
 impl RenderCamera {
     pub fn new_ref(
-        projection_matrix: Matrix4<f32>,
-        window: &Window,
+        projection: Projection,
+        target: Rc<RefCell<dyn RenderTarget>>,
         state: &State,
     ) -> Rc<RefCell<RenderCamera>> {
-        let size = window.inner_size();
-
-        let sc_desc = wgpu::SwapChainDescriptor {
-            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
-            format: wgpu::TextureFormat::Bgra8UnormSrgb,
-            width: size.width,
-            height: size.height,
-            present_mode: wgpu::PresentMode::Fifo,
-        };
-
-        let surface = unsafe { state.instance.create_surface(window) };
-        let swap_chain = state.device.create_swap_chain(&surface, &sc_desc);
+        let size = target.borrow().size();
 
+        let aspect = RenderCamera::aspect(size, Viewport::FULL);
+        let projection_matrix = projection.matrix(aspect);
         let data = CameraUniform {
-            projection_matrix: OPENGL_TO_WGPU_MATRIX * projection_matrix,
+            projection_matrix: projection.clip_matrix(aspect, false),
             camera_position: Vector3::<f32>::new(0.0f32, 0.0f32, 0.0f32),
         };
 
         let result = RenderCamera {
             state: state.clone(),
-            surface,
-            sc_desc,
-            swap_chain,
+            target,
             size,
+            viewport: Viewport::FULL,
+            scissor: None,
+            background: Background::Color(DEFAULT_CLEAR_COLOR),
+            order: 0,
+            projection,
+            reversed_z: false,
+            depth_format: Some(DEFAULT_DEPTH_FORMAT),
+            sample_count: 1,
+            depth_texture: RenderCamera::create_depth_texture(
+                Some(DEFAULT_DEPTH_FORMAT),
+                1,
+                size,
+                state,
+            ),
+            background_renderer: BackgroundRenderer::new(state),
+            tonemapper: None,
+            tonemapping: Tonemapping::AcesFilmic,
+            exposure: Exposure::Manual(0.0),
+            exposure_time_step: 1.0 / 60.0,
+            post_effects: PostStack::new(),
             projection_matrix,
             data,
+            debug_view: None,
+            lights: LightArray::new(state),
             has_parent: false,
             scene: Weak::new(),
             children: Vec::new(),
@@ -77,14 +139,442 @@ impl RenderCamera {
         Rc::new(RefCell::new(result))
     }
 
-    pub fn resize(&mut self, size: winit::dpi::PhysicalSize<u32>) {
-        self.size = size;
-        self.sc_desc.width = size.width;
-        self.sc_desc.height = size.height;
-        self.swap_chain = self
-            .state
-            .device
-            .create_swap_chain(&self.surface, &self.sc_desc);
+    /// Renders the cameras, then presents their windows. Cameras rendering
+    /// into a texture go before the cameras whose scene samples it, otherwise
+    /// cameras go by ascending `order`.
+    pub fn render_all(cameras: &[Rc<RefCell<RenderCamera>>]) {
+        let orders: Vec<i32> = cameras.iter().map(|camera| camera.borrow().order).collect();
+        let dependencies: Vec<Vec<usize>> = cameras
+            .iter()
+            .enumerate()
+            .map(|(i, camera)| {
+                let camera = camera.borrow();
+                (0..cameras.len())
+                    .filter(|&j| {
+                        j != i
+                            && match cameras[j].borrow().target.borrow().texture() {
+                                Some(texture) => camera.samples(texture),
+                                None => false,
+                            }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        for i in RenderCamera::render_order(&orders, &dependencies) {
+            cameras[i].borrow_mut().render();
+        }
+        for camera in cameras.iter() {
+            camera.borrow().target.borrow_mut().finish_frame();
+        }
+    }
+
+    /// Indices of cameras by `orders`, each after the cameras it depends on.
+    /// A cycle is broken at its camera with the lowest order, which then sees
+    /// the previous frame of the others.
+    fn render_order(orders: &[i32], dependencies: &[Vec<usize>]) -> Vec<usize> {
+        let mut remaining: Vec<usize> = (0..orders.len()).collect();
+        remaining.sort_by_key(|&i| orders[i]);
+        let mut result = Vec::new();
+        while !remaining.is_empty() {
+            let ready = |i: &usize| {
+                dependencies[*i]
+                    .iter()
+                    .all(|dependency| !remaining.contains(dependency))
+            };
+            let in_cycle = |i: &usize| {
+                let mut stack = dependencies[*i].clone();
+                let mut visited = Vec::new();
+                while let Some(j) = stack.pop() {
+                    if j == *i {
+                        return true;
+                    }
+                    if remaining.contains(&j) && !visited.contains(&j) {
+                        visited.push(j);
+                        stack.extend(dependencies[j].iter().copied());
+                    }
+                }
+                false
+            };
+            let next = remaining
+                .iter()
+                .position(ready)
+                .or_else(|| remaining.iter().position(in_cycle))
+                .unwrap_or(0);
+            result.push(remaining.remove(next));
+        }
+        result
+    }
+
+    /// Whether a mesh of the camera's scene samples `texture`.
+    fn samples(&self, texture: &Rc<Texture>) -> bool {
+        match self.scene.upgrade() {
+            Some(scene) => scene
+                .borrow()
+                .meshes
+                .iter()
+                .any(|mesh| RenderCamera::material_samples(&*mesh.borrow().material, texture)),
+            None => false,
+        }
+    }
+
+    fn material_samples(material: &dyn Material, texture: &Rc<Texture>) -> bool {
+        material
+            .sampled_textures()
+            .iter()
+            .any(|sampled| Rc::ptr_eq(sampled, texture))
+    }
+
+    pub fn target(&self) -> &Rc<RefCell<dyn RenderTarget>> {
+        &self.target
+    }
+
+    /// Renders the scene again into an image of the viewport, leaving the
+    /// target untouched; this is not a read back of the last presented frame.
+    /// Automatic exposure does not adapt during the capture, so it does not
+    /// change the next frame. Targets in formats other than 8 bit RGBA or
+    /// BGRA are captured as `DEFAULT_RENDER_TEXTURE_FORMAT`.
+    pub fn capture(&mut self) -> image::ImageResult<image::RgbaImage> {
+        let (size, format) = {
+            let target = self.target.borrow();
+            (target.size(), target.format())
+        };
+        let format = if Texture::is_readable_format(format) {
+            format
+        } else {
+            DEFAULT_RENDER_TEXTURE_FORMAT
+        };
+        let texture = RenderTexture::new_ref(size[0], size[1], format, &self.state);
+        let target = std::mem::replace(&mut self.target, texture.clone());
+        let exposure_time_step = std::mem::replace(&mut self.exposure_time_step, 0.0);
+        self.render();
+        self.exposure_time_step = exposure_time_step;
+        self.target = target;
+
+        let texture = texture.borrow();
+        texture
+            .texture()
+            .unwrap()
+            .to_image(self.viewport.pixels(size), &self.state)
+    }
+
+    /// Captures the viewport into an image file, in the format of its extension.
+    pub fn screenshot<P: AsRef<Path>>(&mut self, path: P) -> image::ImageResult<()> {
+        self.capture()?.save(path)
+    }
+
+    /// Follows a resize of the target.
+    fn update_size(&mut self) {
+        let size = self.target.borrow().size();
+        if size != self.size {
+            self.size = size;
+            self.projection_matrix = self.projection.matrix(self.current_aspect());
+            self.create_attachments();
+        }
+    }
+
+    fn create_attachments(&mut self) {
+        if let Some(ref mut tonemapper) = self.tonemapper {
+            tonemapper.resize(self.size, self.sample_count, &self.state);
+        }
+        self.depth_texture = RenderCamera::create_depth_texture(
+            self.depth_format,
+            self.sample_count,
+            self.size,
+            &self.state,
+        );
+    }
+
+    fn create_depth_texture(
+        format: Option<wgpu::TextureFormat>,
+        sample_count: u32,
+        size: [u32; 2],
+        state: &State,
+    ) -> Option<Texture> {
+        format.map(|format| {
+            Texture::new_multisampled(
+                size[0].max(1),
+                size[1].max(1),
+                format,
+                wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+                sample_count,
+                Some("Depth Texture"),
+                state,
+            )
+        })
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+
+    /// Part of the target the camera draws into; the aspect ratio of the
+    /// projection follows it.
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.viewport = viewport;
+        self.projection_matrix = self.projection.matrix(self.current_aspect());
+    }
+
+    pub fn scissor(&self) -> Option<Viewport> {
+        self.scissor
+    }
+
+    /// Further limits drawing to a part of the viewport, given in fractions of
+    /// the target like the viewport itself.
+    pub fn set_scissor(&mut self, scissor: Option<Viewport>) {
+        self.scissor = scissor;
+    }
+
+    pub fn background(&self) -> &Background {
+        &self.background
+    }
+
+    /// What fills the viewport before the scene is drawn, `Background::None`
+    /// to draw over what cameras with a lower order rendered. The depth
+    /// buffer of a camera is its own and always cleared.
+    pub fn set_background(&mut self, background: Background) {
+        self.background = background;
+        self.background_renderer.invalidate();
+    }
+
+    pub fn order(&self) -> i32 {
+        self.order
+    }
+
+    /// Cameras with a higher order draw later, over those with a lower one.
+    pub fn set_order(&mut self, order: i32) {
+        self.order = order;
+    }
+
+    pub fn depth_format(&self) -> Option<wgpu::TextureFormat> {
+        self.depth_format
+    }
+
+    /// `None` draws without a depth buffer, in submission order. A format with
+    /// a stencil aspect enables the stencil state of materials.
+    pub fn set_depth_format(&mut self, depth_format: Option<wgpu::TextureFormat>) {
+        self.depth_format = depth_format;
+        self.create_attachments();
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Number of samples per pixel for multisample anti-aliasing, 1 to turn
+    /// it off. Counts the adapter supports are powers of two, commonly 4.
+    /// Cameras sharing a target should use the same count. Keeps the current
+    /// count if `sample_count` is not a power of two up to 32.
+    pub fn set_sample_count(&mut self, sample_count: u32) -> Result<(), InvalidSampleCount> {
+        if !RenderCamera::is_valid_sample_count(sample_count) {
+            return Err(InvalidSampleCount(sample_count));
+        }
+        self.sample_count = sample_count;
+        self.create_attachments();
+        Ok(())
+    }
+
+    fn is_valid_sample_count(sample_count: u32) -> bool {
+        sample_count.is_power_of_two() && sample_count <= 32
+    }
+
+    /// The depth buffer of the last frame.
+    pub fn depth_texture(&self) -> Option<&Texture> {
+        self.depth_texture.as_ref()
+    }
+
+    pub fn hdr(&self) -> bool {
+        self.tonemapper.is_some()
+    }
+
+    /// Renders the scene into a texture of `HDR_FORMAT`, which is then
+    /// exposed and tonemapped onto the target, instead of straight into the
+    /// target.
+    pub fn set_hdr(&mut self, hdr: bool) {
+        self.tonemapper = if hdr {
+            Some(Tonemapper::new(self.size, self.sample_count, &self.state))
+        } else {
+            None
+        };
+    }
+
+    pub fn tonemapping(&self) -> Tonemapping {
+        self.tonemapping
+    }
+
+    /// Curve mapping the exposed scene onto the target when rendering in HDR.
+    pub fn set_tonemapping(&mut self, tonemapping: Tonemapping) {
+        self.tonemapping = tonemapping;
+    }
+
+    pub fn exposure(&self) -> Exposure {
+        self.exposure
+    }
+
+    /// Brightness of the scene when rendering in HDR.
+    pub fn set_exposure(&mut self, exposure: Exposure) {
+        self.exposure = exposure;
+    }
+
+    pub fn exposure_time_step(&self) -> f32 {
+        self.exposure_time_step
+    }
+
+    /// Seconds automatic exposure adapts by in the next frame, 1/60 by
+    /// default; set it to the time since the last frame. `FrameRecorder` sets
+    /// its frame duration.
+    pub fn set_exposure_time_step(&mut self, dt: f32) {
+        self.exposure_time_step = dt;
+    }
+
+    pub fn post_effects(&self) -> &PostStack {
+        &self.post_effects
+    }
+
+    /// Effects applied to the rendered viewport, after tonemapping when
+    /// rendering in HDR.
+    pub fn post_effects_mut(&mut self) -> &mut PostStack {
+        &mut self.post_effects
+    }
+
+    /// Format meshes and backgrounds are drawn in, that of the HDR scene
+    /// texture when rendering in HDR.
+    pub fn target_format(&self) -> RenderTargetFormat {
+        let color_format = if self.tonemapper.is_some() {
+            HDR_FORMAT
+        } else {
+            self.target.borrow().format()
+        };
+        RenderTargetFormat {
+            color_format: Some(color_format),
+            depth_format: self.depth_format,
+            reversed_z: self.reversed_z,
+            sample_count: self.sample_count,
+        }
+    }
+
+    /// Format of the passes onto the target after the scene.
+    fn output_format(&self) -> RenderTargetFormat {
+        RenderTargetFormat {
+            color_format: Some(self.target.borrow().format()),
+            depth_format: None,
+            reversed_z: false,
+            sample_count: self.sample_count,
+        }
+    }
+
+    fn aspect(size: [u32; 2], viewport: Viewport) -> f32 {
+        let [_, _, width, height] = viewport.pixels(size);
+        width.max(1) as f32 / height.max(1) as f32
+    }
+
+    fn current_aspect(&self) -> f32 {
+        RenderCamera::aspect(self.size, self.viewport)
+    }
+
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    /// Takes effect at the next update; the aspect ratio follows the viewport.
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+        self.projection_matrix = projection.matrix(self.current_aspect());
+    }
+
+    /// OpenGL style projection matrix at the current aspect ratio.
+    pub fn projection_matrix(&self) -> Matrix4<f32> {
+        self.projection_matrix
+    }
+
+    pub fn reversed_z(&self) -> bool {
+        self.reversed_z
+    }
+
+    /// Maps the near plane to a depth of 1 and the far plane to 0, which with
+    /// a float depth buffer keeps precision far from the camera.
+    pub fn set_reversed_z(&mut self, reversed_z: bool) {
+        self.reversed_z = reversed_z;
+    }
+
+    /// World space planes of the view volume as of the last update.
+    pub fn frustum(&self) -> Frustum {
+        let view_matrix = self
+            .transformation
+            .global_transformation()
+            .invert()
+            .unwrap_or_else(Matrix4::identity);
+        Frustum::from_matrix(&(self.projection_matrix * view_matrix))
+    }
+
+    pub fn debug_view(&self) -> Option<DebugView> {
+        self.debug_view
+    }
+
+    /// Draws every mesh with the given debug view, overriding `Mesh::debug_view`.
+    pub fn set_debug_view(&mut self, debug_view: Option<DebugView>) {
+        self.debug_view = debug_view;
+    }
+
+    pub fn max_lights(&self) -> usize {
+        self.lights.max_lights()
+    }
+
+    pub fn set_max_lights(&mut self, max_lights: usize) {
+        self.lights.set_max_lights(max_lights);
+    }
+
+    /// How many lights of the scene exceeded `max_lights` in the last frame.
+    pub fn dropped_lights(&self) -> usize {
+        self.lights.dropped_lights()
+    }
+
+    /// Size and number of the shadow maps; lights asking for shadows beyond
+    /// that many get none.
+    pub fn set_shadow_maps(&mut self, size: u32, layers: u32) {
+        self.lights.set_shadow_maps(size, layers, &self.state);
+    }
+
+    pub fn cluster_grid(&self) -> ClusterGrid {
+        self.lights.cluster_grid()
+    }
+
+    /// The grid lights are binned into; `near` and `far` should cover the
+    /// depth range of the projection.
+    pub fn set_cluster_grid(&mut self, cluster_grid: ClusterGrid) {
+        self.lights.set_cluster_grid(cluster_grid);
+    }
+
+    /// Overlap of two pixel rectangles, empty if they are disjoint.
+    fn intersect(a: [u32; 4], b: [u32; 4]) -> [u32; 4] {
+        let left = a[0].max(b[0]);
+        let top = a[1].max(b[1]);
+        let right = (a[0] + a[2]).min(b[0] + b[2]);
+        let bottom = (a[1] + a[3]).min(b[1] + b[3]);
+        [
+            left,
+            top,
+            right.saturating_sub(left),
+            bottom.saturating_sub(top),
+        ]
+    }
+
+    /// Opaque meshes are drawn first in submission order, then transparent
+    /// meshes from the farthest to the nearest along the view direction.
+    fn draw_order(meshes: &[(bool, Vector4<f32>)], view_matrix: &Matrix4<f32>) -> Vec<usize> {
+        let mut opaque = Vec::new();
+        let mut transparent = Vec::new();
+        for (i, (is_transparent, position)) in meshes.iter().enumerate() {
+            if *is_transparent {
+                transparent.push((i, (view_matrix * position).z));
+            } else {
+                opaque.push(i);
+            }
+        }
+        // The camera looks down -z, so the farthest meshes have the smallest z.
+        transparent.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+        opaque.extend(transparent.into_iter().map(|(i, _)| i));
+        opaque
     }
 }
 
@@ -117,8 +607,9 @@ impl Component for RenderCamera {
         &mut self.transformation
     }
     fn update(&mut self) {
-        self.data.projection_matrix = OPENGL_TO_WGPU_MATRIX
-            * self.projection_matrix
+        self.data.projection_matrix = self
+            .projection
+            .clip_matrix(self.current_aspect(), self.reversed_z)
             * self
                 .transformation()
                 .global_transformation()
@@ -133,13 +624,19 @@ impl Component for RenderCamera {
 }
 
 impl Camera for RenderCamera {
+    /// Draws into the viewport of the target's current frame, which windows
+    /// show on `RenderWindow::present`. Meshes sampling the target are left
+    /// out.
     fn render(&mut self) {
-        let frame = {
-            self.swap_chain
-                .get_current_frame()
-                .expect("Timeout getting texture")
-                .output
+        self.update_size();
+        let viewport = self.viewport.pixels(self.size);
+        let scissor = match self.scissor {
+            Some(scissor) => RenderCamera::intersect(viewport, scissor.pixels(self.size)),
+            None => viewport,
         };
+        if scissor[2] == 0 || scissor[3] == 0 {
+            return;
+        }
 
         let scene = self.scene.upgrade().unwrap();
 
@@ -152,41 +649,330 @@ impl Camera for RenderCamera {
                     label: Some("Render Encoder"),
                 });
 
+        let view_matrix = self
+            .transformation()
+            .global_transformation()
+            .invert()
+            .unwrap_or_else(Matrix4::identity);
+
+        let lights: Vec<LightUniform> = scene
+            .lights
+            .iter()
+            .map(|light| light.borrow().light_uniform())
+            .collect();
+        self.lights.update(
+            &lights,
+            scene.environment.as_ref(),
+            &view_matrix,
+            &self.projection_matrix,
+            Vector4::new(
+                viewport[0] as f32,
+                viewport[1] as f32,
+                viewport[2] as f32,
+                viewport[3] as f32,
+            ),
+            &self.state,
+        );
+
+        let render_shadows = !self.lights.shadow_layers().is_empty();
+        let target = self.target_format();
+        self.background_renderer
+            .prepare(&self.background, target, &self.state);
+        let output_format = self.output_format();
+        let post = self.post_effects.is_active();
+        if post {
+            self.post_effects
+                .prepare(self.size, output_format, &self.state);
+        }
+        for mesh in scene.meshes.iter() {
+            let mut mesh = mesh.borrow_mut();
+            mesh.prepare_pipeline(target, &self.state);
+            if render_shadows && mesh.cast_shadows() {
+                mesh.prepare_shadow_pipeline(&self.state);
+            }
+            if let Some(view) = self.debug_view.or(mesh.debug_view) {
+                mesh.prepare_debug_view(view, target, &self.state);
+                if let Some(debug_pipeline) = mesh.debug_pipeline_mut(view) {
+                    debug_pipeline.material.set_view_matrix(view_matrix);
+                }
+            }
+        }
+
         {
             let meshes: Vec<Ref<Mesh>> = scene.meshes.iter().map(|x| x.borrow()).collect();
             let meshes: Vec<&Mesh> = meshes.iter().map(|x| &**x).collect();
+            let debug_pipelines: Vec<_> = meshes
+                .iter()
+                .map(|mesh| {
+                    self.debug_view
+                        .or(mesh.debug_view)
+                        .and_then(|view| mesh.debug_pipeline(view))
+                })
+                .collect();
+            for (mesh, debug_pipeline) in meshes.iter().zip(debug_pipelines.iter()) {
+                mesh.material.update_buffers(&self.state);
+                if let Some(debug_pipeline) = debug_pipeline {
+                    debug_pipeline.material.update_buffers(&self.state);
+                }
+            }
             let lights: Vec<Ref<dyn Light>> = scene.lights.iter().map(|x| x.borrow()).collect();
             let lights: Vec<&dyn Light> = lights.iter().map(|x| &**x).collect();
+
+            let shadow_cameras: Vec<CameraUniform> = self
+                .lights
+                .shadow_layers()
+                .iter()
+                .map(|shadow_layer| CameraUniform {
+                    projection_matrix: shadow_layer.projection_matrix,
+                    camera_position: Vector3::new(0.0, 0.0, 0.0),
+                })
+                .collect();
+            for (layer, shadow_camera) in shadow_cameras.iter().enumerate() {
+                let mut shadow_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    color_attachments: &[],
+                    depth_stencil_attachment: Some(
+                        wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                            attachment: self.lights.shadow_maps().layer_view(layer as u32),
+                            depth_ops: Some(wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(1.0),
+                                store: true,
+                            }),
+                            stencil_ops: None,
+                        },
+                    ),
+                });
+                for mesh in meshes.iter() {
+                    mesh.draw_shadow(&mut RenderPassWrapper {
+                        render_pass: &mut shadow_pass,
+                        mesh_uniform: &mesh.data,
+                        camera_uniform: shadow_camera,
+                        state: &self.state,
+                    });
+                }
+            }
+
+            let mut render_target = self.target.borrow_mut();
+            let own_texture = render_target.texture().cloned();
+            // HDR cameras and cameras with post effects draw into a texture of
+            // their own, which the tonemapping pass or the post effects map
+            // onto the target afterwards.
+            let offscreen = self.tonemapper.is_some() || post;
+            let attachment = match self.tonemapper {
+                Some(ref tonemapper) => tonemapper.attachment(),
+                None if post => self.post_effects.attachment(),
+                None => render_target.attachment(self.sample_count),
+            };
+            // The first pass of a frame clears all of it, later ones draw
+            // their background over their viewport only.
+            let (load, draw_background) = match (attachment.first, &self.background) {
+                (true, Background::Color(color)) => (wgpu::LoadOp::Clear(*color), false),
+                (true, Background::None) if offscreen => {
+                    (wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT), false)
+                }
+                (true, _) => (wgpu::LoadOp::Clear(wgpu::Color::BLACK), true),
+                (false, _) => (wgpu::LoadOp::Load, true),
+            };
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                    attachment: &frame.view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
-                        store: true,
-                    },
+                    attachment: attachment.view,
+                    resolve_target: attachment.resolve_target,
+                    ops: wgpu::Operations { load, store: true },
                 }],
-                depth_stencil_attachment: None,
+                depth_stencil_attachment: self.depth_texture.as_ref().map(|depth_texture| {
+                    wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                        attachment: &depth_texture.view,
+                        depth_ops: Some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(target.clear_depth()),
+                            store: true,
+                        }),
+                        stencil_ops: if target.has_stencil() {
+                            Some(wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(0),
+                                store: true,
+                            })
+                        } else {
+                            None
+                        },
+                    }
+                }),
             });
 
-            for mesh in meshes.iter() {
-                mesh.material.draw_material(
-                    &*mesh.geometry,
-                    &lights,
-                    &mesh.render_pipeline,
-                    &mut RenderPassWrapper {
-                        render_pass: &mut render_pass,
-                        mesh_uniform: &mesh.data,
-                        camera_uniform: &self.data,
-                    },
+            render_pass.set_viewport(
+                viewport[0] as f32,
+                viewport[1] as f32,
+                viewport[2] as f32,
+                viewport[3] as f32,
+                0.0,
+                1.0,
+            );
+            render_pass.set_scissor_rect(scissor[0], scissor[1], scissor[2], scissor[3]);
+            if draw_background {
+                // Only the direction matters, so the camera stays at the origin.
+                let mut rotation_matrix = view_matrix;
+                rotation_matrix.w = Vector4::new(0.0, 0.0, 0.0, 1.0);
+                self.background_renderer.draw(
+                    &self.background,
+                    target,
+                    &(self.projection_matrix * rotation_matrix),
+                    &mut render_pass,
                 );
             }
+
+            self.lights.bind(&mut render_pass);
+
+            let draw_order = RenderCamera::draw_order(
+                &meshes
+                    .iter()
+                    .map(|mesh| {
+                        (
+                            mesh.material.blend_mode().is_transparent(),
+                            mesh.transformation().global_transformation().w,
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+                &view_matrix,
+            );
+
+            for i in draw_order {
+                let mesh = meshes[i];
+                if let Some(ref own_texture) = own_texture {
+                    if RenderCamera::material_samples(&*mesh.material, own_texture) {
+                        continue;
+                    }
+                }
+                let debug_pipeline = debug_pipelines[i];
+                let mut render_pass = RenderPassWrapper {
+                    render_pass: &mut render_pass,
+                    mesh_uniform: &mesh.data,
+                    camera_uniform: &self.data,
+                    state: &self.state,
+                };
+                // The wireframe is an overlay, every other debug view replaces the material.
+                let overlay = debug_pipeline
+                    .map(|debug_pipeline| debug_pipeline.material.view() == DebugView::Wireframe)
+                    .unwrap_or(true);
+                if overlay {
+                    mesh.material.draw_material(
+                        &*mesh.geometry,
+                        &lights,
+                        mesh.render_pipeline(&target).unwrap(),
+                        &mut render_pass,
+                    );
+                }
+                if let Some(debug_pipeline) = debug_pipeline {
+                    let geometry: &dyn Geometry = match debug_pipeline.geometry {
+                        Some(ref geometry) => geometry,
+                        None => &*mesh.geometry,
+                    };
+                    debug_pipeline.material.draw_material(
+                        geometry,
+                        &lights,
+                        debug_pipeline.render_pipeline(&target).unwrap(),
+                        &mut render_pass,
+                    );
+                }
+            }
+            drop(render_pass);
+
+            if offscreen {
+                if let Some(ref mut tonemapper) = self.tonemapper {
+                    tonemapper.update_exposure(
+                        self.exposure,
+                        self.exposure_time_step,
+                        viewport,
+                        &mut encoder,
+                        &self.state,
+                    );
+                }
+
+                let attachment = render_target.attachment(self.sample_count);
+                let target_output = PostOutput {
+                    view: attachment.view,
+                    resolve_target: attachment.resolve_target,
+                    load: match (attachment.first, &self.background) {
+                        (true, Background::Color(color)) => wgpu::LoadOp::Clear(*color),
+                        (true, _) => wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        (false, _) => wgpu::LoadOp::Load,
+                    },
+                    format: output_format,
+                    blend: true,
+                    size: self.size,
+                    viewport,
+                    scissor,
+                };
+                match self.tonemapper {
+                    Some(ref mut tonemapper) if post => {
+                        let source = self.post_effects.source();
+                        let source_output = PostOutput {
+                            view: &source.view,
+                            resolve_target: None,
+                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                            format: self.post_effects.texture_format(),
+                            blend: false,
+                            ..target_output
+                        };
+                        tonemapper.render(
+                            self.tonemapping,
+                            &source_output,
+                            &mut encoder,
+                            &self.state,
+                        );
+                    }
+                    Some(ref mut tonemapper) => {
+                        tonemapper.render(
+                            self.tonemapping,
+                            &target_output,
+                            &mut encoder,
+                            &self.state,
+                        );
+                    }
+                    None => {}
+                }
+                if post {
+                    self.post_effects
+                        .render(&target_output, &mut encoder, &self.state);
+                }
+            }
         }
         self.state.queue.submit(Some(encoder.finish()));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_order() {
+        // A monitor camera 1 whose scene samples the texture of camera 0, a
+        // camera 2 without dependencies and cameras 0 and 3 depending on each
+        // other.
+        let orders = [1, 0, -1, 2];
+        let dependencies = [vec![3], vec![0], vec![], vec![0]];
+        let order = RenderCamera::render_order(&orders, &dependencies);
+        assert_eq!(order, vec![2, 0, 1, 3]);
+    }
+
+    #[test]
+    fn test_sample_count() {
+        for &count in &[1, 2, 4, 8, 32] {
+            assert!(RenderCamera::is_valid_sample_count(count));
+        }
+        for &count in &[0, 3, 6, 64] {
+            assert!(!RenderCamera::is_valid_sample_count(count));
+        }
+    }
+
+    #[test]
+    fn test_draw_order() {
+        let meshes = [
+            (true, Vector4::new(0.0, 0.0, -1.0, 1.0)),
+            (false, Vector4::new(0.0, 0.0, -5.0, 1.0)),
+            (true, Vector4::new(0.0, 0.0, -10.0, 1.0)),
+            (false, Vector4::new(0.0, 0.0, -2.0, 1.0)),
+            (true, Vector4::new(0.0, 0.0, -3.0, 1.0)),
+        ];
+        let order = RenderCamera::draw_order(&meshes, &Matrix4::identity());
+        assert_eq!(order, vec![1, 3, 2, 4, 0]);
+    }
+}
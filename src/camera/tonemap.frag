@@ -0,0 +1,46 @@
+#version 450
+
+layout(push_constant) uniform ConstData {
+  uint tonemapping;
+  uint encode_srgb;
+} PushConstant;
+
+layout(set=0, binding=0) uniform texture2D hdr_texture;
+layout(set=0, binding=1) uniform sampler hdr_sampler;
+layout(set=0, binding=2) readonly buffer Exposure {
+  float average_luminance;
+  float exposure;
+};
+
+layout(location=0) out vec4 f_color;
+
+const uint TONEMAPPING_CLAMP = 0;
+const uint TONEMAPPING_REINHARD = 1;
+const uint TONEMAPPING_ACES_FILMIC = 2;
+
+// Krzysztof Narkowicz's fit of the ACES reference rendering transform.
+vec3 aces_filmic(vec3 color) {
+  color *= 0.6;
+  return (color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14);
+}
+
+vec3 srgb_encode(vec3 color) {
+  return mix(12.92 * color, 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, color));
+}
+
+// The scene texture has the size of the target, so the fragment reads the
+// texel under it.
+void main() {
+  vec4 hdr = texelFetch(sampler2D(hdr_texture, hdr_sampler), ivec2(gl_FragCoord.xy), 0);
+  vec3 color = max(hdr.rgb * exposure, vec3(0.0));
+  if (PushConstant.tonemapping == TONEMAPPING_REINHARD) {
+    color = color / (1.0 + color);
+  } else if (PushConstant.tonemapping == TONEMAPPING_ACES_FILMIC) {
+    color = aces_filmic(color);
+  }
+  color = clamp(color, 0.0, 1.0);
+  if (PushConstant.encode_srgb != 0) {
+    color = srgb_encode(color);
+  }
+  f_color = vec4(color, clamp(hdr.a, 0.0, 1.0));
+}
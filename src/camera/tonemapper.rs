@@ -0,0 +1,490 @@
+use std::collections::HashMap;
+
+use wgpu::util::DeviceExt;
+
+use crate::{smoothing_factor, FrameAttachment, PostOutput, RenderTargetFormat, State, Texture};
+
+/// Format of the scene texture of cameras rendering in HDR.
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Curve compressing the exposed scene into the range the target shows.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Tonemapping {
+    /// Cuts off everything brighter than white.
+    Clamp = 0,
+    /// `c / (1 + c)` per channel, which never quite reaches white.
+    Reinhard = 1,
+    /// A fit of the ACES filmic curve, with a toe and a shoulder and more
+    /// contrast than Reinhard.
+    AcesFilmic = 2,
+}
+
+/// How much the scene is brightened or darkened before tonemapping.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Exposure {
+    /// Scales the scene by 2 to the power of the given number of stops.
+    Manual(f32),
+    /// Meters the viewport every frame and adapts to it.
+    Automatic(AutoExposure),
+}
+
+/// Exposure mapping the log average luminance of the viewport to middle grey,
+/// metered from a histogram of the luminance.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AutoExposure {
+    /// Range of log2 luminance the histogram covers; darker and brighter
+    /// pixels count as its ends.
+    pub min_log2_luminance: f32,
+    pub max_log2_luminance: f32,
+    /// Stops added to the metered exposure.
+    pub compensation: f32,
+    /// Time constant in seconds the exposure follows the scene with, 0 to
+    /// snap.
+    pub smoothing: f32,
+}
+
+impl Default for AutoExposure {
+    fn default() -> Self {
+        AutoExposure {
+            min_log2_luminance: -8.0,
+            max_log2_luminance: 4.0,
+            compensation: 0.0,
+            smoothing: 0.5,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct ExposureConstants {
+    origin: [u32; 2],
+    size: [u32; 2],
+    min_log2_luminance: f32,
+    inverse_log2_range: f32,
+    adaptation: f32,
+    compensation: f32,
+}
+
+unsafe impl bytemuck::Zeroable for ExposureConstants {}
+unsafe impl bytemuck::Pod for ExposureConstants {}
+
+/// Whether a target of `format` needs the tonemapping pass to encode sRGB
+/// itself, because the hardware only does it for `*Srgb` formats.
+fn encodes_srgb(format: wgpu::TextureFormat) -> bool {
+    matches!(
+        format,
+        wgpu::TextureFormat::Rgba8Unorm
+            | wgpu::TextureFormat::Bgra8Unorm
+            | wgpu::TextureFormat::Rgb10a2Unorm
+    )
+}
+
+const HISTOGRAM_BINS: u64 = 256;
+const HISTOGRAM_GROUP_SIZE: u32 = 16;
+
+/// The scene texture of an HDR camera, the exposure metering and the pass
+/// tonemapping the scene onto the camera's target.
+pub(crate) struct Tonemapper {
+    hdr_texture: Texture,
+    msaa_texture: Option<Texture>,
+    sampler: wgpu::Sampler,
+    /// The average luminance and the exposure derived from it.
+    exposure_buffer: wgpu::Buffer,
+    histogram_buffer: wgpu::Buffer,
+    tonemap_layout: wgpu::BindGroupLayout,
+    tonemap_bind_group: wgpu::BindGroup,
+    exposure_layout: wgpu::BindGroupLayout,
+    exposure_bind_group: wgpu::BindGroup,
+    histogram_pipeline: wgpu::ComputePipeline,
+    average_pipeline: wgpu::ComputePipeline,
+    tonemap_pipelines: HashMap<(RenderTargetFormat, bool), wgpu::RenderPipeline>,
+    /// Whether the exposure was metered the frame before, so that the
+    /// first metering snaps to the scene and the following adapt to it.
+    metered: bool,
+}
+
+impl Tonemapper {
+    pub fn new(size: [u32; 2], sample_count: u32, state: &State) -> Tonemapper {
+        let device = &state.device;
+        let (hdr_texture, msaa_texture) = Tonemapper::create_textures(size, sample_count, state);
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("hdr_sampler"),
+            ..Default::default()
+        });
+        let exposure_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Exposure Buffer"),
+            contents: bytemuck::cast_slice(&[0.18f32, 1.0f32]),
+            usage: wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::COPY_DST,
+        });
+        let histogram_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Luminance Histogram Buffer"),
+            contents: &[0; 4 * HISTOGRAM_BINS as usize],
+            usage: wgpu::BufferUsage::STORAGE,
+        });
+
+        let texture_entries = |visibility| {
+            vec![
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility,
+                    ty: wgpu::BindingType::SampledTexture {
+                        dimension: wgpu::TextureViewDimension::D2,
+                        component_type: wgpu::TextureComponentType::Float,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility,
+                    ty: wgpu::BindingType::Sampler { comparison: false },
+                    count: None,
+                },
+            ]
+        };
+        let storage_entry = |binding, visibility, size, readonly| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::StorageBuffer {
+                dynamic: false,
+                min_binding_size: std::num::NonZeroU64::new(size),
+                readonly,
+            },
+            count: None,
+        };
+
+        let mut tonemap_entries = texture_entries(wgpu::ShaderStage::FRAGMENT);
+        tonemap_entries.push(storage_entry(2, wgpu::ShaderStage::FRAGMENT, 8, true));
+        let tonemap_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &tonemap_entries,
+            label: Some("tonemap_bind_group_layout"),
+        });
+        let mut exposure_entries = texture_entries(wgpu::ShaderStage::COMPUTE);
+        exposure_entries.push(storage_entry(2, wgpu::ShaderStage::COMPUTE, 8, false));
+        exposure_entries.push(storage_entry(
+            3,
+            wgpu::ShaderStage::COMPUTE,
+            4 * HISTOGRAM_BINS,
+            false,
+        ));
+        let exposure_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &exposure_entries,
+            label: Some("exposure_bind_group_layout"),
+        });
+
+        let exposure_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                bind_group_layouts: &[&exposure_layout],
+                push_constant_ranges: &[wgpu::PushConstantRange {
+                    stages: wgpu::ShaderStage::COMPUTE,
+                    range: 0..std::mem::size_of::<ExposureConstants>() as u32,
+                }],
+                label: Some("exposure_pipeline_layout"),
+            });
+        let histogram_module = device.create_shader_module(wgpu::include_spirv!(concat!(
+            env!("OUT_DIR"),
+            "/camera/luminance_histogram.comp.spv"
+        )));
+        let histogram_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("luminance_histogram"),
+            layout: Some(&exposure_pipeline_layout),
+            compute_stage: wgpu::ProgrammableStageDescriptor {
+                module: &histogram_module,
+                entry_point: "main",
+            },
+        });
+        let average_module = device.create_shader_module(wgpu::include_spirv!(concat!(
+            env!("OUT_DIR"),
+            "/camera/luminance_average.comp.spv"
+        )));
+        let average_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("luminance_average"),
+            layout: Some(&exposure_pipeline_layout),
+            compute_stage: wgpu::ProgrammableStageDescriptor {
+                module: &average_module,
+                entry_point: "main",
+            },
+        });
+
+        let (tonemap_bind_group, exposure_bind_group) = Tonemapper::create_bind_groups(
+            &hdr_texture,
+            &sampler,
+            &exposure_buffer,
+            &histogram_buffer,
+            &tonemap_layout,
+            &exposure_layout,
+            state,
+        );
+
+        Tonemapper {
+            hdr_texture,
+            msaa_texture,
+            sampler,
+            exposure_buffer,
+            histogram_buffer,
+            tonemap_layout,
+            tonemap_bind_group,
+            exposure_layout,
+            exposure_bind_group,
+            histogram_pipeline,
+            average_pipeline,
+            tonemap_pipelines: HashMap::new(),
+            metered: false,
+        }
+    }
+
+    fn create_textures(
+        size: [u32; 2],
+        sample_count: u32,
+        state: &State,
+    ) -> (Texture, Option<Texture>) {
+        let hdr_texture = Texture::new(
+            size[0].max(1),
+            size[1].max(1),
+            HDR_FORMAT,
+            wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+            Some("HDR Texture"),
+            state,
+        );
+        let msaa_texture = if sample_count > 1 {
+            Some(Texture::new_multisampled(
+                size[0].max(1),
+                size[1].max(1),
+                HDR_FORMAT,
+                wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+                sample_count,
+                Some("HDR MSAA Texture"),
+                state,
+            ))
+        } else {
+            None
+        };
+        (hdr_texture, msaa_texture)
+    }
+
+    fn create_bind_groups(
+        hdr_texture: &Texture,
+        sampler: &wgpu::Sampler,
+        exposure_buffer: &wgpu::Buffer,
+        histogram_buffer: &wgpu::Buffer,
+        tonemap_layout: &wgpu::BindGroupLayout,
+        exposure_layout: &wgpu::BindGroupLayout,
+        state: &State,
+    ) -> (wgpu::BindGroup, wgpu::BindGroup) {
+        let tonemap_bind_group = state.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: tonemap_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&hdr_texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: exposure_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("tonemap_bind_group"),
+        });
+        let exposure_bind_group = state.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: exposure_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&hdr_texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: exposure_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: histogram_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("exposure_bind_group"),
+        });
+        (tonemap_bind_group, exposure_bind_group)
+    }
+
+    /// Follows a resize of the target or a change of the sample count.
+    pub fn resize(&mut self, size: [u32; 2], sample_count: u32, state: &State) {
+        let (hdr_texture, msaa_texture) = Tonemapper::create_textures(size, sample_count, state);
+        let (tonemap_bind_group, exposure_bind_group) = Tonemapper::create_bind_groups(
+            &hdr_texture,
+            &self.sampler,
+            &self.exposure_buffer,
+            &self.histogram_buffer,
+            &self.tonemap_layout,
+            &self.exposure_layout,
+            state,
+        );
+        self.hdr_texture = hdr_texture;
+        self.msaa_texture = msaa_texture;
+        self.tonemap_bind_group = tonemap_bind_group;
+        self.exposure_bind_group = exposure_bind_group;
+    }
+
+    /// The scene texture, which is the camera's own and so always cleared.
+    pub fn attachment(&self) -> FrameAttachment<'_> {
+        match self.msaa_texture {
+            Some(ref texture) => FrameAttachment {
+                view: &texture.view,
+                resolve_target: Some(&self.hdr_texture.view),
+                first: true,
+            },
+            None => FrameAttachment {
+                view: &self.hdr_texture.view,
+                resolve_target: None,
+                first: true,
+            },
+        }
+    }
+
+    fn prepare(&mut self, output: &PostOutput, state: &State) {
+        let key = (output.format, output.blend);
+        if self.tonemap_pipelines.contains_key(&key) {
+            return;
+        }
+
+        let device = &state.device;
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            bind_group_layouts: &[&self.tonemap_layout],
+            push_constant_ranges: &[wgpu::PushConstantRange {
+                stages: wgpu::ShaderStage::FRAGMENT,
+                range: 0..8,
+            }],
+            label: Some("tonemap_pipeline_layout"),
+        });
+        let vs_module = device.create_shader_module(wgpu::include_spirv!(concat!(
+            env!("OUT_DIR"),
+            "/camera/background.vert.spv"
+        )));
+        let fs_module = device.create_shader_module(wgpu::include_spirv!(concat!(
+            env!("OUT_DIR"),
+            "/camera/tonemap.frag.spv"
+        )));
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            layout: Some(&layout),
+            vertex_stage: wgpu::ProgrammableStageDescriptor {
+                module: &vs_module,
+                entry_point: "main",
+            },
+            fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+                module: &fs_module,
+                entry_point: "main",
+            }),
+            rasterization_state: Some(wgpu::RasterizationStateDescriptor::default()),
+            color_states: &[output.color_state()],
+            primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+            depth_stencil_state: None,
+            vertex_state: wgpu::VertexStateDescriptor {
+                index_format: wgpu::IndexFormat::Uint16,
+                vertex_buffers: &[],
+            },
+            sample_count: output.format.sample_count,
+            sample_mask: !0,
+            alpha_to_coverage_enabled: false,
+            label: Some("tonemap_pipeline"),
+        });
+        self.tonemap_pipelines.insert(key, pipeline);
+    }
+
+    /// Meters the `viewport` of the scene texture or sets the manual exposure,
+    /// for the following tonemapping pass. Automatic exposure adapts by `dt`
+    /// seconds since the previous metering.
+    pub fn update_exposure(
+        &mut self,
+        exposure: Exposure,
+        dt: f32,
+        viewport: [u32; 4],
+        encoder: &mut wgpu::CommandEncoder,
+        state: &State,
+    ) {
+        let metered = std::mem::replace(&mut self.metered, true);
+
+        let auto_exposure = match exposure {
+            Exposure::Manual(stops) => {
+                state.queue.write_buffer(
+                    &self.exposure_buffer,
+                    4,
+                    bytemuck::cast_slice(&[2.0f32.powf(stops)]),
+                );
+                self.metered = false;
+                return;
+            }
+            Exposure::Automatic(auto_exposure) => auto_exposure,
+        };
+
+        let range = auto_exposure.max_log2_luminance - auto_exposure.min_log2_luminance;
+        let constants = ExposureConstants {
+            origin: [viewport[0], viewport[1]],
+            size: [viewport[2], viewport[3]],
+            min_log2_luminance: auto_exposure.min_log2_luminance,
+            inverse_log2_range: 1.0 / range.max(1e-3),
+            // The first metering snaps to the scene.
+            adaptation: if metered {
+                smoothing_factor(dt, auto_exposure.smoothing)
+            } else {
+                1.0
+            },
+            compensation: auto_exposure.compensation,
+        };
+
+        let mut compute_pass = encoder.begin_compute_pass();
+        compute_pass.set_bind_group(0, &self.exposure_bind_group, &[]);
+        compute_pass.set_push_constants(0, bytemuck::cast_slice(&[constants]));
+        compute_pass.set_pipeline(&self.histogram_pipeline);
+        // The shader skips the pixels past the viewport of the last groups.
+        compute_pass.dispatch(
+            viewport[2] / HISTOGRAM_GROUP_SIZE + 1,
+            viewport[3] / HISTOGRAM_GROUP_SIZE + 1,
+            1,
+        );
+        compute_pass.set_pipeline(&self.average_pipeline);
+        compute_pass.dispatch(1, 1, 1);
+    }
+
+    /// Draws the exposed and tonemapped scene over the viewport of `output`
+    /// in a pass of its own.
+    pub fn render(
+        &mut self,
+        tonemapping: Tonemapping,
+        output: &PostOutput,
+        encoder: &mut wgpu::CommandEncoder,
+        state: &State,
+    ) {
+        self.prepare(output, state);
+        let encode_srgb = output
+            .format
+            .color_format
+            .map(encodes_srgb)
+            .unwrap_or(false);
+        let constants = [tonemapping as u32, encode_srgb as u32];
+
+        let mut render_pass = output.begin_render_pass(encoder);
+        render_pass.set_pipeline(&self.tonemap_pipelines[&(output.format, output.blend)]);
+        render_pass.set_bind_group(0, &self.tonemap_bind_group, &[]);
+        render_pass.set_push_constants(wgpu::ShaderStage::FRAGMENT, 0, &constants);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encodes_srgb() {
+        assert!(encodes_srgb(wgpu::TextureFormat::Bgra8Unorm));
+        assert!(!encodes_srgb(wgpu::TextureFormat::Bgra8UnormSrgb));
+        assert!(!encodes_srgb(wgpu::TextureFormat::Rgba16Float));
+    }
+}
@@ -0,0 +1,117 @@
+use cgmath::{InnerSpace, Quaternion, Rad, Rotation3, Vector3, Zero};
+use winit::event::{DeviceEvent, VirtualKeyCode, WindowEvent};
+
+use crate::core::camera_controller::{look_rotation, MAX_PITCH};
+use crate::{smoothing_factor, CameraController, ControllerInput, Transformation};
+
+/// Walks on the horizontal plane: W, A, S and D move, left shift runs and
+/// mouse motion looks around with the pitch kept within limits. Mouse look
+/// uses raw device motion, so the application should grab and hide the cursor
+/// and pass device events to `handle_device_event`.
+pub struct FirstPersonController {
+    input: ControllerInput,
+    /// Units per second.
+    pub move_speed: f32,
+    /// Factor applied to `move_speed` while left shift is held.
+    pub run_factor: f32,
+    /// Radians per unit of mouse motion.
+    pub look_speed: f32,
+    /// Lowest and highest pitch in radians, within a quarter turn.
+    pub min_pitch: f32,
+    pub max_pitch: f32,
+    /// Time constant in seconds the velocity and view direction follow the
+    /// input with, 0 to snap.
+    pub smoothing: f32,
+    position: Vector3<f32>,
+    velocity: Vector3<f32>,
+    yaw: f32,
+    pitch: f32,
+    goal_yaw: f32,
+    goal_pitch: f32,
+}
+
+impl FirstPersonController {
+    pub fn new(position: Vector3<f32>) -> FirstPersonController {
+        FirstPersonController {
+            input: ControllerInput::new(),
+            move_speed: 3.0,
+            run_factor: 2.0,
+            look_speed: 0.002,
+            min_pitch: -1.4,
+            max_pitch: 1.4,
+            smoothing: 0.05,
+            position,
+            velocity: Vector3::zero(),
+            yaw: 0.0,
+            pitch: 0.0,
+            goal_yaw: 0.0,
+            goal_pitch: 0.0,
+        }
+    }
+
+    pub fn position(&self) -> Vector3<f32> {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: Vector3<f32>) {
+        self.position = position;
+        self.velocity = Vector3::zero();
+    }
+
+    /// Yaw and pitch the view turns towards.
+    pub fn look(&self) -> (f32, f32) {
+        (self.goal_yaw, self.goal_pitch)
+    }
+
+    /// Turns towards `yaw` around the y axis and `pitch` up or down, clamped
+    /// to the pitch limits.
+    pub fn set_look(&mut self, yaw: f32, pitch: f32) {
+        let min_pitch = self.min_pitch.max(-MAX_PITCH);
+        let max_pitch = self.max_pitch.min(MAX_PITCH);
+        self.goal_yaw = yaw;
+        self.goal_pitch = min_pitch.max(pitch.min(max_pitch));
+    }
+}
+
+impl CameraController for FirstPersonController {
+    fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        self.input.handle_event(event)
+    }
+
+    fn handle_device_event(&mut self, event: &DeviceEvent) -> bool {
+        self.input.handle_device_event(event)
+    }
+
+    fn update(&mut self, dt: f32, transformation: &mut Transformation) {
+        self.input.take_cursor_delta();
+        let mouse_delta = self.input.take_mouse_delta();
+        self.set_look(
+            self.goal_yaw - mouse_delta.x * self.look_speed,
+            self.goal_pitch - mouse_delta.y * self.look_speed,
+        );
+
+        let amount = smoothing_factor(dt, self.smoothing);
+        self.yaw += (self.goal_yaw - self.yaw) * amount;
+        self.pitch += (self.goal_pitch - self.pitch) * amount;
+
+        // Only the yaw turns the walking direction.
+        let direction = Vector3::new(
+            self.input.axis(VirtualKeyCode::D, VirtualKeyCode::A),
+            0.0,
+            self.input.axis(VirtualKeyCode::S, VirtualKeyCode::W),
+        );
+        let mut goal_velocity = Vector3::zero();
+        if direction != Vector3::zero() {
+            let mut speed = self.move_speed;
+            if self.input.is_key_pressed(VirtualKeyCode::LShift) {
+                speed *= self.run_factor;
+            }
+            goal_velocity = Quaternion::from_angle_y(Rad(self.yaw)) * direction.normalize() * speed;
+        }
+        self.velocity += (goal_velocity - self.velocity) * amount;
+        self.position += self.velocity * dt;
+
+        transformation.set_translation(&self.position);
+        transformation.set_rotation(&look_rotation(self.yaw, self.pitch));
+    }
+}
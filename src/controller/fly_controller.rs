@@ -0,0 +1,105 @@
+use cgmath::{InnerSpace, Vector3, Zero};
+use winit::event::{MouseButton, VirtualKeyCode, WindowEvent};
+
+use crate::core::camera_controller::{look_rotation, MAX_PITCH};
+use crate::{smoothing_factor, CameraController, ControllerInput, Transformation};
+
+/// Moves freely along the view direction: W, A, S and D move, Q and E go down
+/// and up, left shift speeds up and dragging with the right button looks
+/// around.
+pub struct FlyController {
+    input: ControllerInput,
+    /// Units per second.
+    pub move_speed: f32,
+    /// Factor applied to `move_speed` while left shift is held.
+    pub boost: f32,
+    /// Radians per pixel dragged.
+    pub look_speed: f32,
+    /// Time constant in seconds the velocity and view direction follow the
+    /// input with, 0 to snap.
+    pub smoothing: f32,
+    position: Vector3<f32>,
+    velocity: Vector3<f32>,
+    yaw: f32,
+    pitch: f32,
+    goal_yaw: f32,
+    goal_pitch: f32,
+}
+
+impl FlyController {
+    pub fn new(position: Vector3<f32>) -> FlyController {
+        FlyController {
+            input: ControllerInput::new(),
+            move_speed: 5.0,
+            boost: 4.0,
+            look_speed: 0.003,
+            smoothing: 0.1,
+            position,
+            velocity: Vector3::zero(),
+            yaw: 0.0,
+            pitch: 0.0,
+            goal_yaw: 0.0,
+            goal_pitch: 0.0,
+        }
+    }
+
+    pub fn position(&self) -> Vector3<f32> {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: Vector3<f32>) {
+        self.position = position;
+        self.velocity = Vector3::zero();
+    }
+
+    /// Yaw and pitch the view turns towards.
+    pub fn look(&self) -> (f32, f32) {
+        (self.goal_yaw, self.goal_pitch)
+    }
+
+    /// Turns towards `yaw` around the y axis and `pitch` up or down.
+    pub fn set_look(&mut self, yaw: f32, pitch: f32) {
+        self.goal_yaw = yaw;
+        self.goal_pitch = (-MAX_PITCH).max(pitch.min(MAX_PITCH));
+    }
+}
+
+impl CameraController for FlyController {
+    fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        self.input.handle_event(event)
+    }
+
+    fn update(&mut self, dt: f32, transformation: &mut Transformation) {
+        let cursor_delta = self.input.take_cursor_delta();
+        if self.input.is_button_pressed(MouseButton::Right) {
+            self.set_look(
+                self.goal_yaw - cursor_delta.x * self.look_speed,
+                self.goal_pitch - cursor_delta.y * self.look_speed,
+            );
+        }
+
+        let amount = smoothing_factor(dt, self.smoothing);
+        self.yaw += (self.goal_yaw - self.yaw) * amount;
+        self.pitch += (self.goal_pitch - self.pitch) * amount;
+        let rotation = look_rotation(self.yaw, self.pitch);
+
+        let direction = Vector3::new(
+            self.input.axis(VirtualKeyCode::D, VirtualKeyCode::A),
+            self.input.axis(VirtualKeyCode::E, VirtualKeyCode::Q),
+            self.input.axis(VirtualKeyCode::S, VirtualKeyCode::W),
+        );
+        let mut goal_velocity = Vector3::zero();
+        if direction != Vector3::zero() {
+            let mut speed = self.move_speed;
+            if self.input.is_key_pressed(VirtualKeyCode::LShift) {
+                speed *= self.boost;
+            }
+            goal_velocity = rotation * direction.normalize() * speed;
+        }
+        self.velocity += (goal_velocity - self.velocity) * amount;
+        self.position += self.velocity * dt;
+
+        transformation.set_translation(&self.position);
+        transformation.set_rotation(&rotation);
+    }
+}
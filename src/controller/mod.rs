@@ -0,0 +1,3 @@
+pub mod first_person_controller;
+pub mod fly_controller;
+pub mod orbit_controller;
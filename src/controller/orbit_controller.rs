@@ -0,0 +1,151 @@
+use cgmath::{InnerSpace, Vector3};
+use winit::event::{MouseButton, WindowEvent};
+
+use crate::core::camera_controller::{look_rotation, MAX_PITCH};
+use crate::{smoothing_factor, CameraController, ControllerInput, Transformation};
+
+/// Circles around `target`: dragging with the left button rotates, with the
+/// right or middle button pans and scrolling zooms.
+pub struct OrbitController {
+    input: ControllerInput,
+    /// Radians per pixel dragged.
+    pub rotate_speed: f32,
+    /// Fraction of the distance per pixel dragged.
+    pub pan_speed: f32,
+    /// Factor the distance shrinks by per scrolled line.
+    pub zoom_speed: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    /// Time constant in seconds the pose follows the input with, 0 to snap.
+    pub smoothing: f32,
+    goal: OrbitPose,
+    pose: OrbitPose,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct OrbitPose {
+    target: Vector3<f32>,
+    distance: f32,
+    yaw: f32,
+    pitch: f32,
+}
+
+impl OrbitPose {
+    fn lerp(&self, goal: &OrbitPose, amount: f32) -> OrbitPose {
+        OrbitPose {
+            target: self.target + (goal.target - self.target) * amount,
+            distance: self.distance + (goal.distance - self.distance) * amount,
+            yaw: self.yaw + (goal.yaw - self.yaw) * amount,
+            pitch: self.pitch + (goal.pitch - self.pitch) * amount,
+        }
+    }
+
+    fn position(&self) -> Vector3<f32> {
+        self.target + look_rotation(self.yaw, self.pitch) * Vector3::new(0.0, 0.0, self.distance)
+    }
+}
+
+impl OrbitController {
+    pub fn new(target: Vector3<f32>, distance: f32) -> OrbitController {
+        let pose = OrbitPose {
+            target,
+            distance,
+            yaw: 0.0,
+            pitch: 0.0,
+        };
+        OrbitController {
+            input: ControllerInput::new(),
+            rotate_speed: 0.005,
+            pan_speed: 0.001,
+            zoom_speed: 1.1,
+            min_distance: 0.01,
+            max_distance: f32::INFINITY,
+            smoothing: 0.05,
+            goal: pose,
+            pose,
+        }
+    }
+
+    /// Orbits from the direction of `position` as seen from the target.
+    pub fn look_from(&mut self, position: Vector3<f32>) {
+        let offset = position - self.goal.target;
+        self.goal.distance = offset.magnitude();
+        self.goal.yaw = offset.x.atan2(offset.z);
+        self.goal.pitch = -(offset.y / self.goal.distance.max(1e-6)).asin();
+        self.goal.pitch = (-MAX_PITCH).max(self.goal.pitch.min(MAX_PITCH));
+    }
+
+    pub fn target(&self) -> Vector3<f32> {
+        self.goal.target
+    }
+
+    pub fn set_target(&mut self, target: Vector3<f32>) {
+        self.goal.target = target;
+    }
+
+    pub fn distance(&self) -> f32 {
+        self.goal.distance
+    }
+
+    pub fn set_distance(&mut self, distance: f32) {
+        self.goal.distance = distance;
+    }
+}
+
+impl CameraController for OrbitController {
+    fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        self.input.handle_event(event)
+    }
+
+    fn update(&mut self, dt: f32, transformation: &mut Transformation) {
+        let cursor_delta = self.input.take_cursor_delta();
+        let scroll = self.input.take_scroll();
+
+        if self.input.is_button_pressed(MouseButton::Left) {
+            self.goal.yaw -= cursor_delta.x * self.rotate_speed;
+            self.goal.pitch -= cursor_delta.y * self.rotate_speed;
+            self.goal.pitch = (-MAX_PITCH).max(self.goal.pitch.min(MAX_PITCH));
+        } else if self.input.is_button_pressed(MouseButton::Right)
+            || self.input.is_button_pressed(MouseButton::Middle)
+        {
+            let rotation = look_rotation(self.goal.yaw, self.goal.pitch);
+            let right = rotation * Vector3::unit_x();
+            let up = rotation * Vector3::unit_y();
+            self.goal.target += (up * cursor_delta.y - right * cursor_delta.x)
+                * self.pan_speed
+                * self.goal.distance;
+        }
+        self.goal.distance /= self.zoom_speed.powf(scroll);
+        self.goal.distance = self
+            .min_distance
+            .max(self.goal.distance.min(self.max_distance));
+
+        self.pose = self
+            .pose
+            .lerp(&self.goal, smoothing_factor(dt, self.smoothing));
+        transformation.set_translation(&self.pose.position());
+        transformation.set_rotation(&look_rotation(self.pose.yaw, self.pose.pitch));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_look_from() {
+        let mut controller = OrbitController::new(Vector3::new(1.0, 0.0, 0.0), 1.0);
+        controller.smoothing = 0.0;
+        let position = Vector3::new(3.0, 2.0, -1.0);
+        controller.look_from(position);
+        let mut transformation = Transformation::new();
+        controller.update(0.016, &mut transformation);
+        assert!((controller.pose.position() - position).magnitude() < 1e-5);
+
+        // The camera looks at the target.
+        let forward =
+            look_rotation(controller.pose.yaw, controller.pose.pitch) * -Vector3::unit_z();
+        let to_target = (controller.target() - position).normalize();
+        assert!((forward - to_target).magnitude() < 1e-5);
+    }
+}
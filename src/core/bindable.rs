@@ -1,3 +1,8 @@
 pub trait Bindable {
     fn bind_group(&self) -> Option<&wgpu::BindGroup>;
 }
+
+/// Bind group slots shared by every render pipeline built by `Mesh`.
+pub const LIGHT_BIND_GROUP: u32 = 0;
+pub const GEOMETRY_BIND_GROUP: u32 = 1;
+pub const MATERIAL_BIND_GROUP: u32 = 2;
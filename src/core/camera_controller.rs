@@ -0,0 +1,191 @@
+use std::collections::HashSet;
+
+use cgmath::{Quaternion, Rad, Rotation3, Vector2};
+use winit::event::{
+    DeviceEvent, ElementState, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode,
+    WindowEvent,
+};
+
+use crate::Transformation;
+
+/// Turns window events into the pose of a camera. Feed it every event, then
+/// call `update` once per frame with the camera's transformation, e.g.
+/// `controller.update(dt, camera.borrow_mut().transformation_mut())`.
+pub trait CameraController {
+    /// Returns whether the event was used.
+    fn handle_event(&mut self, event: &WindowEvent) -> bool;
+    /// Raw mouse motion, which keeps coming when the cursor is grabbed.
+    fn handle_device_event(&mut self, _event: &DeviceEvent) -> bool {
+        false
+    }
+    /// Advances by `dt` seconds and writes the pose into `transformation`.
+    fn update(&mut self, dt: f32, transformation: &mut Transformation);
+}
+
+/// Keys and buttons held and mouse motion accumulated between updates.
+#[derive(Debug)]
+pub struct ControllerInput {
+    keys: HashSet<VirtualKeyCode>,
+    buttons: HashSet<MouseButton>,
+    cursor: Option<Vector2<f32>>,
+    cursor_delta: Vector2<f32>,
+    mouse_delta: Vector2<f32>,
+    scroll: f32,
+}
+
+/// Pixels of a `PixelDelta` scroll counting as one line.
+const PIXELS_PER_LINE: f32 = 40.0;
+
+impl ControllerInput {
+    pub fn new() -> ControllerInput {
+        ControllerInput {
+            keys: HashSet::new(),
+            buttons: HashSet::new(),
+            cursor: None,
+            cursor_delta: Vector2::new(0.0, 0.0),
+            mouse_delta: Vector2::new(0.0, 0.0),
+            scroll: 0.0,
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => {
+                match state {
+                    ElementState::Pressed => self.keys.insert(*key),
+                    ElementState::Released => self.keys.remove(key),
+                };
+                true
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                match state {
+                    ElementState::Pressed => self.buttons.insert(*button),
+                    ElementState::Released => self.buttons.remove(button),
+                };
+                true
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = Vector2::new(position.x as f32, position.y as f32);
+                if let Some(cursor) = self.cursor {
+                    self.cursor_delta += position - cursor;
+                }
+                self.cursor = Some(position);
+                true
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor = None;
+                false
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.scroll += match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / PIXELS_PER_LINE,
+                };
+                true
+            }
+            // Releases are lost while unfocused.
+            WindowEvent::Focused(false) => {
+                self.keys.clear();
+                self.buttons.clear();
+                false
+            }
+            _ => false,
+        }
+    }
+
+    pub fn handle_device_event(&mut self, event: &DeviceEvent) -> bool {
+        match event {
+            DeviceEvent::MouseMotion { delta } => {
+                self.mouse_delta += Vector2::new(delta.0 as f32, delta.1 as f32);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_key_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.keys.contains(&key)
+    }
+
+    pub fn is_button_pressed(&self, button: MouseButton) -> bool {
+        self.buttons.contains(&button)
+    }
+
+    /// 1, -1 or 0 depending on which of two opposing keys is held.
+    pub fn axis(&self, positive: VirtualKeyCode, negative: VirtualKeyCode) -> f32 {
+        self.is_key_pressed(positive) as i32 as f32 - self.is_key_pressed(negative) as i32 as f32
+    }
+
+    /// Cursor movement in pixels since the last call.
+    pub fn take_cursor_delta(&mut self) -> Vector2<f32> {
+        std::mem::replace(&mut self.cursor_delta, Vector2::new(0.0, 0.0))
+    }
+
+    /// Raw mouse movement since the last call.
+    pub fn take_mouse_delta(&mut self) -> Vector2<f32> {
+        std::mem::replace(&mut self.mouse_delta, Vector2::new(0.0, 0.0))
+    }
+
+    /// Scrolled lines since the last call, positive away from the user.
+    pub fn take_scroll(&mut self) -> f32 {
+        std::mem::replace(&mut self.scroll, 0.0)
+    }
+}
+
+impl Default for ControllerInput {
+    fn default() -> Self {
+        ControllerInput::new()
+    }
+}
+
+/// Fraction of the remaining distance to its goal a smoothed value covers in
+/// `dt` seconds, for a `smoothing` time constant in seconds; 0 snaps.
+pub fn smoothing_factor(dt: f32, smoothing: f32) -> f32 {
+    if smoothing <= 0.0 {
+        1.0
+    } else {
+        1.0 - (-dt / smoothing).exp()
+    }
+}
+
+/// Keeps cameras from flipping over when looking straight up or down.
+pub(crate) const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+/// Rotation turning by `yaw` around the world's y axis and then by `pitch`
+/// around the camera's x axis.
+pub(crate) fn look_rotation(yaw: f32, pitch: f32) -> Quaternion<f32> {
+    Quaternion::from_angle_y(Rad(yaw)) * Quaternion::from_angle_x(Rad(pitch))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::{InnerSpace, Vector3};
+
+    #[test]
+    fn test_smoothing_factor() {
+        assert_eq!(smoothing_factor(0.016, 0.0), 1.0);
+        // Two half steps cover as much as one full step.
+        let full = smoothing_factor(0.1, 0.2);
+        let half = smoothing_factor(0.05, 0.2);
+        assert!((full - (1.0 - (1.0 - half) * (1.0 - half))).abs() < 1e-6);
+        assert!(full > 0.0 && full < 1.0);
+    }
+
+    #[test]
+    fn test_look_rotation() {
+        let forward = look_rotation(std::f32::consts::FRAC_PI_2, 0.0) * -Vector3::unit_z();
+        assert!((forward - -Vector3::unit_x()).magnitude() < 1e-6);
+        let up = look_rotation(0.0, std::f32::consts::FRAC_PI_2) * -Vector3::unit_z();
+        assert!((up - Vector3::unit_y()).magnitude() < 1e-6);
+    }
+}
@@ -44,13 +44,15 @@ pub trait Component {
         }
     }
 
+    /// Brings the global transformation up to date before `update`, so
+    /// components see where they are this frame.
     fn update_recursive(&mut self, parent_transformation: &Matrix4<f32>) {
-        self.update();
         {
             let transformation = self.transformation_mut();
             transformation.update_local(false);
             transformation.update_global(parent_transformation);
         }
+        self.update();
         let transformation = self.transformation();
         for child in self.children().iter() {
             child
@@ -0,0 +1,678 @@
+use std::f32::consts::PI;
+use std::num::NonZeroU32;
+use std::path::Path;
+
+use cgmath::{InnerSpace, Vector2, Vector3};
+
+use crate::State;
+
+/// Mirrors the std140 `EnvironmentParameters` uniform bound at set 0, binding 6.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub(crate) struct EnvironmentUniform {
+    pub intensity: f32,
+    pub specular_mip_levels: f32,
+    pub _padding: [u32; 2],
+}
+
+unsafe impl bytemuck::Zeroable for EnvironmentUniform {}
+unsafe impl bytemuck::Pod for EnvironmentUniform {}
+
+/// Image based lighting from the surroundings of a scene: an irradiance cube
+/// map for diffuse light, a cube map prefiltered for increasing roughness in
+/// its mip levels for specular light, and the BRDF lookup table of the split
+/// sum approximation. Lit shaders sample them at set 0, bindings 7 to 10.
+pub struct Environment {
+    irradiance_map: wgpu::Texture,
+    irradiance_view: wgpu::TextureView,
+    specular_map: wgpu::Texture,
+    specular_view: wgpu::TextureView,
+    brdf_lut: wgpu::Texture,
+    brdf_lut_view: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+    specular_mip_levels: u32,
+    intensity: f32,
+}
+
+impl Environment {
+    pub const IRRADIANCE_SIZE: u32 = 32;
+    pub const SPECULAR_SIZE: u32 = 128;
+    pub const SPECULAR_MIP_LEVELS: u32 = 6;
+    pub const BRDF_LUT_SIZE: u32 = 64;
+    const SPECULAR_SAMPLES: u32 = 64;
+    const BRDF_SAMPLES: u32 = 256;
+
+    /// Loads an equirectangular Radiance HDR image, see `from_equirectangular`.
+    pub fn load<P: AsRef<Path>>(path: P, state: &State) -> image::ImageResult<Environment> {
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        let decoder = image::codecs::hdr::HdrDecoder::new(file)?;
+        let metadata = decoder.metadata();
+        let pixels = decoder.read_image_hdr()?;
+        let image = image::ImageBuffer::from_fn(metadata.width, metadata.height, |x, y| {
+            pixels[(y * metadata.width + x) as usize]
+        });
+        Ok(Environment::from_equirectangular(&image, state))
+    }
+
+    /// Generates the maps from linear radiance in an equirectangular
+    /// projection, +y at the top and -z at the center of the image.
+    pub fn from_equirectangular(
+        image: &image::ImageBuffer<image::Rgb<f32>, Vec<f32>>,
+        state: &State,
+    ) -> Environment {
+        let (width, height) = image.dimensions();
+        let map = EquirectangularMap::new(
+            width as usize,
+            height as usize,
+            image
+                .pixels()
+                .map(|pixel| Vector3::new(pixel[0], pixel[1], pixel[2]))
+                .collect(),
+        );
+        let pyramid = map.pyramid();
+
+        let environment = Environment::with_device(
+            Environment::IRRADIANCE_SIZE,
+            Environment::SPECULAR_SIZE,
+            Environment::SPECULAR_MIP_LEVELS,
+            Environment::BRDF_LUT_SIZE,
+            &state.device,
+        );
+
+        let coefficients = irradiance_coefficients(&pyramid[pyramid.len().min(3) - 1]);
+        let irradiance = cube_faces(Environment::IRRADIANCE_SIZE, |direction| {
+            irradiance(&coefficients, direction)
+        });
+        write_cube(&environment.irradiance_map, 0, &irradiance, state);
+
+        for mip_level in 0..Environment::SPECULAR_MIP_LEVELS {
+            let size = Environment::SPECULAR_SIZE >> mip_level;
+            let roughness = mip_level as f32 / (Environment::SPECULAR_MIP_LEVELS - 1) as f32;
+            let faces = cube_faces(size, |direction| {
+                prefilter(
+                    &pyramid,
+                    direction,
+                    roughness,
+                    size,
+                    Environment::SPECULAR_SAMPLES,
+                )
+            });
+            write_cube(&environment.specular_map, mip_level, &faces, state);
+        }
+
+        let brdf_size = Environment::BRDF_LUT_SIZE;
+        let mut brdf_lut = Vec::with_capacity((brdf_size * brdf_size * 2) as usize);
+        for y in 0..brdf_size {
+            for x in 0..brdf_size {
+                let n_dot_v = (x as f32 + 0.5) / brdf_size as f32;
+                let roughness = (y as f32 + 0.5) / brdf_size as f32;
+                let brdf = integrate_brdf(n_dot_v, roughness, Environment::BRDF_SAMPLES);
+                brdf_lut.push(f16_bits(brdf.x));
+                brdf_lut.push(f16_bits(brdf.y));
+            }
+        }
+        state.queue.write_texture(
+            wgpu::TextureCopyView {
+                texture: &environment.brdf_lut,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            bytemuck::cast_slice(&brdf_lut),
+            wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row: 4 * brdf_size,
+                rows_per_image: brdf_size,
+            },
+            wgpu::Extent3d {
+                width: brdf_size,
+                height: brdf_size,
+                depth: 1,
+            },
+        );
+
+        Environment {
+            intensity: 1.0,
+            ..environment
+        }
+    }
+
+    /// Uninitialized maps of the given sizes with an intensity of 0. A 1 texel
+    /// one is bound in place of a missing environment.
+    pub(crate) fn with_device(
+        irradiance_size: u32,
+        specular_size: u32,
+        specular_mip_levels: u32,
+        brdf_lut_size: u32,
+        device: &wgpu::Device,
+    ) -> Environment {
+        let create_texture = |label, size, mip_level_count, layers, format| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width: size,
+                    height: size,
+                    depth: layers,
+                },
+                mip_level_count,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+            })
+        };
+        let cube_view = |texture: &wgpu::Texture| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                dimension: Some(wgpu::TextureViewDimension::Cube),
+                array_layer_count: NonZeroU32::new(6),
+                ..Default::default()
+            })
+        };
+
+        let irradiance_map = create_texture(
+            "Irradiance Map",
+            irradiance_size,
+            1,
+            6,
+            wgpu::TextureFormat::Rgba16Float,
+        );
+        let irradiance_view = cube_view(&irradiance_map);
+        let specular_map = create_texture(
+            "Specular Map",
+            specular_size,
+            specular_mip_levels,
+            6,
+            wgpu::TextureFormat::Rgba16Float,
+        );
+        let specular_view = cube_view(&specular_map);
+        let brdf_lut = create_texture(
+            "BRDF LUT",
+            brdf_lut_size,
+            1,
+            1,
+            wgpu::TextureFormat::Rg16Float,
+        );
+        let brdf_lut_view = brdf_lut.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("environment_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Environment {
+            irradiance_map,
+            irradiance_view,
+            specular_map,
+            specular_view,
+            brdf_lut,
+            brdf_lut_view,
+            sampler,
+            specular_mip_levels,
+            intensity: 0.0,
+        }
+    }
+
+    pub fn intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    /// Scales both the diffuse and the specular environment light.
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.intensity = intensity;
+    }
+
+    pub fn specular_mip_levels(&self) -> u32 {
+        self.specular_mip_levels
+    }
+
+    pub fn irradiance_view(&self) -> &wgpu::TextureView {
+        &self.irradiance_view
+    }
+
+    pub fn specular_view(&self) -> &wgpu::TextureView {
+        &self.specular_view
+    }
+
+    pub fn brdf_lut_view(&self) -> &wgpu::TextureView {
+        &self.brdf_lut_view
+    }
+
+    pub fn sampler(&self) -> &wgpu::Sampler {
+        &self.sampler
+    }
+
+    pub(crate) fn uniform(&self) -> EnvironmentUniform {
+        EnvironmentUniform {
+            intensity: self.intensity,
+            specular_mip_levels: self.specular_mip_levels as f32,
+            _padding: [0; 2],
+        }
+    }
+}
+
+/// Radiance in an equirectangular projection, sampled bilinearly.
+struct EquirectangularMap {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vector3<f32>>,
+}
+
+impl EquirectangularMap {
+    fn new(width: usize, height: usize, pixels: Vec<Vector3<f32>>) -> EquirectangularMap {
+        EquirectangularMap {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> Vector3<f32> {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Average solid angle of a pixel.
+    fn texel_solid_angle(&self) -> f32 {
+        4.0 * PI / (self.width * self.height) as f32
+    }
+
+    /// Half the size in both directions, averaging 2x2 pixels.
+    fn downsample(&self) -> EquirectangularMap {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let x0 = (2 * x).min(self.width - 1);
+                let x1 = (2 * x + 1).min(self.width - 1);
+                let y0 = (2 * y).min(self.height - 1);
+                let y1 = (2 * y + 1).min(self.height - 1);
+                pixels.push(
+                    (self.pixel(x0, y0)
+                        + self.pixel(x1, y0)
+                        + self.pixel(x0, y1)
+                        + self.pixel(x1, y1))
+                        / 4.0,
+                );
+            }
+        }
+        EquirectangularMap::new(width, height, pixels)
+    }
+
+    /// The map followed by ever smaller downsampled versions, down to a few pixels.
+    fn pyramid(self) -> Vec<EquirectangularMap> {
+        let mut pyramid = vec![self];
+        while pyramid.last().unwrap().height > 4 {
+            let next = pyramid.last().unwrap().downsample();
+            pyramid.push(next);
+        }
+        pyramid
+    }
+
+    fn sample(&self, direction: Vector3<f32>) -> Vector3<f32> {
+        let uv = equirectangular_uv(direction);
+        let x = uv.x * self.width as f32 - 0.5;
+        let y = (uv.y * self.height as f32 - 0.5)
+            .max(0.0)
+            .min((self.height - 1) as f32);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let wrap = |x: f32| (x as isize).rem_euclid(self.width as isize) as usize;
+        let (x0, x1) = (wrap(x0), wrap(x0 + 1.0));
+        let y1 = (y0 as usize + 1).min(self.height - 1);
+        let y0 = y0 as usize;
+        let top = self.pixel(x0, y0) * (1.0 - tx) + self.pixel(x1, y0) * tx;
+        let bottom = self.pixel(x0, y1) * (1.0 - tx) + self.pixel(x1, y1) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+}
+
+/// Blends between the levels of a pyramid, level 0 being the full size map.
+fn sample_pyramid(
+    pyramid: &[EquirectangularMap],
+    direction: Vector3<f32>,
+    level: f32,
+) -> Vector3<f32> {
+    let level = level.max(0.0).min((pyramid.len() - 1) as f32);
+    let lower = level.floor() as usize;
+    let upper = (lower + 1).min(pyramid.len() - 1);
+    let t = level - lower as f32;
+    pyramid[lower].sample(direction) * (1.0 - t) + pyramid[upper].sample(direction) * t
+}
+
+/// Texture coordinates of a direction, u around the y axis starting behind
+/// the viewer and v from +y down to -y.
+fn equirectangular_uv(direction: Vector3<f32>) -> Vector2<f32> {
+    let direction = direction.normalize();
+    Vector2::new(
+        0.5 + direction.x.atan2(-direction.z) / (2.0 * PI),
+        (-1.0f32).max(direction.y.min(1.0)).acos() / PI,
+    )
+}
+
+/// Direction through the center of a texel of a cube map face, with the
+/// faces ordered +x, -x, +y, -y, +z, -z and texels from the top left like
+/// GPUs sample cube maps.
+fn cube_face_direction(face: usize, x: u32, y: u32, size: u32) -> Vector3<f32> {
+    let s = 2.0 * (x as f32 + 0.5) / size as f32 - 1.0;
+    let t = 2.0 * (y as f32 + 0.5) / size as f32 - 1.0;
+    let direction = match face {
+        0 => Vector3::new(1.0, -t, -s),
+        1 => Vector3::new(-1.0, -t, s),
+        2 => Vector3::new(s, 1.0, t),
+        3 => Vector3::new(s, -1.0, -t),
+        4 => Vector3::new(s, -t, 1.0),
+        _ => Vector3::new(-s, -t, -1.0),
+    };
+    direction.normalize()
+}
+
+/// Evaluates `f` for every texel of the six faces of a cube map.
+fn cube_faces<F: Fn(Vector3<f32>) -> Vector3<f32>>(size: u32, f: F) -> Vec<Vec<Vector3<f32>>> {
+    (0..6)
+        .map(|face| {
+            (0..size * size)
+                .map(|i| f(cube_face_direction(face, i % size, i / size, size)))
+                .collect()
+        })
+        .collect()
+}
+
+fn write_cube(texture: &wgpu::Texture, mip_level: u32, faces: &[Vec<Vector3<f32>>], state: &State) {
+    let size = (faces[0].len() as f32).sqrt() as u32;
+    for (face, texels) in faces.iter().enumerate() {
+        let data: Vec<u16> = texels
+            .iter()
+            .flat_map(|texel| {
+                vec![
+                    f16_bits(texel.x),
+                    f16_bits(texel.y),
+                    f16_bits(texel.z),
+                    f16_bits(1.0),
+                ]
+            })
+            .collect();
+        state.queue.write_texture(
+            wgpu::TextureCopyView {
+                texture,
+                mip_level,
+                origin: wgpu::Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: face as u32,
+                },
+            },
+            bytemuck::cast_slice(&data),
+            wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row: 8 * size,
+                rows_per_image: size,
+            },
+            wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth: 1,
+            },
+        );
+    }
+}
+
+/// The first nine real spherical harmonics.
+fn sh_basis(d: Vector3<f32>) -> [f32; 9] {
+    [
+        0.282_095,
+        0.488_603 * d.y,
+        0.488_603 * d.z,
+        0.488_603 * d.x,
+        1.092_548 * d.x * d.y,
+        1.092_548 * d.y * d.z,
+        0.315_392 * (3.0 * d.z * d.z - 1.0),
+        1.092_548 * d.x * d.z,
+        0.546_274 * (d.x * d.x - d.y * d.y),
+    ]
+}
+
+/// Projects the radiance of the map on the first nine spherical harmonics,
+/// which hold nearly all of the irradiance it gives.
+fn irradiance_coefficients(map: &EquirectangularMap) -> [Vector3<f32>; 9] {
+    let mut coefficients = [Vector3::new(0.0, 0.0, 0.0); 9];
+    for y in 0..map.height {
+        let theta = (y as f32 + 0.5) / map.height as f32 * PI;
+        let solid_angle = (2.0 * PI / map.width as f32) * (PI / map.height as f32) * theta.sin();
+        for x in 0..map.width {
+            let phi = ((x as f32 + 0.5) / map.width as f32 - 0.5) * 2.0 * PI;
+            // The inverse of `equirectangular_uv`.
+            let direction = Vector3::new(
+                theta.sin() * phi.sin(),
+                theta.cos(),
+                -theta.sin() * phi.cos(),
+            );
+            let radiance = map.pixel(x, y) * solid_angle;
+            for (coefficient, basis) in coefficients.iter_mut().zip(sh_basis(direction).iter()) {
+                *coefficient += radiance * *basis;
+            }
+        }
+    }
+    coefficients
+}
+
+/// Irradiance around `normal` divided by pi, so that multiplying it by the
+/// albedo gives the diffusely reflected radiance.
+fn irradiance(coefficients: &[Vector3<f32>; 9], normal: Vector3<f32>) -> Vector3<f32> {
+    // Convolution of each band with the clamped cosine, over pi.
+    const BANDS: [f32; 9] = [
+        1.0,
+        2.0 / 3.0,
+        2.0 / 3.0,
+        2.0 / 3.0,
+        0.25,
+        0.25,
+        0.25,
+        0.25,
+        0.25,
+    ];
+    let mut result = Vector3::new(0.0, 0.0, 0.0);
+    for ((coefficient, basis), band) in coefficients
+        .iter()
+        .zip(sh_basis(normal).iter())
+        .zip(BANDS.iter())
+    {
+        result += coefficient * *basis * *band;
+    }
+    Vector3::new(result.x.max(0.0), result.y.max(0.0), result.z.max(0.0))
+}
+
+/// Low discrepancy point `i` of `count` in the unit square.
+fn hammersley(i: u32, count: u32) -> Vector2<f32> {
+    Vector2::new(
+        i as f32 / count as f32,
+        i.reverse_bits() as f32 * 2.328_306_4e-10,
+    )
+}
+
+/// Half vector around `normal` distributed like the GGX normal distribution.
+fn importance_sample_ggx(xi: Vector2<f32>, normal: Vector3<f32>, roughness: f32) -> Vector3<f32> {
+    let a = roughness * roughness;
+    let phi = 2.0 * PI * xi.x;
+    let cos_theta = ((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y)).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+
+    let up = if normal.z.abs() < 0.999 {
+        Vector3::unit_z()
+    } else {
+        Vector3::unit_x()
+    };
+    let tangent = up.cross(normal).normalize();
+    let bitangent = normal.cross(tangent);
+    (tangent * (phi.cos() * sin_theta) + bitangent * (phi.sin() * sin_theta) + normal * cos_theta)
+        .normalize()
+}
+
+/// Radiance reflected around `direction` by a GGX lobe of the given
+/// roughness, assuming the view is along the normal. Samples are read from
+/// the pyramid level whose pixels cover about the solid angle of a sample, so
+/// that few samples suffice. `size` is the cube map face size the result is
+/// stored at.
+fn prefilter(
+    pyramid: &[EquirectangularMap],
+    direction: Vector3<f32>,
+    roughness: f32,
+    size: u32,
+    samples: u32,
+) -> Vector3<f32> {
+    let texel_solid_angle = pyramid[0].texel_solid_angle();
+    if roughness <= 0.0 {
+        let cube_texel_solid_angle = 4.0 * PI / (6 * size * size) as f32;
+        return sample_pyramid(
+            pyramid,
+            direction,
+            0.5 * (cube_texel_solid_angle / texel_solid_angle).log2(),
+        );
+    }
+
+    let a = roughness * roughness;
+    let mut result = Vector3::new(0.0, 0.0, 0.0);
+    let mut total_weight = 0.0;
+    for i in 0..samples {
+        let h = importance_sample_ggx(hammersley(i, samples), direction, roughness);
+        let n_dot_h = direction.dot(h).max(0.0);
+        let l = h * 2.0 * n_dot_h - direction;
+        let n_dot_l = direction.dot(l);
+        if n_dot_l > 0.0 {
+            // With the view along the normal the pdf of `l` is D / 4.
+            let d = n_dot_h * n_dot_h * (a * a - 1.0) + 1.0;
+            let pdf = a * a / (PI * d * d) / 4.0;
+            let sample_solid_angle = 1.0 / (samples as f32 * pdf + 1e-4);
+            let level = 0.5 * (sample_solid_angle / texel_solid_angle).log2() + 1.0;
+            result += sample_pyramid(pyramid, l, level) * n_dot_l;
+            total_weight += n_dot_l;
+        }
+    }
+    if total_weight > 0.0 {
+        result / total_weight
+    } else {
+        result
+    }
+}
+
+/// Scale and bias applied to F0 by the specular environment term of the
+/// split sum approximation.
+fn integrate_brdf(n_dot_v: f32, roughness: f32, samples: u32) -> Vector2<f32> {
+    let v = Vector3::new((1.0 - n_dot_v * n_dot_v).sqrt(), 0.0, n_dot_v);
+    let normal = Vector3::unit_z();
+    let k = roughness * roughness / 2.0;
+    let geometry = |n_dot: f32| n_dot / (n_dot * (1.0 - k) + k);
+
+    let mut result = Vector2::new(0.0, 0.0);
+    for i in 0..samples {
+        let h = importance_sample_ggx(hammersley(i, samples), normal, roughness);
+        let v_dot_h = v.dot(h).max(0.0);
+        let l = h * 2.0 * v_dot_h - v;
+        let n_dot_l = l.z;
+        if n_dot_l > 0.0 {
+            let n_dot_h = h.z.max(0.0);
+            let g = geometry(n_dot_v) * geometry(n_dot_l);
+            let visibility = g * v_dot_h / (n_dot_h * n_dot_v).max(1e-4);
+            let fresnel = (1.0 - v_dot_h).powi(5);
+            result += Vector2::new((1.0 - fresnel) * visibility, fresnel * visibility);
+        }
+    }
+    result / samples as f32
+}
+
+/// Converts to a half float, rounding to nearest. Values too small for a
+/// normal half become 0 and values too large the largest finite half.
+fn f16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent <= 0 {
+        return sign;
+    }
+    if exponent >= 31 {
+        return sign | 0x7bff;
+    }
+    // A carry of the rounding into the exponent still gives the right value.
+    let half = ((exponent as u32) << 10 | mantissa >> 13) + ((mantissa >> 12) & 1);
+    sign | half.min(0x7bff) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_f16_bits() {
+        assert_eq!(f16_bits(0.0), 0);
+        assert_eq!(f16_bits(1.0), 0x3c00);
+        assert_eq!(f16_bits(0.5), 0x3800);
+        assert_eq!(f16_bits(-2.0), 0xc000);
+        assert_eq!(f16_bits(1e6), 0x7bff);
+        assert_eq!(f16_bits(1e-10), 0);
+    }
+
+    #[test]
+    fn test_cube_face_direction() {
+        let center = |face| cube_face_direction(face, 0, 0, 1);
+        assert_eq!(center(0), Vector3::unit_x());
+        assert_eq!(center(1), -Vector3::unit_x());
+        assert_eq!(center(2), Vector3::unit_y());
+        assert_eq!(center(3), -Vector3::unit_y());
+        assert_eq!(center(4), Vector3::unit_z());
+        assert_eq!(center(5), -Vector3::unit_z());
+        // The top row of the side faces looks up.
+        assert!(cube_face_direction(4, 1, 0, 4).y > 0.0);
+    }
+
+    #[test]
+    fn test_irradiance_of_uniform_environment() {
+        let map = EquirectangularMap::new(64, 32, vec![Vector3::new(1.0, 0.5, 0.25); 64 * 32]);
+        let coefficients = irradiance_coefficients(&map);
+        for &normal in &[Vector3::unit_x(), Vector3::unit_y(), -Vector3::unit_z()] {
+            let result = irradiance(&coefficients, normal);
+            assert!((result - Vector3::new(1.0, 0.5, 0.25)).magnitude() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn test_irradiance_of_sky() {
+        // Light only from above reaches surfaces facing up, not those facing down.
+        let pixels = (0..32)
+            .flat_map(|y| vec![if y < 16 { 1.0 } else { 0.0 }; 64])
+            .map(|value| Vector3::new(value, value, value))
+            .collect();
+        let coefficients = irradiance_coefficients(&EquirectangularMap::new(64, 32, pixels));
+        let up = irradiance(&coefficients, Vector3::unit_y()).x;
+        let down = irradiance(&coefficients, -Vector3::unit_y()).x;
+        assert!(up > 0.9 && down < 0.1);
+    }
+
+    #[test]
+    fn test_equirectangular_sample() {
+        let mut pixels = vec![Vector3::new(0.0, 0.0, 0.0); 8 * 4];
+        pixels[8 + 4] = Vector3::new(1.0, 1.0, 1.0);
+        let map = EquirectangularMap::new(8, 4, pixels);
+        // The center of the image looks down -z.
+        let direction = Vector3::new(0.0, 0.382_683_43, -0.923_879_5);
+        assert!(map.sample(direction).x > 0.2);
+        assert_eq!(map.sample(Vector3::unit_z()).x, 0.0);
+    }
+
+    #[test]
+    fn test_integrate_brdf() {
+        // A smooth surface seen head on reflects F0 unchanged.
+        let brdf = integrate_brdf(1.0, 0.01, 64);
+        assert!((brdf.x + brdf.y - 1.0).abs() < 0.02);
+        assert!(brdf.y < 0.01);
+        // Rough surfaces lose light to masking.
+        let total = |brdf: Vector2<f32>| brdf.x + brdf.y;
+        assert!(total(integrate_brdf(0.5, 1.0, 256)) < total(integrate_brdf(0.5, 0.2, 256)));
+        // Fresnel reflection grows at grazing angles.
+        assert!(integrate_brdf(0.1, 0.2, 256).y > integrate_brdf(0.9, 0.2, 256).y);
+    }
+}
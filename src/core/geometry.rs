@@ -1,3 +1,7 @@
+use std::path::Path;
+
+use cgmath::Vector3;
+
 use crate::Bindable;
 
 pub struct GeometryUniformBase {
@@ -14,4 +18,14 @@ pub trait Geometry: Bindable {
     fn vertex_buffer(&self) -> Vec<wgpu::VertexBufferDescriptor>;
     fn draw_geometry<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>);
     fn geometry_bind_group_layout(&self) -> Option<&wgpu::BindGroupLayout>;
+    /// GLSL source of the vertex stage, watched by `ShaderHotReload`. Relative
+    /// paths name shaders of this crate, relative to its `src` directory.
+    fn vertex_shader_path(&self) -> Option<&Path> {
+        None
+    }
+    /// Triangles in model space, used by the barycentric wireframe fallback
+    /// when the device cannot rasterize lines.
+    fn triangles(&self) -> Vec<[Vector3<f32>; 3]> {
+        Vec::new()
+    }
 }
@@ -0,0 +1,177 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{
+    load_shader, reflect_bindings, ReflectedBinding, ReflectedBindingType, Scene, ShaderError,
+    State,
+};
+
+/// Development helper that rebuilds render pipelines when the GLSL sources of
+/// their geometry or material change on disk. Call `update` once per frame.
+///
+/// A shader that fails to compile, or whose bindings do not fit the layout of
+/// the mesh's pipeline, is returned as an error and the mesh keeps its
+/// previous pipeline, so a typo does not bring the application down. Changed
+/// vertex inputs or fragment outputs are not checked and still fail in wgpu.
+pub struct ShaderHotReload {
+    shader_dir: Option<PathBuf>,
+    modified: HashMap<PathBuf, SystemTime>,
+}
+
+impl ShaderHotReload {
+    /// Watches the shaders loaded from files, like those of a
+    /// `ShaderMaterial::from_file`.
+    pub fn new() -> ShaderHotReload {
+        ShaderHotReload {
+            shader_dir: None,
+            modified: HashMap::new(),
+        }
+    }
+
+    /// Also watches the built-in shaders of this crate, looking them up in
+    /// `shader_dir`, the `src` directory of a checkout of it.
+    pub fn with_shader_dir(shader_dir: impl Into<PathBuf>) -> ShaderHotReload {
+        ShaderHotReload {
+            shader_dir: Some(shader_dir.into()),
+            modified: HashMap::new(),
+        }
+    }
+
+    /// Where a shader path of a geometry or material is found on disk, if it
+    /// is watched at all.
+    fn resolve(&self, path: &Path) -> Option<PathBuf> {
+        if path.is_absolute() {
+            Some(path.to_path_buf())
+        } else {
+            self.shader_dir.as_ref().map(|dir| dir.join(path))
+        }
+    }
+
+    /// Returns whether the file changed since the last call. Files seen for the
+    /// first time are only recorded.
+    fn has_changed(&mut self, path: &Path) -> bool {
+        let modified = match fs::metadata(path).and_then(|metadata| metadata.modified()) {
+            Ok(modified) => modified,
+            Err(_) => return false,
+        };
+        match self.modified.insert(path.to_path_buf(), modified) {
+            Some(previous) => previous != modified,
+            None => false,
+        }
+    }
+
+    /// Reloads the shaders changed since the last call. Every mesh is
+    /// updated even if some fail; the first error is returned.
+    pub fn update(&mut self, scene: &Scene, state: &State) -> Result<(), ShaderError> {
+        let mut first_error = None;
+        let mut compiled: HashMap<PathBuf, Option<Vec<u32>>> = HashMap::new();
+        let mut compile = |path: Option<&Path>,
+                           shader_kind,
+                           this: &mut ShaderHotReload,
+                           first_error: &mut Option<ShaderError>| {
+            let path = this.resolve(path?)?;
+            if !compiled.contains_key(&path) {
+                let spirv = if this.has_changed(&path) {
+                    match load_shader(&path, shader_kind) {
+                        Ok(spirv) => Some(spirv),
+                        Err(error) => {
+                            first_error.get_or_insert(error);
+                            None
+                        }
+                    }
+                } else {
+                    None
+                };
+                compiled.insert(path.clone(), spirv);
+            }
+            compiled[&path].clone()
+        };
+
+        for mesh in scene.meshes.iter() {
+            let mut mesh = mesh.borrow_mut();
+            let vertex_spirv = compile(
+                mesh.geometry.vertex_shader_path(),
+                shaderc::ShaderKind::Vertex,
+                self,
+                &mut first_error,
+            );
+            let fragment_spirv = compile(
+                mesh.material.fragment_shader_path(),
+                shaderc::ShaderKind::Fragment,
+                self,
+                &mut first_error,
+            );
+            if vertex_spirv.is_some() || fragment_spirv.is_some() {
+                if let Err(error) = mesh.reload_shaders(vertex_spirv, fragment_spirv, state) {
+                    first_error.get_or_insert(error);
+                }
+            }
+        }
+
+        match first_error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Default for ShaderHotReload {
+    fn default() -> Self {
+        ShaderHotReload::new()
+    }
+}
+
+/// Checks a reloaded shader against the one the mesh's pipelines were built
+/// from: every binding it declares has to exist in the current shader with a
+/// compatible type, so the pipeline layout still fits.
+pub(crate) fn check_compatible(
+    current: &wgpu::ShaderModuleSource,
+    reloaded: &[u32],
+) -> Result<(), ShaderError> {
+    let current = match current {
+        wgpu::ShaderModuleSource::SpirV(words) => reflect_bindings(words)?,
+        _ => return Ok(()),
+    };
+    for binding in reflect_bindings(reloaded)? {
+        if !current
+            .iter()
+            .any(|current| is_compatible(current, &binding))
+        {
+            return Err(ShaderError::UnsupportedBinding {
+                set: binding.set,
+                binding: binding.binding,
+                reason: "does not match the layout of the current pipeline".to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn is_compatible(current: &ReflectedBinding, reloaded: &ReflectedBinding) -> bool {
+    if (current.set, current.binding) != (reloaded.set, reloaded.binding) {
+        return false;
+    }
+    match (&current.ty, &reloaded.ty) {
+        (
+            ReflectedBindingType::UniformBlock { size: current, .. },
+            ReflectedBindingType::UniformBlock { size: reloaded, .. },
+        ) => reloaded <= current,
+        (
+            ReflectedBindingType::Texture {
+                dimension,
+                multisampled,
+            },
+            ReflectedBindingType::Texture {
+                dimension: reloaded_dimension,
+                multisampled: reloaded_multisampled,
+            },
+        ) => dimension == reloaded_dimension && multisampled == reloaded_multisampled,
+        (ReflectedBindingType::Sampler, ReflectedBindingType::Sampler)
+        | (ReflectedBindingType::StorageBuffer, ReflectedBindingType::StorageBuffer) => true,
+        _ => false,
+    }
+}
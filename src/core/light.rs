@@ -1,20 +1,82 @@
-use cgmath::Matrix4;
-use cgmath::Vector3;
+use cgmath::{InnerSpace, Matrix4, Vector3, Vector4};
 
-use crate::Component;
+use crate::{Component, Transformation};
 
+/// Value of `LightUniform::light_type`, matching the constants in lit shaders.
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LightType {
+    Point = 0,
+    Directional = 1,
+    Spot = 2,
+    Ambient = 3,
+    Hemisphere = 4,
+}
+
+/// Mirrors the std430 `Light` struct of the light array bound at set 0, binding 0.
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
 pub struct LightUniform {
-    light_color: Vector3<f32>,
-    light_position: Vector3<f32>,
-    is_directional_light: u32,
-    light_projection_matrix: Matrix4<f32>,
+    pub light_position: Vector3<f32>,
+    pub light_type: u32,
+    pub light_color: Vector3<f32>,
+    pub light_intensity: f32,
+    /// World to shadow map clip space. Point lights only hold the projection
+    /// of a cube face, the light array adds the view of each face.
+    pub light_projection_matrix: Matrix4<f32>,
+    /// Direction the light shines in, unused by point lights.
+    pub light_direction: Vector3<f32>,
+    /// Distance at which the light's contribution reaches zero, 0 for no cutoff.
+    pub light_range: f32,
+    /// Color of a hemisphere light from below, `light_color` being its sky color.
+    pub ground_color: Vector3<f32>,
+    /// Cosines of the spot light's inner and outer cone half-angles.
+    pub spot_inner_cos: f32,
+    pub spot_outer_cos: f32,
+    /// Layer of the shadow maps, or -1 without shadows. Lights set 0 to ask
+    /// for a shadow map; the light array assigns the actual layer. Point
+    /// lights take six layers from there, one per cube face.
+    pub shadow_map: i32,
+    /// Subtracted from the depth compared against the shadow map.
+    pub shadow_bias: f32,
+    /// Offset of the receiving position along its normal before the lookup.
+    pub shadow_normal_bias: f32,
+    /// Number of `ShadowCascades` of a directional light, 0 for a single
+    /// shadow map from `light_projection_matrix`.
+    pub shadow_cascades: u32,
+    pub cascade_split_lambda: f32,
+    pub cascade_distance: f32,
+    pub cascade_blend: f32,
 }
 
 unsafe impl bytemuck::Zeroable for LightUniform {}
 unsafe impl bytemuck::Pod for LightUniform {}
 
+impl Default for LightUniform {
+    fn default() -> Self {
+        LightUniform {
+            shadow_map: -1,
+            shadow_bias: 0.005,
+            shadow_normal_bias: 0.02,
+            ..bytemuck::Zeroable::zeroed()
+        }
+    }
+}
+
+impl LightUniform {
+    /// Takes the light's position from the origin of `transformation` and its
+    /// direction from the -z axis, the way cameras look.
+    pub fn set_transformation(&mut self, transformation: &Transformation) {
+        let global_transformation = transformation.global_transformation();
+        let position = global_transformation * Vector4::new(0.0, 0.0, 0.0, 1.0);
+        self.light_position = position.truncate() / position.w;
+        self.light_direction = (global_transformation * Vector4::new(0.0, 0.0, -1.0, 0.0))
+            .truncate()
+            .normalize();
+    }
+}
+
 pub trait Light: Component {
-    fn bind_light(&self, render_pass: &wgpu::RenderPass);
+    /// The light as it is uploaded into the scene's light array.
+    fn light_uniform(&self) -> LightUniform;
 }
@@ -0,0 +1,551 @@
+use std::{cell::RefCell, cmp::Ordering, rc::Rc};
+
+use cgmath::{prelude::Zero, InnerSpace, Matrix4, SquareMatrix, Vector2, Vector3, Vector4};
+
+use crate::core::{environment::EnvironmentUniform, light_clusters::ClusterHeader};
+use crate::{
+    ClusterGrid, Environment, LightType, LightUniform, ShadowCascades, ShadowLayer, ShadowMaps,
+    State, UniformBuffer, LIGHT_BIND_GROUP,
+};
+
+/// Precedes the lights in the std430 `LightInformation` buffer.
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct LightArrayHeader {
+    light_count: u32,
+    _padding: [u32; 3],
+}
+
+unsafe impl bytemuck::Zeroable for LightArrayHeader {}
+unsafe impl bytemuck::Pod for LightArrayHeader {}
+
+/// A storage buffer that is recreated larger when the data outgrows it.
+struct GrowableBuffer {
+    buffer: wgpu::Buffer,
+    size: u64,
+    label: &'static str,
+}
+
+impl GrowableBuffer {
+    fn new(size: u64, label: &'static str, state: &State) -> GrowableBuffer {
+        GrowableBuffer {
+            buffer: GrowableBuffer::create_buffer(size, label, state),
+            size,
+            label,
+        }
+    }
+
+    fn create_buffer(size: u64, label: &str, state: &State) -> wgpu::Buffer {
+        state.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage: wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Returns whether the buffer was recreated.
+    fn reserve(&mut self, size: u64, state: &State) -> bool {
+        if size <= self.size {
+            return false;
+        }
+        self.size = size.next_power_of_two();
+        self.buffer = GrowableBuffer::create_buffer(self.size, self.label, state);
+        true
+    }
+}
+
+/// Storage buffers holding every light of a scene and the lights of each view
+/// space cluster, bound at set 0 along with the shadow maps and the scene's
+/// environment. The buffers grow with the number of lights up to `max_lights`.
+pub struct LightArray {
+    light_buffer: GrowableBuffer,
+    cluster_buffer: GrowableBuffer,
+    index_buffer: GrowableBuffer,
+    shadow_layer_buffer: GrowableBuffer,
+    shadow_maps: ShadowMaps,
+    shadow_layers: Vec<ShadowLayer>,
+    environment_uniform: UniformBuffer<EnvironmentUniform>,
+    /// Bound while the scene has no environment.
+    default_environment: Environment,
+    environment: Option<Rc<RefCell<Environment>>>,
+    bind_group: wgpu::BindGroup,
+    cluster_grid: ClusterGrid,
+    max_lights: usize,
+    max_point_shadows: usize,
+    dropped_lights: usize,
+}
+
+impl LightArray {
+    pub const DEFAULT_MAX_LIGHTS: usize = 1024;
+    pub const DEFAULT_MAX_POINT_SHADOWS: usize = 2;
+    /// Shadow map layers taken by a point light, one per cube face.
+    pub const POINT_SHADOW_LAYERS: usize = 6;
+
+    pub fn new(state: &State) -> LightArray {
+        let [light_size, cluster_size, index_size, shadow_layer_size] =
+            LightArray::storage_bindings();
+        let light_buffer = GrowableBuffer::new(light_size.1, "Light Array Buffer", state);
+        let cluster_buffer = GrowableBuffer::new(cluster_size.1, "Light Cluster Buffer", state);
+        let index_buffer = GrowableBuffer::new(index_size.1, "Light Index Buffer", state);
+        let shadow_layer_buffer =
+            GrowableBuffer::new(shadow_layer_size.1, "Shadow Layer Buffer", state);
+        let shadow_maps =
+            ShadowMaps::new(ShadowMaps::DEFAULT_SIZE, ShadowMaps::DEFAULT_LAYERS, state);
+        let default_environment = Environment::with_device(1, 1, 1, 1, &state.device);
+        let environment_uniform = UniformBuffer::new(
+            default_environment.uniform(),
+            Some("Environment Buffer"),
+            state,
+        );
+        let bind_group = LightArray::create_bind_group(
+            [
+                &light_buffer,
+                &cluster_buffer,
+                &index_buffer,
+                &shadow_layer_buffer,
+            ],
+            &shadow_maps,
+            &environment_uniform,
+            &default_environment,
+            state,
+        );
+        LightArray {
+            light_buffer,
+            cluster_buffer,
+            index_buffer,
+            shadow_layer_buffer,
+            shadow_maps,
+            shadow_layers: Vec::new(),
+            environment_uniform,
+            default_environment,
+            environment: None,
+            bind_group,
+            cluster_grid: ClusterGrid::default(),
+            max_lights: LightArray::DEFAULT_MAX_LIGHTS,
+            max_point_shadows: LightArray::DEFAULT_MAX_POINT_SHADOWS,
+            dropped_lights: 0,
+        }
+    }
+
+    /// Binding and smallest size in bytes of the light, cluster, light index
+    /// and shadow layer buffers. Bindings 3 and 4 hold the shadow maps, 6 to
+    /// 10 the environment.
+    pub(crate) fn storage_bindings() -> [(u32, u64); 4] {
+        [
+            (
+                0,
+                (std::mem::size_of::<LightArrayHeader>() + std::mem::size_of::<LightUniform>())
+                    as u64,
+            ),
+            (
+                1,
+                (std::mem::size_of::<ClusterHeader>() + std::mem::size_of::<[u32; 2]>()) as u64,
+            ),
+            (2, std::mem::size_of::<u32>() as u64),
+            (5, std::mem::size_of::<ShadowLayer>() as u64),
+        ]
+    }
+
+    fn create_bind_group(
+        buffers: [&GrowableBuffer; 4],
+        shadow_maps: &ShadowMaps,
+        environment_uniform: &UniformBuffer<EnvironmentUniform>,
+        environment: &Environment,
+        state: &State,
+    ) -> wgpu::BindGroup {
+        let mut entries: Vec<_> = LightArray::storage_bindings()
+            .iter()
+            .zip(buffers.iter())
+            .map(|(&(binding, _), buffer)| wgpu::BindGroupEntry {
+                binding,
+                resource: buffer.buffer.as_entire_binding(),
+            })
+            .collect();
+        entries.push(wgpu::BindGroupEntry {
+            binding: 3,
+            resource: wgpu::BindingResource::TextureView(shadow_maps.view()),
+        });
+        entries.push(wgpu::BindGroupEntry {
+            binding: 4,
+            resource: wgpu::BindingResource::Sampler(shadow_maps.sampler()),
+        });
+        entries.extend(vec![
+            wgpu::BindGroupEntry {
+                binding: 6,
+                resource: environment_uniform.buffer().as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 7,
+                resource: wgpu::BindingResource::TextureView(environment.irradiance_view()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 8,
+                resource: wgpu::BindingResource::TextureView(environment.specular_view()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 9,
+                resource: wgpu::BindingResource::TextureView(environment.brdf_lut_view()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 10,
+                resource: wgpu::BindingResource::Sampler(environment.sampler()),
+            },
+        ]);
+        state.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &state.light_bind_group_layout,
+            entries: &entries,
+            label: Some("light_array_bind_group"),
+        })
+    }
+
+    fn rebuild_bind_group(&mut self, state: &State) {
+        let environment = self
+            .environment
+            .as_ref()
+            .map(|environment| environment.borrow());
+        self.bind_group = LightArray::create_bind_group(
+            [
+                &self.light_buffer,
+                &self.cluster_buffer,
+                &self.index_buffer,
+                &self.shadow_layer_buffer,
+            ],
+            &self.shadow_maps,
+            &self.environment_uniform,
+            environment.as_deref().unwrap_or(&self.default_environment),
+            state,
+        );
+    }
+
+    pub fn shadow_maps(&self) -> &ShadowMaps {
+        &self.shadow_maps
+    }
+
+    /// Replaces the shadow maps; lights asking for shadows beyond `layers` get none.
+    pub fn set_shadow_maps(&mut self, size: u32, layers: u32, state: &State) {
+        self.shadow_maps = ShadowMaps::new(size, layers, state);
+        self.rebuild_bind_group(state);
+    }
+
+    /// Light projection of each shadow map layer as of the last `update`.
+    pub fn shadow_layers(&self) -> &[ShadowLayer] {
+        &self.shadow_layers
+    }
+
+    pub fn cluster_grid(&self) -> ClusterGrid {
+        self.cluster_grid
+    }
+
+    pub fn set_cluster_grid(&mut self, cluster_grid: ClusterGrid) {
+        self.cluster_grid = cluster_grid;
+    }
+
+    pub fn max_lights(&self) -> usize {
+        self.max_lights
+    }
+
+    /// Limits how many lights are uploaded, see `select_lights` for which are kept.
+    pub fn set_max_lights(&mut self, max_lights: usize) {
+        self.max_lights = max_lights;
+    }
+
+    /// How many lights the last `update` left out because of `max_lights`.
+    pub fn dropped_lights(&self) -> usize {
+        self.dropped_lights
+    }
+
+    pub fn max_point_shadows(&self) -> usize {
+        self.max_point_shadows
+    }
+
+    /// Limits how many point lights render shadows per frame, see
+    /// `limit_point_shadows` for which keep them.
+    pub fn set_max_point_shadows(&mut self, max_point_shadows: usize) {
+        self.max_point_shadows = max_point_shadows;
+    }
+
+    /// Keeps the shadows of the `max_point_shadows` point lights of highest
+    /// importance, their brightness over the squared distance to
+    /// `camera_position`, and turns off the shadows of the others.
+    pub fn limit_point_shadows(
+        lights: &mut [LightUniform],
+        camera_position: Vector3<f32>,
+        max_point_shadows: usize,
+    ) {
+        let importance = |light: &LightUniform| {
+            let color = light.light_color;
+            let brightness = light.light_intensity * color.x.max(color.y).max(color.z);
+            brightness / (1.0 + (light.light_position - camera_position).magnitude2())
+        };
+        let mut shadowed: Vec<usize> = (0..lights.len())
+            .filter(|&i| {
+                lights[i].light_type == LightType::Point as u32 && lights[i].shadow_map >= 0
+            })
+            .collect();
+        shadowed.sort_by(|&a, &b| {
+            importance(&lights[b])
+                .partial_cmp(&importance(&lights[a]))
+                .unwrap_or(Ordering::Equal)
+        });
+        for &i in shadowed.iter().skip(max_point_shadows) {
+            lights[i].shadow_map = -1;
+        }
+    }
+
+    /// When there are more lights than `max_lights`, lights without a position
+    /// (directional, ambient and hemisphere lights) are kept first and the
+    /// others by increasing distance to `camera_position`; the rest is dropped.
+    pub fn select_lights(
+        lights: &[LightUniform],
+        camera_position: Vector3<f32>,
+        max_lights: usize,
+    ) -> Vec<LightUniform> {
+        let mut lights = lights.to_vec();
+        if lights.len() > max_lights {
+            let priority = |light: &LightUniform| {
+                if light.light_type != LightType::Point as u32
+                    && light.light_type != LightType::Spot as u32
+                {
+                    -1.0
+                } else {
+                    (light.light_position - camera_position).magnitude2()
+                }
+            };
+            lights.sort_by(|a, b| {
+                priority(a)
+                    .partial_cmp(&priority(b))
+                    .unwrap_or(Ordering::Equal)
+            });
+            lights.truncate(max_lights);
+        }
+        lights
+    }
+
+    /// Gives each light asking for shadows its layers of the shadow maps,
+    /// fitting cascades to the camera frustum and facing point light layers
+    /// along the cube axes. Lights that do not fit in the
+    /// remaining layers get no shadows.
+    fn assign_shadow_layers(
+        &mut self,
+        lights: &mut [LightUniform],
+        view_matrix: &Matrix4<f32>,
+        projection_matrix: &Matrix4<f32>,
+    ) {
+        self.shadow_layers.clear();
+        for light in lights.iter_mut() {
+            let point = light.light_type == LightType::Point as u32;
+            let layers = if point {
+                LightArray::POINT_SHADOW_LAYERS
+            } else {
+                light.shadow_cascades.max(1) as usize
+            };
+            if light.shadow_map < 0
+                || self.shadow_layers.len() + layers > self.shadow_maps.layers() as usize
+            {
+                light.shadow_map = -1;
+                continue;
+            }
+            light.shadow_map = self.shadow_layers.len() as i32;
+
+            if point {
+                for face_view in ShadowMaps::cube_face_views(light.light_position).iter() {
+                    self.shadow_layers.push(ShadowLayer::new(
+                        light.light_projection_matrix * face_view,
+                        0.0,
+                    ));
+                }
+                continue;
+            }
+            if light.shadow_cascades == 0 {
+                self.shadow_layers
+                    .push(ShadowLayer::new(light.light_projection_matrix, 0.0));
+                continue;
+            }
+            let cascades = ShadowCascades {
+                count: light.shadow_cascades,
+                split_lambda: light.cascade_split_lambda,
+                distance: light.cascade_distance,
+                blend: light.cascade_blend,
+            };
+            let mut near = self.cluster_grid.near;
+            for split in cascades.splits(near) {
+                let projection = ShadowCascades::fit(
+                    light.light_direction,
+                    view_matrix,
+                    projection_matrix,
+                    near,
+                    split,
+                    self.shadow_maps.size(),
+                );
+                self.shadow_layers.push(ShadowLayer::new(projection, split));
+                near = split;
+            }
+        }
+    }
+
+    /// Uploads the lights and the environment to be used by the next frame
+    /// and bins the lights into the clusters of a camera whose `viewport` is
+    /// x, y, width and height in pixels of the render target.
+    pub fn update(
+        &mut self,
+        lights: &[LightUniform],
+        environment: Option<&Rc<RefCell<Environment>>>,
+        view_matrix: &Matrix4<f32>,
+        projection_matrix: &Matrix4<f32>,
+        viewport: Vector4<f32>,
+        state: &State,
+    ) {
+        self.dropped_lights = lights.len().saturating_sub(self.max_lights);
+        let camera_position = view_matrix
+            .invert()
+            .map(|camera| camera.w.truncate())
+            .unwrap_or_else(Vector3::zero);
+        let mut lights = LightArray::select_lights(lights, camera_position, self.max_lights);
+        LightArray::limit_point_shadows(&mut lights, camera_position, self.max_point_shadows);
+
+        self.assign_shadow_layers(&mut lights, view_matrix, projection_matrix);
+
+        let bins = self
+            .cluster_grid
+            .bin_lights(&lights, view_matrix, projection_matrix);
+
+        let light_header_size = std::mem::size_of::<LightArrayHeader>();
+        let cluster_header_size = std::mem::size_of::<ClusterHeader>();
+        let resized = [
+            self.light_buffer.reserve(
+                (light_header_size + std::mem::size_of_val(&lights[..])) as u64,
+                state,
+            ),
+            self.cluster_buffer.reserve(
+                (cluster_header_size + std::mem::size_of_val(&bins.clusters[..])) as u64,
+                state,
+            ),
+            self.index_buffer
+                .reserve(std::mem::size_of_val(&bins.indices[..]) as u64, state),
+            self.shadow_layer_buffer
+                .reserve(std::mem::size_of_val(&self.shadow_layers[..]) as u64, state),
+        ];
+        let environment_changed = match (&self.environment, environment) {
+            (Some(current), Some(environment)) => !Rc::ptr_eq(current, environment),
+            (None, None) => false,
+            _ => true,
+        };
+        if environment_changed {
+            self.environment = environment.cloned();
+        }
+        if environment_changed || resized.iter().any(|&resized| resized) {
+            self.rebuild_bind_group(state);
+        }
+        *self.environment_uniform.data_mut() = match self.environment {
+            Some(ref environment) => environment.borrow().uniform(),
+            None => self.default_environment.uniform(),
+        };
+        self.environment_uniform.flush(state);
+
+        let light_header = LightArrayHeader {
+            light_count: lights.len() as u32,
+            _padding: [0; 3],
+        };
+        let cluster_header = ClusterHeader {
+            view_matrix: *view_matrix,
+            dimensions: self.cluster_grid.dimensions,
+            near: self.cluster_grid.near,
+            screen_size: Vector2::new(viewport.z, viewport.w),
+            far: self.cluster_grid.far,
+            _padding: 0,
+            screen_offset: Vector2::new(viewport.x, viewport.y),
+        };
+        let queue = &state.queue;
+        queue.write_buffer(
+            &self.light_buffer.buffer,
+            0,
+            bytemuck::bytes_of(&light_header),
+        );
+        if !lights.is_empty() {
+            queue.write_buffer(
+                &self.light_buffer.buffer,
+                light_header_size as u64,
+                bytemuck::cast_slice(&lights),
+            );
+        }
+        queue.write_buffer(
+            &self.cluster_buffer.buffer,
+            0,
+            bytemuck::bytes_of(&cluster_header),
+        );
+        queue.write_buffer(
+            &self.cluster_buffer.buffer,
+            cluster_header_size as u64,
+            bytemuck::cast_slice(&bins.clusters),
+        );
+        if !self.shadow_layers.is_empty() {
+            queue.write_buffer(
+                &self.shadow_layer_buffer.buffer,
+                0,
+                bytemuck::cast_slice(&self.shadow_layers),
+            );
+        }
+        if !bins.indices.is_empty() {
+            queue.write_buffer(
+                &self.index_buffer.buffer,
+                0,
+                bytemuck::cast_slice(&bins.indices),
+            );
+        }
+    }
+
+    pub fn bind<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_bind_group(LIGHT_BIND_GROUP, &self.bind_group, &[]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn light(light_type: LightType, position: Vector3<f32>) -> LightUniform {
+        LightUniform {
+            light_type: light_type as u32,
+            light_position: position,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_select_lights() {
+        let lights = [
+            light(LightType::Point, Vector3::new(0.0, 0.0, 10.0)),
+            light(LightType::Directional, Vector3::new(0.0, 0.0, 100.0)),
+            light(LightType::Spot, Vector3::new(0.0, 1.0, 0.0)),
+        ];
+
+        let selected = LightArray::select_lights(&lights, Vector3::new(0.0, 0.0, 0.0), 2);
+        assert_eq!(selected.len(), 2);
+        assert_eq!(selected[0].light_type, LightType::Directional as u32);
+        assert_eq!(selected[1].light_type, LightType::Spot as u32);
+
+        let selected = LightArray::select_lights(&lights, Vector3::new(0.0, 0.0, 0.0), 3);
+        assert_eq!(selected[0].light_type, LightType::Point as u32);
+    }
+
+    #[test]
+    fn test_limit_point_shadows() {
+        let mut lights = [
+            light(LightType::Point, Vector3::new(0.0, 0.0, 10.0)),
+            light(LightType::Point, Vector3::new(0.0, 0.0, 1.0)),
+            light(LightType::Spot, Vector3::new(0.0, 0.0, 20.0)),
+            light(LightType::Point, Vector3::new(0.0, 0.0, 5.0)),
+        ];
+        for light in lights.iter_mut() {
+            light.light_color = Vector3::new(1.0, 1.0, 1.0);
+            light.light_intensity = 1.0;
+            light.shadow_map = 0;
+        }
+        lights[0].light_intensity = 100.0;
+
+        LightArray::limit_point_shadows(&mut lights, Vector3::new(0.0, 0.0, 0.0), 2);
+        let shadowed: Vec<bool> = lights.iter().map(|light| light.shadow_map >= 0).collect();
+        assert_eq!(shadowed, vec![true, true, true, false]);
+    }
+}
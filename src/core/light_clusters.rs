@@ -0,0 +1,220 @@
+use cgmath::{Matrix4, Vector2, Vector4};
+
+use crate::{LightType, LightUniform};
+
+/// Precedes the clusters in the std430 `LightClusters` buffer.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub(crate) struct ClusterHeader {
+    pub view_matrix: Matrix4<f32>,
+    pub dimensions: [u32; 3],
+    pub near: f32,
+    pub screen_size: Vector2<f32>,
+    pub far: f32,
+    pub _padding: u32,
+    /// Top left corner of the viewport in the render target.
+    pub screen_offset: Vector2<f32>,
+}
+
+unsafe impl bytemuck::Zeroable for ClusterHeader {}
+unsafe impl bytemuck::Pod for ClusterHeader {}
+
+/// Splits the view frustum into screen tiles and exponentially spaced depth
+/// slices between `near` and `far`. Lit shaders only iterate the lights
+/// binned into the cluster of their fragment.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ClusterGrid {
+    pub dimensions: [u32; 3],
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for ClusterGrid {
+    fn default() -> Self {
+        ClusterGrid {
+            dimensions: [16, 9, 24],
+            near: 0.1,
+            far: 1000.0,
+        }
+    }
+}
+
+/// Lights of each cluster: `clusters` holds an offset into `indices` and a
+/// count, `indices` the positions of the lights in the light array.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LightBins {
+    pub clusters: Vec<[u32; 2]>,
+    pub indices: Vec<u32>,
+}
+
+impl ClusterGrid {
+    pub fn cluster_count(&self) -> usize {
+        self.dimensions.iter().product::<u32>() as usize
+    }
+
+    /// Index of the cluster, laid out x fastest then y then z like the shaders expect.
+    pub fn cluster_index(&self, x: u32, y: u32, z: u32) -> usize {
+        ((z * self.dimensions[1] + y) * self.dimensions[0] + x) as usize
+    }
+
+    /// Depth slice of a view space distance in front of the camera.
+    pub fn slice(&self, depth: f32) -> u32 {
+        let slice = (depth / self.near).ln() / (self.far / self.near).ln();
+        ((slice * self.dimensions[2] as f32).max(0.0) as u32).min(self.dimensions[2] - 1)
+    }
+
+    /// Bins the lights into clusters. Point and spot lights are bounded by a
+    /// sphere of their range; directional lights and lights without a range
+    /// reach every cluster.
+    pub fn bin_lights(
+        &self,
+        lights: &[LightUniform],
+        view_matrix: &Matrix4<f32>,
+        projection_matrix: &Matrix4<f32>,
+    ) -> LightBins {
+        let [width, height, depth] = self.dimensions;
+        let mut clusters = vec![Vec::new(); self.cluster_count()];
+
+        for (i, light) in lights.iter().enumerate() {
+            let ranges =
+                if light.light_type == LightType::Directional as u32 || light.light_range <= 0.0 {
+                    Some(([0, width - 1], [0, height - 1], [0, depth - 1]))
+                } else {
+                    self.light_ranges(light, view_matrix, projection_matrix)
+                };
+            if let Some((x_range, y_range, z_range)) = ranges {
+                for z in z_range[0]..=z_range[1] {
+                    for y in y_range[0]..=y_range[1] {
+                        for x in x_range[0]..=x_range[1] {
+                            clusters[self.cluster_index(x, y, z)].push(i as u32);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut bins = LightBins::default();
+        for cluster in clusters {
+            bins.clusters
+                .push([bins.indices.len() as u32, cluster.len() as u32]);
+            bins.indices.extend(cluster);
+        }
+        bins
+    }
+
+    /// Inclusive cluster ranges along x, y and z covered by the light's
+    /// sphere, or `None` when it lies outside of the frustum.
+    #[allow(clippy::type_complexity)]
+    fn light_ranges(
+        &self,
+        light: &LightUniform,
+        view_matrix: &Matrix4<f32>,
+        projection_matrix: &Matrix4<f32>,
+    ) -> Option<([u32; 2], [u32; 2], [u32; 2])> {
+        let [width, height, _] = self.dimensions;
+        let center = (view_matrix * light.light_position.extend(1.0)).truncate();
+        let radius = light.light_range;
+        let depth = -center.z;
+        if depth + radius < self.near || depth - radius > self.far {
+            return None;
+        }
+        let z_range = [
+            self.slice((depth - radius).max(self.near)),
+            self.slice((depth + radius).min(self.far)),
+        ];
+
+        // Projecting the corners of the sphere's bounding box gives a
+        // conservative screen rectangle, unless a corner is behind the camera.
+        let mut min = Vector2::new(-1.0f32, -1.0);
+        let mut max = Vector2::new(1.0f32, 1.0);
+        if depth - radius > 0.0 {
+            min = Vector2::new(f32::MAX, f32::MAX);
+            max = Vector2::new(f32::MIN, f32::MIN);
+            for corner in 0..8 {
+                let offset = Vector4::new(
+                    if corner & 1 == 0 { -radius } else { radius },
+                    if corner & 2 == 0 { -radius } else { radius },
+                    if corner & 4 == 0 { -radius } else { radius },
+                    0.0,
+                );
+                let clip = projection_matrix * (center.extend(1.0) + offset);
+                let ndc = clip.truncate().truncate() / clip.w;
+                min = Vector2::new(min.x.min(ndc.x), min.y.min(ndc.y));
+                max = Vector2::new(max.x.max(ndc.x), max.y.max(ndc.y));
+            }
+        }
+        if max.x < -1.0 || min.x > 1.0 || max.y < -1.0 || min.y > 1.0 {
+            return None;
+        }
+
+        let tile = |ndc: f32, tiles: u32| {
+            (((ndc + 1.0) / 2.0 * tiles as f32).max(0.0) as u32).min(tiles - 1)
+        };
+        // Rows count from the top of the screen, like fragment coordinates.
+        Some((
+            [tile(min.x, width), tile(max.x, width)],
+            [tile(-max.y, height), tile(-min.y, height)],
+            z_range,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::{prelude::One, Deg, Vector3};
+
+    fn light(light_type: LightType, position: Vector3<f32>, range: f32) -> LightUniform {
+        LightUniform {
+            light_type: light_type as u32,
+            light_position: position,
+            light_range: range,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_bin_lights() {
+        let grid = ClusterGrid {
+            dimensions: [4, 4, 4],
+            near: 1.0,
+            far: 100.0,
+        };
+        let lights = [
+            light(LightType::Point, Vector3::new(0.0, 0.0, -10.0), 1.0),
+            light(LightType::Directional, Vector3::new(0.0, 0.0, 0.0), 0.0),
+            light(LightType::Point, Vector3::new(0.0, 0.0, 10.0), 1.0),
+        ];
+        let bins = grid.bin_lights(
+            &lights,
+            &Matrix4::one(),
+            &cgmath::perspective(Deg(90.0), 1.0, 1.0, 100.0),
+        );
+        let cluster = |x, y, z| {
+            let [offset, count] = bins.clusters[grid.cluster_index(x, y, z)];
+            bins.indices[offset as usize..(offset + count) as usize].to_vec()
+        };
+
+        assert_eq!(bins.clusters.len(), 64);
+        assert_eq!(cluster(1, 1, 1), vec![0, 1]);
+        assert_eq!(cluster(2, 2, 2), vec![0, 1]);
+        assert_eq!(cluster(0, 0, 1), vec![1]);
+        assert_eq!(cluster(1, 1, 3), vec![1]);
+        // The light behind the camera reaches no cluster.
+        assert!(bins.indices.iter().all(|&i| i != 2));
+    }
+
+    #[test]
+    fn test_slice() {
+        let grid = ClusterGrid {
+            dimensions: [1, 1, 2],
+            near: 1.0,
+            far: 100.0,
+        };
+        assert_eq!(grid.slice(0.5), 0);
+        assert_eq!(grid.slice(5.0), 0);
+        assert_eq!(grid.slice(20.0), 1);
+        assert_eq!(grid.slice(1000.0), 1);
+    }
+}
@@ -1,12 +1,166 @@
-use crate::{Bindable, Geometry, Light, RenderPassWrapper};
+use std::{any::Any, path::Path, rc::Rc};
 
-pub trait Material: Bindable {
+use crate::{Bindable, Geometry, Light, RenderPassWrapper, RenderTargetFormat, State, Texture};
+
+/// How the output of a material is combined with what is already in the render target.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BlendMode {
+    Opaque,
+    AlphaBlend,
+    Additive,
+    /// The material outputs colors already multiplied by their alpha.
+    Premultiplied,
+    /// Opaque, but fragments with an alpha below the cutoff are discarded.
+    AlphaTest(f32),
+}
+
+impl BlendMode {
+    /// Transparent materials are drawn after opaque ones, sorted back to front.
+    pub fn is_transparent(&self) -> bool {
+        match self {
+            BlendMode::Opaque | BlendMode::AlphaTest(_) => false,
+            BlendMode::AlphaBlend | BlendMode::Additive | BlendMode::Premultiplied => true,
+        }
+    }
+
+    pub fn alpha_cutoff(&self) -> f32 {
+        match self {
+            BlendMode::AlphaTest(cutoff) => *cutoff,
+            _ => 0.0,
+        }
+    }
+
+    pub fn color_blend(&self) -> wgpu::BlendDescriptor {
+        match self {
+            BlendMode::Opaque | BlendMode::AlphaTest(_) => wgpu::BlendDescriptor::REPLACE,
+            BlendMode::AlphaBlend => wgpu::BlendDescriptor {
+                src_factor: wgpu::BlendFactor::SrcAlpha,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+            BlendMode::Additive => wgpu::BlendDescriptor {
+                src_factor: wgpu::BlendFactor::SrcAlpha,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            BlendMode::Premultiplied => wgpu::BlendDescriptor {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+        }
+    }
+
+    pub fn alpha_blend(&self) -> wgpu::BlendDescriptor {
+        match self {
+            BlendMode::Opaque | BlendMode::AlphaTest(_) => wgpu::BlendDescriptor::REPLACE,
+            BlendMode::Additive => wgpu::BlendDescriptor {
+                src_factor: wgpu::BlendFactor::Zero,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            BlendMode::AlphaBlend | BlendMode::Premultiplied => wgpu::BlendDescriptor {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+        }
+    }
+}
+
+/// Depth and stencil testing of a material. The depth format comes from the
+/// render target the material is drawn into.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DepthState {
+    pub write: bool,
+    /// Comparison for a depth range from 0 near to 1 far, flipped for render
+    /// targets with reversed z.
+    pub compare: wgpu::CompareFunction,
+    /// Only applies to depth formats with a stencil aspect.
+    pub stencil: wgpu::StencilStateDescriptor,
+}
+
+impl DepthState {
+    /// Opaque materials test and write depth. Transparent ones only test it,
+    /// so that they do not hide what is drawn behind them afterwards.
+    pub fn for_blend_mode(blend_mode: BlendMode) -> DepthState {
+        DepthState {
+            write: !blend_mode.is_transparent(),
+            compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilStateDescriptor::default(),
+        }
+    }
+
+    /// Neither tests nor writes depth.
+    pub fn disabled() -> DepthState {
+        DepthState {
+            write: false,
+            compare: wgpu::CompareFunction::Always,
+            stencil: wgpu::StencilStateDescriptor::default(),
+        }
+    }
+
+    pub fn descriptor(
+        &self,
+        target: &RenderTargetFormat,
+    ) -> Option<wgpu::DepthStencilStateDescriptor> {
+        use wgpu::CompareFunction::*;
+        let format = target.depth_format?;
+        let compare = match (target.reversed_z, self.compare) {
+            (true, Less) => Greater,
+            (true, LessEqual) => GreaterEqual,
+            (true, Greater) => Less,
+            (true, GreaterEqual) => LessEqual,
+            (_, compare) => compare,
+        };
+        Some(wgpu::DepthStencilStateDescriptor {
+            format,
+            depth_write_enabled: self.write,
+            depth_compare: compare,
+            stencil: if target.has_stencil() {
+                self.stencil.clone()
+            } else {
+                wgpu::StencilStateDescriptor::default()
+            },
+        })
+    }
+}
+
+/// Lets a boxed material be downcast back to its concrete type, see `Mesh::material_mut`.
+pub trait AsAny {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+pub trait Material: Bindable + AsAny {
     fn fragment_stage(&self) -> wgpu::ShaderModuleSource;
-    fn color_states(&self) -> Vec<wgpu::ColorStateDescriptor>;
-    fn depth_stencil_state(&self) -> Option<wgpu::DepthStencilStateDescriptor>;
-    fn sample_count(&self) -> u32;
+    /// Color attachments of a pipeline drawing into a target of `format`.
+    fn color_states(&self, format: wgpu::TextureFormat) -> Vec<wgpu::ColorStateDescriptor>;
+    /// Depth test and write, by default following the blend mode.
+    fn depth_state(&self) -> DepthState {
+        DepthState::for_blend_mode(self.blend_mode())
+    }
     fn sample_mask(&self) -> u32;
     fn alpha_to_coverage_enabled(&self) -> bool;
+    fn blend_mode(&self) -> BlendMode {
+        BlendMode::Opaque
+    }
+    /// Textures the material samples, so that cameras rendering into one of
+    /// them run first and leave out the material's meshes.
+    fn sampled_textures(&self) -> Vec<&Rc<Texture>> {
+        Vec::new()
+    }
+    /// Uploads parameters changed since the last frame. Called by cameras before drawing.
+    fn update_buffers(&self, _state: &State) {}
     fn draw_material<'a>(
         &'a self,
         geometry: &'a dyn Geometry,
@@ -15,4 +169,9 @@ pub trait Material: Bindable {
         render_pass: &mut RenderPassWrapper<'a, '_>,
     );
     fn material_bind_group_layout(&self) -> Option<&wgpu::BindGroupLayout>;
+    /// GLSL source of the fragment stage, watched by `ShaderHotReload`. Relative
+    /// paths name shaders of this crate, relative to its `src` directory.
+    fn fragment_shader_path(&self) -> Option<&Path> {
+        None
+    }
 }
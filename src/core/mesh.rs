@@ -1,24 +1,87 @@
 use std::{
+    borrow::Cow,
     cell::RefCell,
+    collections::HashMap,
     rc::{Rc, Weak},
 };
 
-use cgmath::{prelude::One, Matrix3, Matrix4};
+use cgmath::{prelude::One, Matrix, Matrix3, Matrix4, SquareMatrix};
 
-use crate::{CameraUniform, Component, Geometry, Material, Scene, State, Transformation};
+#[cfg(feature = "hot-reload")]
+use crate::{core::hot_reload::check_compatible, ShaderError};
+
+use crate::{
+    CameraUniform, Component, DebugMaterial, DebugView, Geometry, Material, RenderPassWrapper,
+    RenderTargetFormat, Scene, State, Transformation, WireframeGeometry, GEOMETRY_BIND_GROUP,
+    LIGHT_BIND_GROUP, SHADOW_MAP_FORMAT,
+};
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
 pub struct MeshUniform {
     transformation: Matrix4<f32>,
-    normal_transformation: Matrix3<f32>,
+    /// Columns of the `mat3`, each padded to a `vec4` like in the shaders.
+    normal_transformation: [[f32; 4]; 3],
+    receive_shadows: u32,
 }
 
 unsafe impl bytemuck::Zeroable for MeshUniform {}
 unsafe impl bytemuck::Pod for MeshUniform {}
 
+impl MeshUniform {
+    /// Takes the global matrix of `transformation` and the inverse-transpose
+    /// of its 3x3 part, which keeps normals perpendicular under non-uniform
+    /// scaling.
+    pub fn set_transformation(&mut self, transformation: &Transformation) {
+        let global_transformation = transformation.global_transformation();
+        self.transformation = *global_transformation;
+        let normal = Matrix3::from_cols(
+            global_transformation.x.truncate(),
+            global_transformation.y.truncate(),
+            global_transformation.z.truncate(),
+        )
+        .invert()
+        .unwrap_or_else(Matrix3::one)
+        .transpose();
+        self.normal_transformation = [
+            normal.x.extend(0.0).into(),
+            normal.y.extend(0.0).into(),
+            normal.z.extend(0.0).into(),
+        ];
+    }
+
+    pub fn transformation(&self) -> &Matrix4<f32> {
+        &self.transformation
+    }
+
+    pub fn normal_transformation(&self) -> Matrix3<f32> {
+        let [x, y, z] = self.normal_transformation;
+        Matrix3::new(x[0], x[1], x[2], y[0], y[1], y[2], z[0], z[1], z[2])
+    }
+}
+
+/// Pipelines drawing a mesh with a `DebugMaterial`, built the first time the
+/// view is requested for a render target.
+pub struct DebugPipeline {
+    pub material: DebugMaterial,
+    /// Replaces the mesh's geometry for the barycentric wireframe fallback.
+    pub geometry: Option<WireframeGeometry>,
+    polygon_mode: Option<wgpu::PolygonMode>,
+    render_pipelines: HashMap<RenderTargetFormat, wgpu::RenderPipeline>,
+}
+
+impl DebugPipeline {
+    pub fn render_pipeline(&self, target: &RenderTargetFormat) -> Option<&wgpu::RenderPipeline> {
+        self.render_pipelines.get(target)
+    }
+}
+
 pub struct Mesh {
-    pub render_pipeline: wgpu::RenderPipeline,
+    /// Render pipelines by the target they draw into, built on first use.
+    render_pipelines: HashMap<RenderTargetFormat, wgpu::RenderPipeline>,
+    /// Shaders replacing the geometry's and material's own, set by `reload_shaders`.
+    vertex_spirv: Option<Vec<u32>>,
+    fragment_spirv: Option<Vec<u32>>,
     pub geometry: Box<dyn Geometry>,
     pub material: Box<dyn Material>,
     has_parent: bool,
@@ -27,25 +90,73 @@ pub struct Mesh {
     children: Vec<Rc<RefCell<dyn Component>>>,
     this: Weak<RefCell<Mesh>>,
     pub data: MeshUniform,
+    /// Draws this mesh with a diagnostic material, unless the camera overrides it.
+    pub debug_view: Option<DebugView>,
+    debug_pipelines: HashMap<DebugView, DebugPipeline>,
+    cast_shadows: bool,
+    /// Depth only pipeline rendering the mesh into shadow maps.
+    shadow_pipeline: Option<wgpu::RenderPipeline>,
 }
 
 impl Mesh {
     pub fn new_ref(
         geometry: impl Geometry + 'static,
         material: impl Material + 'static,
-        state: &State,
     ) -> Rc<RefCell<Mesh>> {
-        let mut bind_group_layouts = vec![
-            //&state.light_bind_group_layout, UNCOMMENT
-        ];
+        let mesh = Rc::new(RefCell::new(Mesh {
+            render_pipelines: HashMap::new(),
+            vertex_spirv: None,
+            fragment_spirv: None,
+            geometry: Box::new(geometry),
+            material: Box::new(material),
+            has_parent: false,
+            scene: Weak::new(),
+            transformation: Transformation::new(),
+            children: Vec::new(),
+            this: Weak::new(),
+            data: MeshUniform {
+                transformation: Matrix4::one(),
+                normal_transformation: [
+                    [1.0, 0.0, 0.0, 0.0],
+                    [0.0, 1.0, 0.0, 0.0],
+                    [0.0, 0.0, 1.0, 0.0],
+                ],
+                receive_shadows: 1,
+            },
+            debug_view: None,
+            debug_pipelines: HashMap::new(),
+            cast_shadows: true,
+            shadow_pipeline: None,
+        }));
 
-        if let Some(bgl) = geometry.geometry_bind_group_layout() {
-            bind_group_layouts.push(bgl);
-        }
-        if let Some(bgl) = material.material_bind_group_layout() {
-            bind_group_layouts.push(bgl);
+        mesh.borrow_mut().this = Rc::downgrade(&mesh);
+        mesh
+    }
+
+    fn create_render_pipeline(
+        geometry: &dyn Geometry,
+        material: &dyn Material,
+        vertex_stage: wgpu::ShaderModuleSource,
+        fragment_stage: wgpu::ShaderModuleSource,
+        polygon_mode: Option<wgpu::PolygonMode>,
+        target: &RenderTargetFormat,
+        state: &State,
+    ) -> wgpu::RenderPipeline {
+        let mut rasterization_state = geometry.rasterization_state();
+        if let Some(polygon_mode) = polygon_mode {
+            rasterization_state.polygon_mode = polygon_mode;
         }
 
+        let bind_group_layouts = [
+            &state.light_bind_group_layout,
+            geometry
+                .geometry_bind_group_layout()
+                .unwrap_or(&state.empty_bind_group_layout),
+            material
+                .material_bind_group_layout()
+                .unwrap_or(&state.empty_bind_group_layout),
+        ];
+
         let render_pipeline_layout =
             state
                 .device
@@ -60,52 +171,291 @@ impl Mesh {
                     label: None,
                 });
 
-        let vs_module = state.device.create_shader_module(geometry.vertex_stage());
-        let fs_module = state.device.create_shader_module(material.fragment_stage());
+        let vs_module = state.device.create_shader_module(vertex_stage);
+        let fs_module = state.device.create_shader_module(fragment_stage);
+
+        state
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                layout: Some(&render_pipeline_layout),
+                vertex_stage: wgpu::ProgrammableStageDescriptor {
+                    module: &vs_module,
+                    entry_point: "main",
+                },
+                fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+                    module: &fs_module,
+                    entry_point: "main",
+                }),
+                rasterization_state: Some(rasterization_state),
+                color_states: &target
+                    .color_format
+                    .map(|format| material.color_states(format))
+                    .unwrap_or_default(),
+                primitive_topology: geometry.primitive_topology(),
+                depth_stencil_state: material.depth_state().descriptor(target),
+                vertex_state: wgpu::VertexStateDescriptor {
+                    index_format: geometry.index_format(),
+                    vertex_buffers: &geometry.vertex_buffer(),
+                },
+                sample_count: target.sample_count,
+                sample_mask: material.sample_mask(),
+                alpha_to_coverage_enabled: material.alpha_to_coverage_enabled(),
+                label: None,
+            })
+    }
+
+    /// Rebuilds the render pipelines with the given SPIR-V in place of the
+    /// geometry's vertex shader and/or the material's fragment shader. If a
+    /// shader's bindings do not fit the pipeline layout, the mesh keeps its
+    /// previous shaders and pipelines.
+    #[cfg(feature = "hot-reload")]
+    pub fn reload_shaders(
+        &mut self,
+        vertex_spirv: Option<Vec<u32>>,
+        fragment_spirv: Option<Vec<u32>>,
+        state: &State,
+    ) -> Result<(), ShaderError> {
+        if let Some(ref spirv) = vertex_spirv {
+            check_compatible(&self.vertex_stage(), spirv)?;
+        }
+        if let Some(ref spirv) = fragment_spirv {
+            check_compatible(&self.fragment_stage(), spirv)?;
+        }
+
+        if vertex_spirv.is_some() {
+            self.vertex_spirv = vertex_spirv;
+            self.shadow_pipeline = None;
+        }
+        if fragment_spirv.is_some() {
+            self.fragment_spirv = fragment_spirv;
+        }
+        let targets: Vec<_> = self.render_pipelines.keys().copied().collect();
+        self.render_pipelines.clear();
+        for target in targets {
+            self.prepare_pipeline(target, state);
+        }
+        Ok(())
+    }
+
+    /// The vertex shader of the render pipelines, reloaded or the geometry's.
+    fn vertex_stage(&self) -> wgpu::ShaderModuleSource {
+        match self.vertex_spirv {
+            Some(ref spirv) => wgpu::ShaderModuleSource::SpirV(Cow::Borrowed(spirv)),
+            None => self.geometry.vertex_stage(),
+        }
+    }
+
+    /// The fragment shader of the render pipelines, reloaded or the material's.
+    fn fragment_stage(&self) -> wgpu::ShaderModuleSource {
+        match self.fragment_spirv {
+            Some(ref spirv) => wgpu::ShaderModuleSource::SpirV(Cow::Borrowed(spirv)),
+            None => self.material.fragment_stage(),
+        }
+    }
+
+    /// Builds the render pipeline for a target if it does not exist yet.
+    pub fn prepare_pipeline(&mut self, target: RenderTargetFormat, state: &State) {
+        if self.render_pipelines.contains_key(&target) {
+            return;
+        }
+
+        let render_pipeline = Mesh::create_render_pipeline(
+            &*self.geometry,
+            &*self.material,
+            self.vertex_stage(),
+            self.fragment_stage(),
+            None,
+            &target,
+            state,
+        );
+        self.render_pipelines.insert(target, render_pipeline);
+    }
+
+    /// The render pipeline for a target, once prepared.
+    pub fn render_pipeline(&self, target: &RenderTargetFormat) -> Option<&wgpu::RenderPipeline> {
+        self.render_pipelines.get(target)
+    }
+
+    /// Builds the pipeline for a debug view and target if it does not exist
+    /// yet. Wireframes use line rasterization when the device supports it and
+    /// fall back to barycentric coordinates otherwise; geometries that expose
+    /// no triangles then get no wireframe.
+    pub fn prepare_debug_view(
+        &mut self,
+        view: DebugView,
+        target: RenderTargetFormat,
+        state: &State,
+    ) {
+        if !self.debug_pipelines.contains_key(&view) {
+            let lines = view == DebugView::Wireframe
+                && state
+                    .features
+                    .contains(wgpu::Features::NON_FILL_POLYGON_MODE);
+            let geometry = if view == DebugView::Wireframe && !lines {
+                let triangles = self.geometry.triangles();
+                if triangles.is_empty() {
+                    return;
+                }
+                Some(WireframeGeometry::new(&triangles, state))
+            } else {
+                None
+            };
+
+            let material = DebugMaterial::new(view, geometry.is_some(), state);
+            self.debug_pipelines.insert(
+                view,
+                DebugPipeline {
+                    material,
+                    geometry,
+                    polygon_mode: if lines {
+                        Some(wgpu::PolygonMode::Line)
+                    } else {
+                        None
+                    },
+                    render_pipelines: HashMap::new(),
+                },
+            );
+        }
+
+        let geometry = &*self.geometry;
+        let debug_pipeline = self.debug_pipelines.get_mut(&view).unwrap();
+        if debug_pipeline.render_pipelines.contains_key(&target) {
+            return;
+        }
+        let pipeline_geometry: &dyn Geometry = match debug_pipeline.geometry {
+            Some(ref geometry) => geometry,
+            None => geometry,
+        };
+        let render_pipeline = Mesh::create_render_pipeline(
+            pipeline_geometry,
+            &debug_pipeline.material,
+            pipeline_geometry.vertex_stage(),
+            debug_pipeline.material.fragment_stage(),
+            debug_pipeline.polygon_mode,
+            &target,
+            state,
+        );
+        debug_pipeline
+            .render_pipelines
+            .insert(target, render_pipeline);
+    }
+
+    pub fn debug_pipeline(&self, view: DebugView) -> Option<&DebugPipeline> {
+        self.debug_pipelines.get(&view)
+    }
+
+    pub fn debug_pipeline_mut(&mut self, view: DebugView) -> Option<&mut DebugPipeline> {
+        self.debug_pipelines.get_mut(&view)
+    }
+
+    pub fn cast_shadows(&self) -> bool {
+        self.cast_shadows
+    }
+
+    pub fn set_cast_shadows(&mut self, cast_shadows: bool) {
+        self.cast_shadows = cast_shadows;
+    }
+
+    pub fn receive_shadows(&self) -> bool {
+        self.data.receive_shadows != 0
+    }
+
+    pub fn set_receive_shadows(&mut self, receive_shadows: bool) {
+        self.data.receive_shadows = receive_shadows as u32;
+    }
+
+    /// Builds the shadow pipeline if it does not exist yet.
+    pub fn prepare_shadow_pipeline(&mut self, state: &State) {
+        if self.shadow_pipeline.is_some() {
+            return;
+        }
 
-        let render_pipeline =
+        let geometry = &*self.geometry;
+        let mut rasterization_state = geometry.rasterization_state();
+        // Slope scaled bias against shadow acne; clamping keeps casters in
+        // front of the light's near plane in the map.
+        rasterization_state.depth_bias = 2;
+        rasterization_state.depth_bias_slope_scale = 2.0;
+        rasterization_state.clamp_depth = state.features.contains(wgpu::Features::DEPTH_CLAMPING);
+
+        let bind_group_layouts = [
+            &state.empty_bind_group_layout,
+            geometry
+                .geometry_bind_group_layout()
+                .unwrap_or(&state.empty_bind_group_layout),
+        ];
+
+        let render_pipeline_layout =
             state
                 .device
-                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                    layout: Some(&render_pipeline_layout),
-                    vertex_stage: wgpu::ProgrammableStageDescriptor {
-                        module: &vs_module,
-                        entry_point: "main",
-                    },
-                    fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
-                        module: &fs_module,
-                        entry_point: "main",
-                    }),
-                    rasterization_state: Some(geometry.rasterization_state()),
-                    color_states: &material.color_states(),
-                    primitive_topology: geometry.primitive_topology(),
-                    depth_stencil_state: material.depth_stencil_state(),
-                    vertex_state: wgpu::VertexStateDescriptor {
-                        index_format: geometry.index_format(),
-                        vertex_buffers: &geometry.vertex_buffer(),
-                    },
-                    sample_count: material.sample_count(),
-                    sample_mask: material.sample_mask(),
-                    alpha_to_coverage_enabled: material.alpha_to_coverage_enabled(),
-                    label: None,
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    bind_group_layouts: &bind_group_layouts,
+                    push_constant_ranges: &[wgpu::PushConstantRange {
+                        stages: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+                        range: 0..(std::mem::size_of::<CameraUniform>()
+                            + std::mem::size_of::<MeshUniform>())
+                            as u32,
+                    }],
+                    label: Some("shadow_pipeline_layout"),
                 });
-        let mesh = Rc::new(RefCell::new(Mesh {
-            render_pipeline,
-            geometry: Box::new(geometry),
-            material: Box::new(material),
-            has_parent: false,
-            scene: Weak::new(),
-            transformation: Transformation::new(),
-            children: Vec::new(),
-            this: Weak::new(),
-            data: MeshUniform {
-                transformation: Matrix4::one(),
-                normal_transformation: Matrix3::one(),
+
+        let vs_module = state.device.create_shader_module(self.vertex_stage());
+
+        self.shadow_pipeline = Some(state.device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                layout: Some(&render_pipeline_layout),
+                vertex_stage: wgpu::ProgrammableStageDescriptor {
+                    module: &vs_module,
+                    entry_point: "main",
+                },
+                fragment_stage: None,
+                rasterization_state: Some(rasterization_state),
+                color_states: &[],
+                primitive_topology: geometry.primitive_topology(),
+                depth_stencil_state: Some(wgpu::DepthStencilStateDescriptor {
+                    format: SHADOW_MAP_FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::LessEqual,
+                    stencil: wgpu::StencilStateDescriptor::default(),
+                }),
+                vertex_state: wgpu::VertexStateDescriptor {
+                    index_format: geometry.index_format(),
+                    vertex_buffers: &geometry.vertex_buffer(),
+                },
+                sample_count: 1,
+                sample_mask: !0,
+                alpha_to_coverage_enabled: false,
+                label: Some("shadow_pipeline"),
             },
-        }));
+        ));
+    }
 
-        mesh.borrow_mut().this = Rc::downgrade(&mesh);
-        mesh
+    /// Draws the mesh's depth into a shadow map, if it casts shadows and its
+    /// shadow pipeline was prepared.
+    pub fn draw_shadow<'a>(&'a self, render_pass: &mut RenderPassWrapper<'a, '_>) {
+        if let (true, Some(pipeline)) = (self.cast_shadows, &self.shadow_pipeline) {
+            render_pass.set_pipeline(pipeline);
+            let state = render_pass.state;
+            render_pass
+                .render_pass
+                .set_bind_group(LIGHT_BIND_GROUP, &state.empty_bind_group, &[]);
+            render_pass.render_pass.set_bind_group(
+                GEOMETRY_BIND_GROUP,
+                self.geometry
+                    .bind_group()
+                    .unwrap_or(&state.empty_bind_group),
+                &[],
+            );
+            self.geometry.draw_geometry(render_pass);
+        }
+    }
+
+    pub fn material_ref<M: Material + 'static>(&self) -> Option<&M> {
+        (*self.material).as_any().downcast_ref::<M>()
+    }
+
+    pub fn material_mut<M: Material + 'static>(&mut self) -> Option<&mut M> {
+        (*self.material).as_any_mut().downcast_mut::<M>()
     }
 }
 
@@ -142,4 +492,98 @@ impl Component for Mesh {
             .meshes
             .push(self.this.upgrade().unwrap());
     }
+    fn update(&mut self) {
+        self.data.set_transformation(&self.transformation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Bindable, Light};
+    use cgmath::{Vector3, Vector4};
+
+    struct EmptyGeometry;
+
+    impl Bindable for EmptyGeometry {
+        fn bind_group(&self) -> Option<&wgpu::BindGroup> {
+            None
+        }
+    }
+
+    impl Geometry for EmptyGeometry {
+        fn vertex_stage(&self) -> wgpu::ShaderModuleSource {
+            wgpu::ShaderModuleSource::SpirV(Cow::Borrowed(&[]))
+        }
+        fn rasterization_state(&self) -> wgpu::RasterizationStateDescriptor {
+            wgpu::RasterizationStateDescriptor::default()
+        }
+        fn primitive_topology(&self) -> wgpu::PrimitiveTopology {
+            wgpu::PrimitiveTopology::TriangleList
+        }
+        fn index_format(&self) -> wgpu::IndexFormat {
+            wgpu::IndexFormat::Uint16
+        }
+        fn vertex_buffer(&self) -> Vec<wgpu::VertexBufferDescriptor> {
+            Vec::new()
+        }
+        fn draw_geometry<'a>(&'a self, _render_pass: &mut wgpu::RenderPass<'a>) {}
+        fn geometry_bind_group_layout(&self) -> Option<&wgpu::BindGroupLayout> {
+            None
+        }
+    }
+
+    struct EmptyMaterial;
+
+    impl Bindable for EmptyMaterial {
+        fn bind_group(&self) -> Option<&wgpu::BindGroup> {
+            None
+        }
+    }
+
+    impl Material for EmptyMaterial {
+        fn fragment_stage(&self) -> wgpu::ShaderModuleSource {
+            wgpu::ShaderModuleSource::SpirV(Cow::Borrowed(&[]))
+        }
+        fn color_states(&self, _format: wgpu::TextureFormat) -> Vec<wgpu::ColorStateDescriptor> {
+            Vec::new()
+        }
+        fn sample_mask(&self) -> u32 {
+            !0
+        }
+        fn alpha_to_coverage_enabled(&self) -> bool {
+            false
+        }
+        fn draw_material<'a>(
+            &'a self,
+            _geometry: &'a dyn Geometry,
+            _lights: &'a Vec<&'a dyn Light>,
+            _pipeline: &'a wgpu::RenderPipeline,
+            _render_pass: &mut RenderPassWrapper<'a, '_>,
+        ) {
+        }
+        fn material_bind_group_layout(&self) -> Option<&wgpu::BindGroupLayout> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_update_transformation() {
+        let mesh = Mesh::new_ref(EmptyGeometry, EmptyMaterial);
+        {
+            let mut mesh = mesh.borrow_mut();
+            let transformation = mesh.transformation_mut();
+            transformation.translate(&Vector3::new(1.0, 2.0, 3.0));
+            transformation.set_scaling(&Vector3::new(2.0, 1.0, 1.0));
+            mesh.update_recursive(&Matrix4::one());
+        }
+
+        let mesh = mesh.borrow();
+        let origin = mesh.data.transformation() * Vector4::new(0.0, 0.0, 0.0, 1.0);
+        assert_eq!(origin, Vector4::new(1.0, 2.0, 3.0, 1.0));
+        let normal = mesh.data.normal_transformation() * Vector3::new(1.0, 0.0, 0.0);
+        assert_eq!(normal, Vector3::new(0.5, 0.0, 0.0));
+        let bytes = bytemuck::bytes_of(&mesh.data);
+        assert_eq!(&bytes[64..80], bytemuck::bytes_of(&[0.5f32, 0.0, 0.0, 0.0]));
+    }
 }
@@ -1,13 +1,31 @@
 pub mod bindable;
 pub mod camera;
+pub mod camera_controller;
 pub mod component;
+pub mod environment;
 pub mod geometry;
 pub mod group;
 pub mod hitbox;
+#[cfg(feature = "hot-reload")]
+pub mod hot_reload;
 pub mod light;
+pub mod light_array;
+pub mod light_clusters;
 pub mod material;
 pub mod mesh;
+pub mod post_effect;
+pub mod projection;
+pub mod reflection;
 pub mod render_pass_wrapper;
+pub mod render_target;
+pub mod render_texture;
+pub mod render_window;
 pub mod scene;
+pub mod shader;
+pub mod shadow_cascades;
+pub mod shadow_maps;
 pub mod state;
+pub mod texture;
 pub mod transformation;
+pub mod uniform_buffer;
+pub mod viewport;
@@ -0,0 +1,96 @@
+use crate::{RenderTargetFormat, State, Texture, DEFAULT_COLOR_FORMAT};
+
+/// A full screen effect in the post-processing stack of a camera, such as
+/// anti-aliasing, bloom or color grading.
+pub trait PostEffect {
+    /// Records the passes of the effect, reading `input`, which has the size
+    /// of the camera's target, and drawing over the viewport of `output`.
+    fn render(
+        &mut self,
+        input: &Texture,
+        output: &PostOutput,
+        encoder: &mut wgpu::CommandEncoder,
+        state: &State,
+    );
+}
+
+/// Where a post-processing pass draws: the next texture of the stack, or the
+/// target of the camera for the last pass.
+pub struct PostOutput<'a> {
+    pub view: &'a wgpu::TextureView,
+    pub resolve_target: Option<&'a wgpu::TextureView>,
+    pub load: wgpu::LoadOp<wgpu::Color>,
+    pub format: RenderTargetFormat,
+    /// Whether to blend over what the output holds by alpha, which the last
+    /// pass does so that cameras without a background stay overlays.
+    pub blend: bool,
+    /// Size of the output in pixels.
+    pub size: [u32; 2],
+    /// Pixels drawn into, as x, y, width and height.
+    pub viewport: [u32; 4],
+    pub scissor: [u32; 4],
+}
+
+impl PostOutput<'_> {
+    pub fn color_state(&self) -> wgpu::ColorStateDescriptor {
+        let (color_blend, alpha_blend) = if self.blend {
+            (
+                wgpu::BlendDescriptor {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                wgpu::BlendDescriptor {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                    operation: wgpu::BlendOperation::Add,
+                },
+            )
+        } else {
+            (
+                wgpu::BlendDescriptor::REPLACE,
+                wgpu::BlendDescriptor::REPLACE,
+            )
+        };
+        wgpu::ColorStateDescriptor {
+            format: self.format.color_format.unwrap_or(DEFAULT_COLOR_FORMAT),
+            color_blend,
+            alpha_blend,
+            write_mask: wgpu::ColorWrite::ALL,
+        }
+    }
+
+    /// Begins a pass drawing into the output, limited to its viewport and
+    /// scissor.
+    pub fn begin_render_pass<'a>(
+        &'a self,
+        encoder: &'a mut wgpu::CommandEncoder,
+    ) -> wgpu::RenderPass<'a> {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: self.view,
+                resolve_target: self.resolve_target,
+                ops: wgpu::Operations {
+                    load: self.load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_viewport(
+            self.viewport[0] as f32,
+            self.viewport[1] as f32,
+            self.viewport[2] as f32,
+            self.viewport[3] as f32,
+            0.0,
+            1.0,
+        );
+        render_pass.set_scissor_rect(
+            self.scissor[0],
+            self.scissor[1],
+            self.scissor[2],
+            self.scissor[3],
+        );
+        render_pass
+    }
+}
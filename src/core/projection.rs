@@ -0,0 +1,221 @@
+use cgmath::{InnerSpace, Matrix, Matrix4, Rad, Vector3, Vector4};
+
+use crate::camera::render_camera::OPENGL_TO_WGPU_MATRIX;
+
+/// Maps a depth of 0 to 1 and 1 to 0, applied after `OPENGL_TO_WGPU_MATRIX`.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+pub const REVERSE_Z_MATRIX: Matrix4<f32> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, -1.0, 0.0,
+    0.0, 0.0, 1.0, 1.0,
+);
+
+/// How a camera maps view space to clip space. Every variant but `OffCenter`
+/// and `Custom` follows the aspect ratio of the render target.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Projection {
+    /// `fov_y` is the vertical field of view; a `far` of `None` puts the far
+    /// plane at infinity.
+    Perspective {
+        fov_y: Rad<f32>,
+        near: f32,
+        far: Option<f32>,
+    },
+    /// `height` of the view volume, its width follows the aspect ratio.
+    Orthographic { height: f32, near: f32, far: f32 },
+    /// A perspective frustum with the given extents on the near plane, for
+    /// example for stereo or tiled rendering.
+    OffCenter {
+        left: f32,
+        right: f32,
+        bottom: f32,
+        top: f32,
+        near: f32,
+        far: f32,
+    },
+    /// An OpenGL style projection matrix, used as is.
+    Custom(Matrix4<f32>),
+}
+
+impl Projection {
+    pub fn perspective<A: Into<Rad<f32>>>(fov_y: A, near: f32, far: f32) -> Projection {
+        Projection::Perspective {
+            fov_y: fov_y.into(),
+            near,
+            far: Some(far),
+        }
+    }
+
+    pub fn infinite_perspective<A: Into<Rad<f32>>>(fov_y: A, near: f32) -> Projection {
+        Projection::Perspective {
+            fov_y: fov_y.into(),
+            near,
+            far: None,
+        }
+    }
+
+    pub fn orthographic(height: f32, near: f32, far: f32) -> Projection {
+        Projection::Orthographic { height, near, far }
+    }
+
+    /// OpenGL style matrix, with depths from -1 at the near plane to 1 at the far plane.
+    pub fn matrix(&self, aspect: f32) -> Matrix4<f32> {
+        match *self {
+            Projection::Perspective {
+                fov_y,
+                near,
+                far: Some(far),
+            } => cgmath::perspective(fov_y, aspect, near, far),
+            Projection::Perspective {
+                fov_y,
+                near,
+                far: None,
+            } => {
+                let f = 1.0 / (fov_y.0 / 2.0).tan();
+                // The limit of `cgmath::perspective` as far goes to infinity.
+                Matrix4::new(
+                    f / aspect,
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                    f,
+                    0.0,
+                    0.0,
+                    0.0,
+                    0.0,
+                    -1.0,
+                    -1.0,
+                    0.0,
+                    0.0,
+                    -2.0 * near,
+                    0.0,
+                )
+            }
+            Projection::Orthographic { height, near, far } => {
+                let half_height = height / 2.0;
+                let half_width = half_height * aspect;
+                cgmath::ortho(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    near,
+                    far,
+                )
+            }
+            Projection::OffCenter {
+                left,
+                right,
+                bottom,
+                top,
+                near,
+                far,
+            } => cgmath::frustum(left, right, bottom, top, near, far),
+            Projection::Custom(matrix) => matrix,
+        }
+    }
+
+    /// Matrix to the clip space of wgpu, depths from 0 to 1 or from 1 to 0
+    /// with `reversed_z`, which spreads the precision of float depth buffers
+    /// more evenly.
+    pub fn clip_matrix(&self, aspect: f32, reversed_z: bool) -> Matrix4<f32> {
+        let matrix = OPENGL_TO_WGPU_MATRIX * self.matrix(aspect);
+        if reversed_z {
+            REVERSE_Z_MATRIX * matrix
+        } else {
+            matrix
+        }
+    }
+}
+
+/// The six planes bounding a view volume, as (normal, distance) with normals
+/// pointing inwards: left, right, bottom, top, near and far. The far plane of
+/// an infinite projection is all zeros, which contains every point.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Frustum {
+    pub planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    /// Planes of the volume mapped into clip space by `matrix`, an OpenGL style
+    /// projection possibly multiplied by a view matrix.
+    pub fn from_matrix(matrix: &Matrix4<f32>) -> Frustum {
+        let row = |i| matrix.row(i);
+        let normalize = |plane: Vector4<f32>| {
+            let length = plane.truncate().magnitude();
+            if length > 0.0 {
+                plane / length
+            } else {
+                Vector4::new(0.0, 0.0, 0.0, 0.0)
+            }
+        };
+        Frustum {
+            planes: [
+                normalize(row(3) + row(0)),
+                normalize(row(3) - row(0)),
+                normalize(row(3) + row(1)),
+                normalize(row(3) - row(1)),
+                normalize(row(3) + row(2)),
+                normalize(row(3) - row(2)),
+            ],
+        }
+    }
+
+    /// Signed distance of a point to each plane is at least `-radius`.
+    pub fn intersects_sphere(&self, center: Vector3<f32>, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.truncate().dot(center) + plane.w >= -radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::Deg;
+
+    #[test]
+    fn test_infinite_perspective() {
+        let finite = Projection::perspective(Deg(60.0), 0.5, 1e7).matrix(1.5);
+        let infinite = Projection::infinite_perspective(Deg(60.0), 0.5).matrix(1.5);
+        for i in 0..4 {
+            for j in 0..4 {
+                assert!((finite[i][j] - infinite[i][j]).abs() < 1e-4);
+            }
+        }
+
+        // The near plane maps to 1 and distant points towards 0 when reversed.
+        let reversed = Projection::infinite_perspective(Deg(60.0), 0.5).clip_matrix(1.5, true);
+        let depth = |distance: f32| {
+            let clip = reversed * Vector4::new(0.0, 0.0, -distance, 1.0);
+            clip.z / clip.w
+        };
+        assert!((depth(0.5) - 1.0).abs() < 1e-6);
+        assert!(depth(1e6) > 0.0 && depth(1e6) < 1e-5);
+    }
+
+    #[test]
+    fn test_aspect() {
+        let projection = Projection::orthographic(2.0, -1.0, 1.0);
+        let clip = projection.matrix(2.0) * Vector4::new(2.0, 1.0, 0.0, 1.0);
+        assert_eq!((clip.x, clip.y), (1.0, 1.0));
+    }
+
+    #[test]
+    fn test_frustum() {
+        let frustum =
+            Frustum::from_matrix(&Projection::perspective(Deg(90.0), 1.0, 10.0).matrix(1.0));
+        assert!(frustum.intersects_sphere(Vector3::new(0.0, 0.0, -5.0), 0.1));
+        assert!(frustum.intersects_sphere(Vector3::new(5.5, 0.0, -5.0), 1.0));
+        assert!(!frustum.intersects_sphere(Vector3::new(7.0, 0.0, -5.0), 1.0));
+        assert!(!frustum.intersects_sphere(Vector3::new(0.0, 0.0, 1.0), 0.5));
+        assert!(!frustum.intersects_sphere(Vector3::new(0.0, 0.0, -12.0), 1.0));
+
+        let infinite =
+            Frustum::from_matrix(&Projection::infinite_perspective(Deg(90.0), 1.0).matrix(1.0));
+        assert!(infinite.intersects_sphere(Vector3::new(0.0, 0.0, -1e6), 0.1));
+    }
+}
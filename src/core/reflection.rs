@@ -0,0 +1,679 @@
+use std::collections::HashMap;
+
+use crate::ShaderError;
+
+const OP_NAME: u32 = 5;
+const OP_MEMBER_NAME: u32 = 6;
+const OP_TYPE_BOOL: u32 = 20;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_IMAGE: u32 = 25;
+const OP_TYPE_SAMPLER: u32 = 26;
+const OP_TYPE_SAMPLED_IMAGE: u32 = 27;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+
+const DECORATION_BUFFER_BLOCK: u32 = 3;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+
+const STORAGE_CLASS_UNIFORM_CONSTANT: u32 = 0;
+const STORAGE_CLASS_UNIFORM: u32 = 2;
+const STORAGE_CLASS_STORAGE_BUFFER: u32 = 12;
+
+const SPIRV_MAGIC: u32 = 0x0723_0203;
+
+/// Type of a uniform block member as seen from Rust.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum UniformType {
+    Float,
+    Int,
+    Uint,
+    Bool,
+    Vec2,
+    Vec3,
+    Vec4,
+    Mat3,
+    Mat4,
+    /// Arrays, nested structs and other types without a typed setter.
+    Other,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReflectedMember {
+    pub name: String,
+    pub offset: u32,
+    pub ty: UniformType,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReflectedBindingType {
+    UniformBlock {
+        size: u32,
+        members: Vec<ReflectedMember>,
+    },
+    Texture {
+        dimension: wgpu::TextureViewDimension,
+        multisampled: bool,
+    },
+    Sampler,
+    StorageBuffer,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReflectedBinding {
+    pub set: u32,
+    pub binding: u32,
+    pub name: String,
+    pub ty: ReflectedBindingType,
+}
+
+#[derive(Clone, Debug)]
+enum SpirVType {
+    Bool,
+    Int,
+    Uint,
+    Float,
+    Vector { component: u32, count: u32 },
+    Matrix { column: u32, count: u32 },
+    Image(ImageType),
+    Sampler,
+    SampledImage,
+    Array { element: u32, length: u32 },
+    Struct(Vec<u32>),
+    Pointer { pointee: u32 },
+}
+
+#[derive(Clone, Debug)]
+struct ImageType {
+    dim: u32,
+    depth: bool,
+    arrayed: bool,
+    multisampled: bool,
+}
+
+/// Extracts the descriptor bindings of a SPIR-V module, which is all that is
+/// needed to build bind group layouts for it.
+pub fn reflect_bindings(words: &[u32]) -> Result<Vec<ReflectedBinding>, ShaderError> {
+    if words.len() < 5 || words[0] != SPIRV_MAGIC {
+        return Err(ShaderError::InvalidSpirV(
+            "missing SPIR-V header".to_string(),
+        ));
+    }
+
+    let mut names = HashMap::new();
+    let mut member_names = HashMap::new();
+    let mut types = HashMap::new();
+    let mut constants = HashMap::new();
+    let mut variables = Vec::new();
+    let mut sets = HashMap::new();
+    let mut bindings = HashMap::new();
+    let mut buffer_blocks = Vec::new();
+    let mut array_strides = HashMap::new();
+    let mut member_offsets = HashMap::new();
+    let mut matrix_strides = HashMap::new();
+
+    let mut i = 5;
+    while i < words.len() {
+        let word_count = (words[i] >> 16) as usize;
+        let opcode = words[i] & 0xffff;
+        if word_count == 0 || i + word_count > words.len() {
+            return Err(ShaderError::InvalidSpirV(format!(
+                "truncated instruction at word {}",
+                i
+            )));
+        }
+        let operands = &words[i + 1..i + word_count];
+        let operand = |index: usize| {
+            operands.get(index).copied().ok_or_else(|| {
+                ShaderError::InvalidSpirV(format!("missing operand of opcode {}", opcode))
+            })
+        };
+
+        match opcode {
+            OP_NAME => {
+                names.insert(operand(0)?, decode_string(&operands[1..]));
+            }
+            OP_MEMBER_NAME => {
+                member_names.insert((operand(0)?, operand(1)?), decode_string(&operands[2..]));
+            }
+            OP_TYPE_BOOL => {
+                types.insert(operand(0)?, SpirVType::Bool);
+            }
+            OP_TYPE_INT => {
+                let ty = if operand(2)? == 1 {
+                    SpirVType::Int
+                } else {
+                    SpirVType::Uint
+                };
+                types.insert(operand(0)?, ty);
+            }
+            OP_TYPE_FLOAT => {
+                types.insert(operand(0)?, SpirVType::Float);
+            }
+            OP_TYPE_VECTOR => {
+                let ty = SpirVType::Vector {
+                    component: operand(1)?,
+                    count: operand(2)?,
+                };
+                types.insert(operand(0)?, ty);
+            }
+            OP_TYPE_MATRIX => {
+                let ty = SpirVType::Matrix {
+                    column: operand(1)?,
+                    count: operand(2)?,
+                };
+                types.insert(operand(0)?, ty);
+            }
+            OP_TYPE_IMAGE => {
+                let ty = SpirVType::Image(ImageType {
+                    dim: operand(2)?,
+                    depth: operand(3)? == 1,
+                    arrayed: operand(4)? == 1,
+                    multisampled: operand(5)? == 1,
+                });
+                types.insert(operand(0)?, ty);
+            }
+            OP_TYPE_SAMPLER => {
+                types.insert(operand(0)?, SpirVType::Sampler);
+            }
+            OP_TYPE_SAMPLED_IMAGE => {
+                types.insert(operand(0)?, SpirVType::SampledImage);
+            }
+            OP_TYPE_ARRAY => {
+                let length = constants.get(&operand(2)?).copied().unwrap_or(0);
+                let ty = SpirVType::Array {
+                    element: operand(1)?,
+                    length,
+                };
+                types.insert(operand(0)?, ty);
+            }
+            OP_TYPE_STRUCT => {
+                types.insert(operand(0)?, SpirVType::Struct(operands[1..].to_vec()));
+            }
+            OP_TYPE_POINTER => {
+                types.insert(
+                    operand(0)?,
+                    SpirVType::Pointer {
+                        pointee: operand(2)?,
+                    },
+                );
+            }
+            OP_CONSTANT => {
+                constants.insert(operand(1)?, operand(2)?);
+            }
+            OP_VARIABLE => {
+                let storage_class = operand(2)?;
+                if storage_class == STORAGE_CLASS_UNIFORM_CONSTANT
+                    || storage_class == STORAGE_CLASS_UNIFORM
+                    || storage_class == STORAGE_CLASS_STORAGE_BUFFER
+                {
+                    variables.push((operand(1)?, operand(0)?, storage_class));
+                }
+            }
+            OP_DECORATE => match operand(1)? {
+                DECORATION_DESCRIPTOR_SET => {
+                    sets.insert(operand(0)?, operand(2)?);
+                }
+                DECORATION_BINDING => {
+                    bindings.insert(operand(0)?, operand(2)?);
+                }
+                DECORATION_BUFFER_BLOCK => buffer_blocks.push(operand(0)?),
+                DECORATION_ARRAY_STRIDE => {
+                    array_strides.insert(operand(0)?, operand(2)?);
+                }
+                _ => {}
+            },
+            OP_MEMBER_DECORATE => match operand(2)? {
+                DECORATION_OFFSET => {
+                    member_offsets.insert((operand(0)?, operand(1)?), operand(3)?);
+                }
+                DECORATION_MATRIX_STRIDE => {
+                    matrix_strides.insert((operand(0)?, operand(1)?), operand(3)?);
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+
+        i += word_count;
+    }
+
+    let reflector = Reflector {
+        types: &types,
+        array_strides: &array_strides,
+        member_offsets: &member_offsets,
+        matrix_strides: &matrix_strides,
+    };
+
+    let mut result = Vec::new();
+    for (id, pointer_type, storage_class) in variables {
+        let (set, binding) = match (sets.get(&id), bindings.get(&id)) {
+            (Some(set), Some(binding)) => (*set, *binding),
+            _ => continue,
+        };
+        let unsupported = |reason: &str| ShaderError::UnsupportedBinding {
+            set,
+            binding,
+            reason: reason.to_string(),
+        };
+        let type_id = match types.get(&pointer_type) {
+            Some(SpirVType::Pointer { pointee }) => *pointee,
+            _ => return Err(unsupported("variable is not a pointer")),
+        };
+
+        let ty = match types.get(&type_id) {
+            Some(SpirVType::Struct(_))
+                if storage_class == STORAGE_CLASS_STORAGE_BUFFER
+                    || buffer_blocks.contains(&type_id) =>
+            {
+                ReflectedBindingType::StorageBuffer
+            }
+            Some(SpirVType::Struct(member_types)) => ReflectedBindingType::UniformBlock {
+                size: reflector.size(type_id),
+                members: member_types
+                    .iter()
+                    .enumerate()
+                    .map(|(member, member_type)| ReflectedMember {
+                        name: member_names
+                            .get(&(type_id, member as u32))
+                            .cloned()
+                            .unwrap_or_default(),
+                        offset: member_offsets
+                            .get(&(type_id, member as u32))
+                            .copied()
+                            .unwrap_or(0),
+                        ty: reflector.uniform_type(*member_type),
+                    })
+                    .collect(),
+            },
+            Some(SpirVType::Image(image)) if image.depth => {
+                return Err(unsupported("depth textures are not supported"))
+            }
+            Some(SpirVType::Image(image)) => ReflectedBindingType::Texture {
+                dimension: match (image.dim, image.arrayed) {
+                    (0, false) => wgpu::TextureViewDimension::D1,
+                    (1, false) => wgpu::TextureViewDimension::D2,
+                    (1, true) => wgpu::TextureViewDimension::D2Array,
+                    (2, false) => wgpu::TextureViewDimension::D3,
+                    (3, false) => wgpu::TextureViewDimension::Cube,
+                    (3, true) => wgpu::TextureViewDimension::CubeArray,
+                    _ => return Err(unsupported("unsupported image dimension")),
+                },
+                multisampled: image.multisampled,
+            },
+            Some(SpirVType::Sampler) => ReflectedBindingType::Sampler,
+            Some(SpirVType::SampledImage) => {
+                return Err(unsupported(
+                    "combined image samplers are not supported, declare a separate \
+                     `texture2D` and `sampler` instead",
+                ))
+            }
+            _ => return Err(unsupported("unsupported resource type")),
+        };
+
+        let name = names
+            .get(&id)
+            .filter(|name| !name.is_empty())
+            .or_else(|| names.get(&type_id))
+            .cloned()
+            .unwrap_or_default();
+
+        result.push(ReflectedBinding {
+            set,
+            binding,
+            name,
+            ty,
+        });
+    }
+
+    result.sort_by_key(|binding| (binding.set, binding.binding));
+    Ok(result)
+}
+
+struct Reflector<'a> {
+    types: &'a HashMap<u32, SpirVType>,
+    array_strides: &'a HashMap<u32, u32>,
+    member_offsets: &'a HashMap<(u32, u32), u32>,
+    matrix_strides: &'a HashMap<(u32, u32), u32>,
+}
+
+impl Reflector<'_> {
+    fn uniform_type(&self, id: u32) -> UniformType {
+        match self.types.get(&id) {
+            Some(SpirVType::Float) => UniformType::Float,
+            Some(SpirVType::Int) => UniformType::Int,
+            Some(SpirVType::Uint) => UniformType::Uint,
+            Some(SpirVType::Bool) => UniformType::Bool,
+            Some(SpirVType::Vector { component, count })
+                if self.uniform_type(*component) == UniformType::Float =>
+            {
+                match count {
+                    2 => UniformType::Vec2,
+                    3 => UniformType::Vec3,
+                    4 => UniformType::Vec4,
+                    _ => UniformType::Other,
+                }
+            }
+            Some(SpirVType::Matrix { column, count }) => match (self.types.get(column), count) {
+                (Some(SpirVType::Vector { count: 3, .. }), 3) => UniformType::Mat3,
+                (Some(SpirVType::Vector { count: 4, .. }), 4) => UniformType::Mat4,
+                _ => UniformType::Other,
+            },
+            _ => UniformType::Other,
+        }
+    }
+
+    fn size(&self, id: u32) -> u32 {
+        match self.types.get(&id) {
+            Some(SpirVType::Bool)
+            | Some(SpirVType::Int)
+            | Some(SpirVType::Uint)
+            | Some(SpirVType::Float) => 4,
+            Some(SpirVType::Vector { component, count }) => self.size(*component) * count,
+            Some(SpirVType::Matrix { column, count }) => self.size(*column).max(16) * count,
+            Some(SpirVType::Array { element, length }) => {
+                let stride = self
+                    .array_strides
+                    .get(&id)
+                    .copied()
+                    .unwrap_or_else(|| self.size(*element));
+                stride * length
+            }
+            Some(SpirVType::Struct(member_types)) => {
+                let end = member_types
+                    .iter()
+                    .enumerate()
+                    .map(|(member, member_type)| {
+                        let key = (id, member as u32);
+                        let offset = self.member_offsets.get(&key).copied().unwrap_or(0);
+                        let size =
+                            match (self.types.get(member_type), self.matrix_strides.get(&key)) {
+                                (Some(SpirVType::Matrix { count, .. }), Some(stride)) => {
+                                    stride * count
+                                }
+                                _ => self.size(*member_type),
+                            };
+                        offset + size
+                    })
+                    .max()
+                    .unwrap_or(0);
+                // std140 pads structs to a multiple of 16 bytes.
+                (end + 15) & !15
+            }
+            _ => 0,
+        }
+    }
+}
+
+fn decode_string(words: &[u32]) -> String {
+    let bytes: Vec<u8> = words
+        .iter()
+        .flat_map(|word| word.to_le_bytes().to_vec())
+        .take_while(|byte| *byte != 0)
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instruction(opcode: u32, operands: &[u32]) -> Vec<u32> {
+        let mut result = vec![((operands.len() as u32 + 1) << 16) | opcode];
+        result.extend_from_slice(operands);
+        result
+    }
+
+    fn string(value: &str) -> Vec<u32> {
+        let mut bytes = value.as_bytes().to_vec();
+        bytes.resize((bytes.len() / 4 + 1) * 4, 0);
+        bytes
+            .chunks(4)
+            .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect()
+    }
+
+    fn name(id: u32, value: &str) -> Vec<u32> {
+        let mut operands = vec![id];
+        operands.extend(string(value));
+        instruction(OP_NAME, &operands)
+    }
+
+    fn member_name(id: u32, member: u32, value: &str) -> Vec<u32> {
+        let mut operands = vec![id, member];
+        operands.extend(string(value));
+        instruction(OP_MEMBER_NAME, &operands)
+    }
+
+    // layout(set=2, binding=0) uniform Parameters { vec4 tint; float strength; };
+    // layout(set=2, binding=1) uniform sampler s;
+    // layout(set=2, binding=2) uniform texture2D t;
+    // layout(set=2, binding=3) uniform sampler2D combined;
+    fn module(with_combined_sampler: bool) -> Vec<u32> {
+        let mut words = vec![SPIRV_MAGIC, 0x0001_0000, 0, 100, 0];
+        words.extend(name(10, "Parameters"));
+        words.extend(member_name(10, 0, "tint"));
+        words.extend(member_name(10, 1, "strength"));
+        words.extend(name(12, ""));
+        words.extend(name(14, "s"));
+        words.extend(name(17, "t"));
+        words.extend(instruction(
+            OP_DECORATE,
+            &[12, DECORATION_DESCRIPTOR_SET, 2],
+        ));
+        words.extend(instruction(OP_DECORATE, &[12, DECORATION_BINDING, 0]));
+        words.extend(instruction(
+            OP_MEMBER_DECORATE,
+            &[10, 0, DECORATION_OFFSET, 0],
+        ));
+        words.extend(instruction(
+            OP_MEMBER_DECORATE,
+            &[10, 1, DECORATION_OFFSET, 16],
+        ));
+        words.extend(instruction(
+            OP_DECORATE,
+            &[14, DECORATION_DESCRIPTOR_SET, 2],
+        ));
+        words.extend(instruction(OP_DECORATE, &[14, DECORATION_BINDING, 1]));
+        words.extend(instruction(
+            OP_DECORATE,
+            &[17, DECORATION_DESCRIPTOR_SET, 2],
+        ));
+        words.extend(instruction(OP_DECORATE, &[17, DECORATION_BINDING, 2]));
+        words.extend(instruction(
+            OP_DECORATE,
+            &[20, DECORATION_DESCRIPTOR_SET, 2],
+        ));
+        words.extend(instruction(OP_DECORATE, &[20, DECORATION_BINDING, 3]));
+        words.extend(instruction(OP_TYPE_FLOAT, &[1, 32]));
+        words.extend(instruction(OP_TYPE_VECTOR, &[2, 1, 4]));
+        words.extend(instruction(OP_TYPE_STRUCT, &[10, 2, 1]));
+        words.extend(instruction(
+            OP_TYPE_POINTER,
+            &[11, STORAGE_CLASS_UNIFORM, 10],
+        ));
+        words.extend(instruction(OP_VARIABLE, &[11, 12, STORAGE_CLASS_UNIFORM]));
+        words.extend(instruction(OP_TYPE_SAMPLER, &[13]));
+        words.extend(instruction(
+            OP_TYPE_POINTER,
+            &[15, STORAGE_CLASS_UNIFORM_CONSTANT, 13],
+        ));
+        words.extend(instruction(
+            OP_VARIABLE,
+            &[15, 14, STORAGE_CLASS_UNIFORM_CONSTANT],
+        ));
+        words.extend(instruction(OP_TYPE_IMAGE, &[16, 1, 1, 0, 0, 0, 1, 0]));
+        words.extend(instruction(
+            OP_TYPE_POINTER,
+            &[18, STORAGE_CLASS_UNIFORM_CONSTANT, 16],
+        ));
+        words.extend(instruction(
+            OP_VARIABLE,
+            &[18, 17, STORAGE_CLASS_UNIFORM_CONSTANT],
+        ));
+        if with_combined_sampler {
+            words.extend(instruction(OP_TYPE_SAMPLED_IMAGE, &[19, 16]));
+            words.extend(instruction(
+                OP_TYPE_POINTER,
+                &[21, STORAGE_CLASS_UNIFORM_CONSTANT, 19],
+            ));
+            words.extend(instruction(
+                OP_VARIABLE,
+                &[21, 20, STORAGE_CLASS_UNIFORM_CONSTANT],
+            ));
+        }
+        words
+    }
+
+    #[test]
+    fn test_reflect_bindings() {
+        let bindings = reflect_bindings(&module(false)).unwrap();
+        assert_eq!(
+            bindings,
+            vec![
+                ReflectedBinding {
+                    set: 2,
+                    binding: 0,
+                    name: "Parameters".to_string(),
+                    ty: ReflectedBindingType::UniformBlock {
+                        size: 32,
+                        members: vec![
+                            ReflectedMember {
+                                name: "tint".to_string(),
+                                offset: 0,
+                                ty: UniformType::Vec4,
+                            },
+                            ReflectedMember {
+                                name: "strength".to_string(),
+                                offset: 16,
+                                ty: UniformType::Float,
+                            },
+                        ],
+                    },
+                },
+                ReflectedBinding {
+                    set: 2,
+                    binding: 1,
+                    name: "s".to_string(),
+                    ty: ReflectedBindingType::Sampler,
+                },
+                ReflectedBinding {
+                    set: 2,
+                    binding: 2,
+                    name: "t".to_string(),
+                    ty: ReflectedBindingType::Texture {
+                        dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reflect_combined_sampler() {
+        match reflect_bindings(&module(true)) {
+            Err(ShaderError::UnsupportedBinding { set, binding, .. }) => {
+                assert_eq!((set, binding), (2, 3))
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_reflect_depth_texture() {
+        let mut words = module(false);
+        let image = words
+            .iter()
+            .position(|word| *word == (9 << 16 | OP_TYPE_IMAGE))
+            .unwrap();
+        words[image + 4] = 1;
+        match reflect_bindings(&words) {
+            Err(ShaderError::UnsupportedBinding { set, binding, .. }) => {
+                assert_eq!((set, binding), (2, 2))
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_reflect_glsl() {
+        let spirv = crate::compile_glsl(
+            r#"
+            #version 450
+            layout(set = 2, binding = 0) uniform Parameters {
+                mat3 rotation;
+                float strength;
+            };
+            layout(set = 2, binding = 1) uniform sampler s;
+            layout(set = 2, binding = 2) uniform texture2DArray layers;
+            layout(location = 0) out vec4 f_color;
+            void main() {
+                f_color = texture(sampler2DArray(layers, s), rotation * vec3(strength));
+            }
+            "#,
+            shaderc::ShaderKind::Fragment,
+            "test_reflect_glsl.frag",
+        )
+        .unwrap();
+        let bindings = reflect_bindings(&spirv).unwrap();
+        assert_eq!(
+            bindings[0].ty,
+            ReflectedBindingType::UniformBlock {
+                size: 64,
+                members: vec![
+                    ReflectedMember {
+                        name: "rotation".to_string(),
+                        offset: 0,
+                        ty: UniformType::Mat3,
+                    },
+                    ReflectedMember {
+                        name: "strength".to_string(),
+                        offset: 48,
+                        ty: UniformType::Float,
+                    },
+                ],
+            }
+        );
+        assert_eq!(bindings[1].ty, ReflectedBindingType::Sampler);
+        assert_eq!(
+            bindings[2].ty,
+            ReflectedBindingType::Texture {
+                dimension: wgpu::TextureViewDimension::D2Array,
+                multisampled: false,
+            }
+        );
+
+        let spirv = crate::compile_glsl(
+            r#"
+            #version 450
+            layout(set = 2, binding = 0) readonly buffer Values { vec4 values[]; };
+            layout(location = 0) out vec4 f_color;
+            void main() {
+                f_color = values[0];
+            }
+            "#,
+            shaderc::ShaderKind::Fragment,
+            "test_reflect_glsl_storage.frag",
+        )
+        .unwrap();
+        let bindings = reflect_bindings(&spirv).unwrap();
+        assert_eq!(bindings[0].ty, ReflectedBindingType::StorageBuffer);
+    }
+
+    #[test]
+    fn test_reflect_invalid() {
+        assert!(reflect_bindings(&[1, 2, 3]).is_err());
+    }
+}
@@ -1,12 +1,17 @@
 use std::ops::{Deref, DerefMut};
 
+use crate::Bindable;
 use crate::CameraUniform;
+use crate::Geometry;
 use crate::MeshUniform;
+use crate::State;
+use crate::{GEOMETRY_BIND_GROUP, MATERIAL_BIND_GROUP};
 
 pub struct RenderPassWrapper<'a, 'b> {
     pub render_pass: &'b mut wgpu::RenderPass<'a>,
     pub mesh_uniform: &'a MeshUniform,
     pub camera_uniform: &'a CameraUniform,
+    pub state: &'a State,
 }
 
 impl<'a> RenderPassWrapper<'a, '_> {
@@ -25,6 +30,19 @@ impl<'a> RenderPassWrapper<'a, '_> {
             bytemuck::cast_slice(&[*self.mesh_uniform]),
         );
     }
+
+    pub fn set_bind_groups(&mut self, geometry: &'a dyn Geometry, material: &'a dyn Bindable) {
+        self.render_pass.set_bind_group(
+            GEOMETRY_BIND_GROUP,
+            geometry.bind_group().unwrap_or(&self.state.empty_bind_group),
+            &[],
+        );
+        self.render_pass.set_bind_group(
+            MATERIAL_BIND_GROUP,
+            material.bind_group().unwrap_or(&self.state.empty_bind_group),
+            &[],
+        );
+    }
 }
 
 impl<'a> Deref for RenderPassWrapper<'a, '_> {
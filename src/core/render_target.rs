@@ -0,0 +1,71 @@
+use std::rc::Rc;
+
+use crate::Texture;
+
+/// Format of window swap chains.
+pub const DEFAULT_COLOR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8UnormSrgb;
+pub const DEFAULT_DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// What a render pipeline must agree on with the render pass it draws in.
+/// Meshes keep a pipeline for each target they were drawn into.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RenderTargetFormat {
+    /// Format of the color attachment, or `None` for depth only passes.
+    pub color_format: Option<wgpu::TextureFormat>,
+    /// Format of the depth buffer, or `None` without one.
+    pub depth_format: Option<wgpu::TextureFormat>,
+    /// The depth buffer is cleared to 0 and depth comparisons are flipped.
+    pub reversed_z: bool,
+    /// Samples per pixel of the color and depth attachments.
+    pub sample_count: u32,
+}
+
+impl Default for RenderTargetFormat {
+    fn default() -> Self {
+        RenderTargetFormat {
+            color_format: Some(DEFAULT_COLOR_FORMAT),
+            depth_format: Some(DEFAULT_DEPTH_FORMAT),
+            reversed_z: false,
+            sample_count: 1,
+        }
+    }
+}
+
+impl RenderTargetFormat {
+    pub fn has_stencil(&self) -> bool {
+        self.depth_format == Some(wgpu::TextureFormat::Depth24PlusStencil8)
+    }
+
+    /// Depth the depth buffer is cleared to, the farthest possible.
+    pub fn clear_depth(&self) -> f32 {
+        if self.reversed_z {
+            0.0
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Color attachment of a render pass drawing into a target.
+pub struct FrameAttachment<'a> {
+    pub view: &'a wgpu::TextureView,
+    pub resolve_target: Option<&'a wgpu::TextureView>,
+    /// No pass drew into the target this frame, so it must be cleared.
+    pub first: bool,
+}
+
+/// What cameras draw into: a window or a texture.
+pub trait RenderTarget {
+    fn size(&self) -> [u32; 2];
+    fn format(&self) -> wgpu::TextureFormat;
+    /// Where a pass with `sample_count` samples draws. Cameras with MSAA share
+    /// one multisampled attachment resolved into the target by every pass, so
+    /// all cameras of a target should use the same sample count.
+    fn attachment(&mut self, sample_count: u32) -> FrameAttachment<'_>;
+    /// The texture materials can sample the result from, if any.
+    fn texture(&self) -> Option<&Rc<Texture>> {
+        None
+    }
+    /// Called once all cameras rendered; the next pass starts a new frame.
+    fn finish_frame(&mut self);
+}
@@ -0,0 +1,97 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{FrameAttachment, RenderTarget, State, Texture};
+
+pub const DEFAULT_RENDER_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// A texture cameras render into and materials sample from, for monitors,
+/// portals and mirrors. Meshes whose material samples the texture are left out
+/// when rendering into it.
+pub struct RenderTexture {
+    state: State,
+    texture: Rc<Texture>,
+    /// Whether a pass drew into the texture this frame.
+    drawn: bool,
+    msaa_texture: Option<(u32, Texture)>,
+}
+
+impl RenderTexture {
+    pub fn new_ref(
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        state: &State,
+    ) -> Rc<RefCell<RenderTexture>> {
+        let texture = Texture::new(
+            width.max(1),
+            height.max(1),
+            format,
+            wgpu::TextureUsage::OUTPUT_ATTACHMENT
+                | wgpu::TextureUsage::SAMPLED
+                | wgpu::TextureUsage::COPY_SRC,
+            Some("Render Texture"),
+            state,
+        );
+        Rc::new(RefCell::new(RenderTexture {
+            state: state.clone(),
+            texture: Rc::new(texture),
+            drawn: false,
+            msaa_texture: None,
+        }))
+    }
+}
+
+impl RenderTarget for RenderTexture {
+    fn size(&self) -> [u32; 2] {
+        [self.texture.size.width, self.texture.size.height]
+    }
+
+    fn format(&self) -> wgpu::TextureFormat {
+        self.texture.format
+    }
+
+    fn attachment(&mut self, sample_count: u32) -> FrameAttachment<'_> {
+        let first = !self.drawn;
+        self.drawn = true;
+
+        let recreate = match self.msaa_texture {
+            Some((count, _)) => count != sample_count,
+            None => true,
+        };
+        if sample_count > 1 && recreate {
+            let texture = Texture::new_multisampled(
+                self.texture.size.width,
+                self.texture.size.height,
+                self.texture.format,
+                wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+                sample_count,
+                Some("MSAA Texture"),
+                &self.state,
+            );
+            self.msaa_texture = Some((sample_count, texture));
+        }
+
+        match self.msaa_texture {
+            Some((count, ref texture)) if sample_count > 1 && count == sample_count => {
+                FrameAttachment {
+                    view: &texture.view,
+                    resolve_target: Some(&self.texture.view),
+                    first,
+                }
+            }
+            _ => FrameAttachment {
+                view: &self.texture.view,
+                resolve_target: None,
+                first,
+            },
+        }
+    }
+
+    fn texture(&self) -> Option<&Rc<Texture>> {
+        Some(&self.texture)
+    }
+
+    fn finish_frame(&mut self) {
+        self.drawn = false;
+    }
+}
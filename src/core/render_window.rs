@@ -0,0 +1,130 @@
+use std::{cell::RefCell, rc::Rc};
+
+use winit::window::{Window, WindowId};
+
+use crate::{FrameAttachment, RenderTarget, State, Texture, DEFAULT_COLOR_FORMAT};
+
+/// The swap chain of a window, shared by the cameras drawing into it. A frame
+/// is acquired by the first camera rendering and shown by `present`.
+pub struct RenderWindow {
+    state: State,
+    id: WindowId,
+    surface: wgpu::Surface,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    frame: Option<wgpu::SwapChainFrame>,
+    /// Whether a pass drew into `frame` yet.
+    drawn: bool,
+    /// Multisampled color attachment shared by all cameras using MSAA, and
+    /// its sample count.
+    msaa_texture: Option<(u32, Texture)>,
+}
+
+impl RenderWindow {
+    pub fn new_ref(window: &Window, state: &State) -> Rc<RefCell<RenderWindow>> {
+        let size = window.inner_size();
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+            format: DEFAULT_COLOR_FORMAT,
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+
+        let surface = unsafe { state.instance.create_surface(window) };
+        let swap_chain = state.device.create_swap_chain(&surface, &sc_desc);
+
+        Rc::new(RefCell::new(RenderWindow {
+            state: state.clone(),
+            id: window.id(),
+            surface,
+            sc_desc,
+            swap_chain,
+            frame: None,
+            drawn: false,
+            msaa_texture: None,
+        }))
+    }
+
+    pub fn id(&self) -> WindowId {
+        self.id
+    }
+
+    /// Recreates the swap chain; cameras follow at their next render.
+    pub fn resize(&mut self, size: winit::dpi::PhysicalSize<u32>) {
+        self.frame = None;
+        self.msaa_texture = None;
+        self.sc_desc.width = size.width;
+        self.sc_desc.height = size.height;
+        self.swap_chain = self
+            .state
+            .device
+            .create_swap_chain(&self.surface, &self.sc_desc);
+    }
+
+    /// Shows what the cameras rendered since the last call.
+    pub fn present(&mut self) {
+        self.frame = None;
+    }
+}
+
+impl RenderTarget for RenderWindow {
+    fn size(&self) -> [u32; 2] {
+        [self.sc_desc.width, self.sc_desc.height]
+    }
+
+    fn format(&self) -> wgpu::TextureFormat {
+        self.sc_desc.format
+    }
+
+    /// Acquires the frame if no camera did yet.
+    fn attachment(&mut self, sample_count: u32) -> FrameAttachment<'_> {
+        if self.frame.is_none() {
+            self.frame = Some(
+                self.swap_chain
+                    .get_current_frame()
+                    .expect("Timeout getting texture"),
+            );
+            self.drawn = false;
+        }
+        let first = !self.drawn;
+        self.drawn = true;
+
+        let recreate = match self.msaa_texture {
+            Some((count, _)) => count != sample_count,
+            None => true,
+        };
+        if sample_count > 1 && recreate {
+            let texture = Texture::new_multisampled(
+                self.sc_desc.width.max(1),
+                self.sc_desc.height.max(1),
+                self.sc_desc.format,
+                wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+                sample_count,
+                Some("MSAA Texture"),
+                &self.state,
+            );
+            self.msaa_texture = Some((sample_count, texture));
+        }
+
+        let frame = &self.frame.as_ref().unwrap().output.view;
+        match self.msaa_texture {
+            Some((count, ref texture)) if sample_count > 1 && count == sample_count => {
+                FrameAttachment {
+                    view: &texture.view,
+                    resolve_target: Some(frame),
+                    first,
+                }
+            }
+            _ => FrameAttachment {
+                view: frame,
+                resolve_target: None,
+                first,
+            },
+        }
+    }
+
+    fn finish_frame(&mut self) {
+        self.present();
+    }
+}
@@ -3,7 +3,7 @@ use std::{
     rc::{Rc, Weak},
 };
 
-use crate::{Component, Hitbox, Light, Mesh};
+use crate::{Component, Environment, Hitbox, Light, Mesh};
 
 pub struct Scene {
     this: Weak<RefCell<Scene>>,
@@ -12,6 +12,8 @@ pub struct Scene {
     pub meshes: Vec<Rc<RefCell<Mesh>>>,
     pub lights: Vec<Rc<RefCell<dyn Light>>>,
     pub hitboxes: Vec<Rc<RefCell<Hitbox>>>,
+    /// Image based lighting of every lit material, none by default.
+    pub environment: Option<Rc<RefCell<Environment>>>,
 }
 
 impl Scene {
@@ -23,6 +25,7 @@ impl Scene {
             meshes: Vec::new(),
             lights: Vec::new(),
             hitboxes: Vec::new(),
+            environment: None,
         };
 
         let scene = Rc::new(RefCell::new(scene));
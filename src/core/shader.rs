@@ -0,0 +1,133 @@
+use std::{error::Error, ffi::OsStr, fmt, fs, io, path::Path};
+
+use crate::UniformType;
+
+pub enum ShaderSource<'a> {
+    Glsl(&'a str),
+    SpirV(&'a [u32]),
+}
+
+#[derive(Debug)]
+pub enum ShaderError {
+    /// The GLSL source did not compile. Contains the compiler log.
+    Compilation { name: String, log: String },
+    Io { path: String, error: io::Error },
+    /// The SPIR-V module could not be parsed for reflection.
+    InvalidSpirV(String),
+    /// The shader declares a binding that cannot be expressed as a wgpu binding.
+    UnsupportedBinding { set: u32, binding: u32, reason: String },
+    UnknownUniform(String),
+    UniformTypeMismatch {
+        name: String,
+        expected: UniformType,
+        found: UniformType,
+    },
+    /// The reflected member does not fit into the reflected size of its block.
+    UniformOutOfBounds {
+        name: String,
+        offset: u32,
+        block_size: u32,
+    },
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShaderError::Compilation { name, log } => {
+                write!(f, "failed to compile shader `{}`:\n{}", name, log)
+            }
+            ShaderError::Io { path, error } => write!(f, "failed to read `{}`: {}", path, error),
+            ShaderError::InvalidSpirV(reason) => write!(f, "invalid SPIR-V: {}", reason),
+            ShaderError::UnsupportedBinding {
+                set,
+                binding,
+                reason,
+            } => write!(
+                f,
+                "unsupported binding (set = {}, binding = {}): {}",
+                set, binding, reason
+            ),
+            ShaderError::UnknownUniform(name) => write!(f, "no uniform named `{}`", name),
+            ShaderError::UniformTypeMismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "uniform `{}` has type {:?} but a {:?} was given",
+                name, expected, found
+            ),
+            ShaderError::UniformOutOfBounds {
+                name,
+                offset,
+                block_size,
+            } => write!(
+                f,
+                "uniform `{}` at offset {} lies outside its block of {} bytes",
+                name, offset, block_size
+            ),
+        }
+    }
+}
+
+impl Error for ShaderError {}
+
+/// Compiles GLSL into SPIR-V at runtime, turning compiler failures into a
+/// `ShaderError` carrying the compiler log instead of panicking.
+pub fn compile_glsl(
+    source: &str,
+    shader_kind: shaderc::ShaderKind,
+    name: &str,
+) -> Result<Vec<u32>, ShaderError> {
+    let mut compiler = shaderc::Compiler::new().ok_or_else(|| ShaderError::Compilation {
+        name: name.to_string(),
+        log: "could not initialize the shader compiler".to_string(),
+    })?;
+    compiler
+        .compile_into_spirv(source, shader_kind, name, "main", None)
+        .map(|artifact| artifact.as_binary().to_vec())
+        .map_err(|error| ShaderError::Compilation {
+            name: name.to_string(),
+            log: match error {
+                shaderc::Error::CompilationError(_, log) => log,
+                error => error.to_string(),
+            },
+        })
+}
+
+/// Reads a shader from disk: `.spv` files as SPIR-V, anything else as GLSL.
+pub fn load_shader(path: &Path, shader_kind: shaderc::ShaderKind) -> Result<Vec<u32>, ShaderError> {
+    let io_error = |error| ShaderError::Io {
+        path: path.display().to_string(),
+        error,
+    };
+    if path.extension() == Some(OsStr::new("spv")) {
+        let bytes = fs::read(path).map_err(io_error)?;
+        if bytes.len() % 4 != 0 {
+            return Err(ShaderError::InvalidSpirV(format!(
+                "`{}` is not a whole number of words",
+                path.display()
+            )));
+        }
+        Ok(bytes
+            .chunks(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect())
+    } else {
+        let source = fs::read_to_string(path).map_err(io_error)?;
+        compile_glsl(&source, shader_kind, &path.display().to_string())
+    }
+}
+
+impl ShaderSource<'_> {
+    pub fn to_spirv(
+        &self,
+        shader_kind: shaderc::ShaderKind,
+        name: &str,
+    ) -> Result<Vec<u32>, ShaderError> {
+        match self {
+            ShaderSource::Glsl(source) => compile_glsl(source, shader_kind, name),
+            ShaderSource::SpirV(words) => Ok(words.to_vec()),
+        }
+    }
+}
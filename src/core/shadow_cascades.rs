@@ -0,0 +1,190 @@
+use cgmath::{prelude::SquareMatrix, InnerSpace, Matrix4, MetricSpace, Point3, Vector3, Vector4};
+
+use crate::camera::render_camera::OPENGL_TO_WGPU_MATRIX;
+
+/// Splits the camera frustum of a directional light's shadow into `count`
+/// depth slices, each rendered into its own shadow map layer.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ShadowCascades {
+    pub count: u32,
+    /// Blends the split scheme from uniform (0) to logarithmic (1) slices.
+    pub split_lambda: f32,
+    /// Distance from the camera the cascades cover; nothing beyond is shadowed.
+    pub distance: f32,
+    /// Fraction of each cascade over which it fades into the next one.
+    pub blend: f32,
+}
+
+impl Default for ShadowCascades {
+    fn default() -> Self {
+        ShadowCascades {
+            count: 4,
+            split_lambda: 0.75,
+            distance: 200.0,
+            blend: 0.1,
+        }
+    }
+}
+
+impl ShadowCascades {
+    /// View space depth at which each cascade ends.
+    pub fn splits(&self, near: f32) -> Vec<f32> {
+        let far = self.distance.max(near);
+        (1..=self.count)
+            .map(|i| {
+                let t = i as f32 / self.count as f32;
+                let logarithmic = near * (far / near).powf(t);
+                let uniform = near + (far - near) * t;
+                self.split_lambda * logarithmic + (1.0 - self.split_lambda) * uniform
+            })
+            .collect()
+    }
+
+    /// Light projection covering the slice of a camera frustum between the
+    /// depths `near` and `far`. The slice is bounded by a sphere so
+    /// the projection keeps its size as the camera turns, and its center is
+    /// snapped to shadow map texels so that shadows do not shimmer.
+    pub fn fit(
+        light_direction: Vector3<f32>,
+        view_matrix: &Matrix4<f32>,
+        projection_matrix: &Matrix4<f32>,
+        near: f32,
+        far: f32,
+        shadow_map_size: u32,
+    ) -> Matrix4<f32> {
+        let inverse_projection = projection_matrix.invert().unwrap_or_else(Matrix4::identity);
+        let inverse_view = view_matrix.invert().unwrap_or_else(Matrix4::identity);
+
+        // The four edges of the frustum meet at the camera for perspective
+        // projections and run along the view direction for orthographic ones.
+        // Each is found from two points between the near and the far plane,
+        // which may be at infinity, and cut at the depths of the slice.
+        let mut corners = Vec::with_capacity(8);
+        for &(x, y) in &[(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+            let unproject = |z: f32| {
+                let point = inverse_projection * Vector4::new(x, y, z, 1.0);
+                point.truncate() / point.w
+            };
+            let (a, b) = (unproject(0.0), unproject(0.5));
+            for &depth in &[near, far] {
+                let corner = a + (b - a) * ((-depth - a.z) / (b.z - a.z));
+                corners.push((inverse_view * corner.extend(1.0)).truncate());
+            }
+        }
+        let center = corners
+            .iter()
+            .fold(Vector3::new(0.0, 0.0, 0.0), |sum, corner| sum + corner)
+            / corners.len() as f32;
+        let radius = corners
+            .iter()
+            .map(|corner| corner.distance(center))
+            .fold(0.0f32, f32::max);
+        // Rounding the radius up keeps it from changing with float noise.
+        let radius = (radius * 16.0).ceil() / 16.0;
+
+        let up = if light_direction.normalize().y.abs() > 0.99 {
+            Vector3::unit_x()
+        } else {
+            Vector3::unit_y()
+        };
+        let light_view = Matrix4::look_at_dir(Point3::new(0.0, 0.0, 0.0), light_direction, up);
+        let center = (light_view * center.extend(1.0)).truncate();
+        let texel = 2.0 * radius / shadow_map_size as f32;
+        let x = (center.x / texel).floor() * texel;
+        let y = (center.y / texel).floor() * texel;
+
+        // Casters in front of the near plane are kept by depth clamping.
+        OPENGL_TO_WGPU_MATRIX
+            * cgmath::ortho(
+                x - radius,
+                x + radius,
+                y - radius,
+                y + radius,
+                -center.z - radius,
+                -center.z + radius,
+            )
+            * light_view
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::{prelude::One, Deg, Matrix};
+
+    use crate::Projection;
+
+    #[test]
+    fn test_splits() {
+        let mut cascades = ShadowCascades {
+            count: 2,
+            split_lambda: 0.0,
+            distance: 101.0,
+            blend: 0.0,
+        };
+        assert_eq!(cascades.splits(1.0), vec![51.0, 101.0]);
+
+        cascades.split_lambda = 1.0;
+        cascades.distance = 100.0;
+        let splits = cascades.splits(1.0);
+        assert!((splits[0] - 10.0).abs() < 1e-4);
+        assert!((splits[1] - 100.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_fit_is_stable() {
+        let projection_matrix = cgmath::perspective(Deg(60.0), 1.0, 1.0, 100.0);
+        let direction = Vector3::new(0.3, -1.0, 0.2);
+        let fit = |view_matrix: &Matrix4<f32>| {
+            ShadowCascades::fit(direction, view_matrix, &projection_matrix, 1.0, 20.0, 1024)
+        };
+
+        // Every point of the slice lands inside the shadow map.
+        let matrix = fit(&Matrix4::one());
+        for &point in &[
+            Vector4::new(0.0, 0.0, -1.0, 1.0),
+            Vector4::new(0.0, 0.0, -20.0, 1.0),
+            Vector4::new(11.0, 11.0, -20.0, 1.0),
+        ] {
+            let clip = matrix * point;
+            assert!(clip.x.abs() <= 1.0 && clip.y.abs() <= 1.0);
+        }
+
+        // Turning the camera keeps the size of the projection.
+        let turned = fit(&Matrix4::from_angle_y(Deg(30.0)));
+        let scale = |matrix: Matrix4<f32>| matrix.row(0).truncate().magnitude();
+        assert!((scale(matrix) - scale(turned)).abs() < 1e-6);
+
+        // The projection only moves by whole texels.
+        let moved = fit(&Matrix4::from_translation(Vector3::new(0.37, 0.0, 0.0)));
+        let texels = moved.row(0).w * 1024.0 / 2.0;
+        assert!((texels - texels.round()).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_fit_orthographic() {
+        let projection_matrix = Projection::orthographic(10.0, 1.0, 100.0).matrix(1.0);
+        let matrix = ShadowCascades::fit(
+            Vector3::new(0.0, -1.0, 0.0),
+            &Matrix4::one(),
+            &projection_matrix,
+            1.0,
+            20.0,
+            1024,
+        );
+
+        for &point in &[
+            Vector4::new(-5.0, -5.0, -1.0, 1.0),
+            Vector4::new(5.0, 5.0, -20.0, 1.0),
+        ] {
+            let clip = matrix * point;
+            assert!(clip.x.abs() <= 1.0 && clip.y.abs() <= 1.0);
+        }
+
+        // The slice is a box of 10 by 10 by 19, bounded by a sphere of a
+        // radius of about 11.8.
+        let radius = 1.0 / matrix.row(0).truncate().magnitude();
+        assert!(radius < 12.0);
+    }
+}
@@ -0,0 +1,142 @@
+use std::num::NonZeroU32;
+
+use cgmath::{Matrix4, Point3, Vector3};
+
+use crate::State;
+
+pub const SHADOW_MAP_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Mirrors the std430 `ShadowLayer` struct bound at set 0, binding 5, one per
+/// shadow map layer.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct ShadowLayer {
+    pub projection_matrix: Matrix4<f32>,
+    /// View space depth at which a cascade ends, unused by other shadows.
+    pub split_depth: f32,
+    _padding: [u32; 3],
+}
+
+unsafe impl bytemuck::Zeroable for ShadowLayer {}
+unsafe impl bytemuck::Pod for ShadowLayer {}
+
+impl ShadowLayer {
+    pub fn new(projection_matrix: Matrix4<f32>, split_depth: f32) -> ShadowLayer {
+        ShadowLayer {
+            projection_matrix,
+            split_depth,
+            _padding: [0; 3],
+        }
+    }
+}
+
+/// Depth texture array with a layer per shadow casting light, sampled with a
+/// comparison sampler by lit shaders at set 0, bindings 3 and 4.
+pub struct ShadowMaps {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    layer_views: Vec<wgpu::TextureView>,
+    sampler: wgpu::Sampler,
+    size: u32,
+}
+
+impl ShadowMaps {
+    pub const DEFAULT_SIZE: u32 = 2048;
+    pub const DEFAULT_LAYERS: u32 = 8;
+
+    pub fn new(size: u32, layers: u32, state: &State) -> ShadowMaps {
+        let layers = layers.max(1);
+        let texture = state.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Shadow Maps"),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth: layers,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: SHADOW_MAP_FORMAT,
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("shadow_maps_view"),
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let layer_views = (0..layers)
+            .map(|layer| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("shadow_map_layer_view"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: NonZeroU32::new(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        let sampler = state.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("shadow_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        ShadowMaps {
+            texture,
+            view,
+            layer_views,
+            sampler,
+            size,
+        }
+    }
+
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn layers(&self) -> u32 {
+        self.layer_views.len() as u32
+    }
+
+    /// All layers, for sampling.
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    /// A single layer, for rendering into.
+    pub fn layer_view(&self, layer: u32) -> &wgpu::TextureView {
+        &self.layer_views[layer as usize]
+    }
+
+    pub fn sampler(&self) -> &wgpu::Sampler {
+        &self.sampler
+    }
+
+    /// Views from `position` through the six faces of a cube, in the order
+    /// +x, -x, +y, -y, +z, -z that lit shaders pick point light shadow layers by.
+    pub fn cube_face_views(position: Vector3<f32>) -> [Matrix4<f32>; 6] {
+        let eye = Point3::new(position.x, position.y, position.z);
+        let face =
+            |direction: Vector3<f32>, up: Vector3<f32>| Matrix4::look_at_dir(eye, direction, up);
+        [
+            face(Vector3::unit_x(), -Vector3::unit_y()),
+            face(-Vector3::unit_x(), -Vector3::unit_y()),
+            face(Vector3::unit_y(), Vector3::unit_z()),
+            face(-Vector3::unit_y(), -Vector3::unit_z()),
+            face(Vector3::unit_z(), -Vector3::unit_y()),
+            face(-Vector3::unit_z(), -Vector3::unit_y()),
+        ]
+    }
+}
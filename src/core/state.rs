@@ -1,15 +1,21 @@
 use std::ops::Deref;
 use std::rc::Rc;
 
+use crate::core::environment::EnvironmentUniform;
 use crate::CameraUniform;
-use crate::LightUniform;
+use crate::LightArray;
 use crate::MeshUniform;
 
 pub struct _State {
     pub instance: wgpu::Instance,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
+    /// Features the device was created with, optional ones included when supported.
+    pub features: wgpu::Features,
     pub light_bind_group_layout: wgpu::BindGroupLayout,
+    pub empty_bind_group_layout: wgpu::BindGroupLayout,
+    /// Bound in the geometry and material slots when nothing else is.
+    pub empty_bind_group: wgpu::BindGroup,
 }
 
 #[derive(Clone)]
@@ -34,10 +40,14 @@ impl State {
             .await
             .unwrap();
 
+        let features = wgpu::Features::PUSH_CONSTANTS
+            | wgpu::Features::DEPTH_CLAMPING
+            | (adapter.features() & wgpu::Features::NON_FILL_POLYGON_MODE);
+
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
-                    features: wgpu::Features::PUSH_CONSTANTS | wgpu::Features::DEPTH_CLAMPING,
+                    features,
                     limits: wgpu::Limits {
                         max_push_constant_size: (std::mem::size_of::<CameraUniform>()
                             + std::mem::size_of::<MeshUniform>())
@@ -51,27 +61,100 @@ impl State {
             .await
             .expect("Failed to create device");
 
+        // The light array, the light ranges of each cluster, the light indices
+        // and the shadow layers.
+        let storage_bindings = LightArray::storage_bindings();
+        let mut light_layout_entries: Vec<_> = storage_bindings
+            .iter()
+            .map(|&(binding, size)| wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::StorageBuffer {
+                    dynamic: false,
+                    min_binding_size: std::num::NonZeroU64::new(size),
+                    readonly: true,
+                },
+                count: None,
+            })
+            .collect();
+        // The shadow maps and their comparison sampler.
+        light_layout_entries.push(wgpu::BindGroupLayoutEntry {
+            binding: 3,
+            visibility: wgpu::ShaderStage::FRAGMENT,
+            ty: wgpu::BindingType::SampledTexture {
+                dimension: wgpu::TextureViewDimension::D2Array,
+                component_type: wgpu::TextureComponentType::Float,
+                multisampled: false,
+            },
+            count: None,
+        });
+        light_layout_entries.push(wgpu::BindGroupLayoutEntry {
+            binding: 4,
+            visibility: wgpu::ShaderStage::FRAGMENT,
+            ty: wgpu::BindingType::Sampler { comparison: true },
+            count: None,
+        });
+        // The environment's parameters, irradiance map, specular map, BRDF
+        // lookup table and sampler.
+        light_layout_entries.push(wgpu::BindGroupLayoutEntry {
+            binding: 6,
+            visibility: wgpu::ShaderStage::FRAGMENT,
+            ty: wgpu::BindingType::UniformBuffer {
+                dynamic: false,
+                min_binding_size: std::num::NonZeroU64::new(
+                    std::mem::size_of::<EnvironmentUniform>() as u64,
+                ),
+            },
+            count: None,
+        });
+        for &(binding, dimension) in &[
+            (7, wgpu::TextureViewDimension::Cube),
+            (8, wgpu::TextureViewDimension::Cube),
+            (9, wgpu::TextureViewDimension::D2),
+        ] {
+            light_layout_entries.push(wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::SampledTexture {
+                    dimension,
+                    component_type: wgpu::TextureComponentType::Float,
+                    multisampled: false,
+                },
+                count: None,
+            });
+        }
+        light_layout_entries.push(wgpu::BindGroupLayoutEntry {
+            binding: 10,
+            visibility: wgpu::ShaderStage::FRAGMENT,
+            ty: wgpu::BindingType::Sampler { comparison: false },
+            count: None,
+        });
         let light_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
-                    ty: wgpu::BindingType::UniformBuffer {
-                        dynamic: false,
-                        min_binding_size: std::num::NonZeroU64::new(
-                            std::mem::size_of::<LightUniform>() as u64,
-                        ),
-                    },
-                    count: None,
-                }],
+                entries: &light_layout_entries,
                 label: Some("light_bind_group_layout"),
             });
 
+        let empty_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[],
+                label: Some("empty_bind_group_layout"),
+            });
+
+        let empty_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &empty_bind_group_layout,
+            entries: &[],
+            label: Some("empty_bind_group"),
+        });
+
         Self(Rc::new(_State {
             instance,
             device,
             queue,
+            features,
             light_bind_group_layout,
+            empty_bind_group_layout,
+            empty_bind_group,
         }))
     }
 }
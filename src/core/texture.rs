@@ -0,0 +1,303 @@
+use std::num::NonZeroU32;
+
+use crate::State;
+
+pub struct Texture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub format: wgpu::TextureFormat,
+    pub size: wgpu::Extent3d,
+}
+
+impl Texture {
+    pub fn new(
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        usage: wgpu::TextureUsage,
+        label: Option<&str>,
+        state: &State,
+    ) -> Texture {
+        Texture::new_multisampled(width, height, format, usage, 1, label, state)
+    }
+
+    /// A render attachment with `sample_count` samples per pixel, resolved
+    /// into a single sampled texture at the end of a render pass.
+    pub fn new_multisampled(
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        usage: wgpu::TextureUsage,
+        sample_count: u32,
+        label: Option<&str>,
+        state: &State,
+    ) -> Texture {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth: 1,
+        };
+        let texture = state.device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size,
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Texture {
+            texture,
+            view,
+            format,
+            size,
+        }
+    }
+
+    /// Uploads an RGBA image. Color data (base color, emissive) should be
+    /// loaded with `srgb` set so that the sampler returns linear values.
+    pub fn from_image(
+        image: &image::RgbaImage,
+        srgb: bool,
+        label: Option<&str>,
+        state: &State,
+    ) -> Texture {
+        let format = if srgb {
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        } else {
+            wgpu::TextureFormat::Rgba8Unorm
+        };
+        let (width, height) = image.dimensions();
+        let result = Texture::new(
+            width,
+            height,
+            format,
+            wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+            label,
+            state,
+        );
+
+        state.queue.write_texture(
+            wgpu::TextureCopyView {
+                texture: &result.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            image,
+            wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row: 4 * width,
+                rows_per_image: height,
+            },
+            result.size,
+        );
+
+        result
+    }
+
+    /// A 1x1 texture of a single color, used in place of missing texture maps.
+    pub fn from_color(color: [u8; 4], srgb: bool, label: Option<&str>, state: &State) -> Texture {
+        Texture::from_image(
+            &image::RgbaImage::from_pixel(1, 1, image::Rgba(color)),
+            srgb,
+            label,
+            state,
+        )
+    }
+
+    /// Whether `to_image` can read back textures of `format`.
+    pub fn is_readable_format(format: wgpu::TextureFormat) -> bool {
+        matches!(
+            format,
+            wgpu::TextureFormat::Rgba8Unorm
+                | wgpu::TextureFormat::Rgba8UnormSrgb
+                | wgpu::TextureFormat::Bgra8Unorm
+                | wgpu::TextureFormat::Bgra8UnormSrgb
+        )
+    }
+
+    /// Reads `[x, y, width, height]` of an 8 bit RGBA or BGRA texture created
+    /// with `COPY_SRC` back into an image, waiting for the GPU. Other formats
+    /// are an unsupported error.
+    pub fn to_image(&self, rect: [u32; 4], state: &State) -> image::ImageResult<image::RgbaImage> {
+        if !Texture::is_readable_format(self.format) {
+            return Err(image::ImageError::Unsupported(
+                image::error::UnsupportedError::from_format_and_kind(
+                    image::error::ImageFormatHint::Unknown,
+                    image::error::UnsupportedErrorKind::GenericFeature(format!(
+                        "reading back a texture of format {:?}",
+                        self.format
+                    )),
+                ),
+            ));
+        }
+        let bgra = matches!(
+            self.format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+        let [x, y, width, height] = rect;
+        if width == 0 || height == 0 {
+            return Ok(image::RgbaImage::new(width, height));
+        }
+
+        let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padding = (alignment - 4 * width % alignment) % alignment;
+        let bytes_per_row = 4 * width + padding;
+        let buffer = state.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: (bytes_per_row * height) as u64,
+            usage: wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = state
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Readback Encoder"),
+            });
+        encoder.copy_texture_to_buffer(
+            wgpu::TextureCopyView {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+            },
+            wgpu::BufferCopyView {
+                buffer: &buffer,
+                layout: wgpu::TextureDataLayout {
+                    offset: 0,
+                    bytes_per_row,
+                    rows_per_image: height,
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+        );
+        state.queue.submit(Some(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let mapping = slice.map_async(wgpu::MapMode::Read);
+        state.device.poll(wgpu::Maintain::Wait);
+        futures::executor::block_on(mapping).expect("Failed to map readback buffer");
+        let pixels = Texture::unpad_rows(&slice.get_mapped_range(), width, bytes_per_row, bgra);
+        buffer.unmap();
+        Ok(image::RgbaImage::from_raw(width, height, pixels).unwrap())
+    }
+
+    /// Tightly packed RGBA rows from rows `bytes_per_row` apart.
+    fn unpad_rows(data: &[u8], width: u32, bytes_per_row: u32, bgra: bool) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(data.len());
+        for row in data.chunks(bytes_per_row as usize) {
+            for pixel in row[..4 * width as usize].chunks(4) {
+                if bgra {
+                    pixels.extend_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
+                } else {
+                    pixels.extend_from_slice(pixel);
+                }
+            }
+        }
+        pixels
+    }
+}
+
+/// Six square faces sampled by direction, ordered +x, -x, +y, -y, +z, -z.
+pub struct CubeTexture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub format: wgpu::TextureFormat,
+    pub size: u32,
+}
+
+impl CubeTexture {
+    /// Uploads six RGBA images of the same square size. Color data should be
+    /// loaded with `srgb` set.
+    pub fn from_images(
+        faces: [&image::RgbaImage; 6],
+        srgb: bool,
+        label: Option<&str>,
+        state: &State,
+    ) -> CubeTexture {
+        let size = faces[0].width();
+        assert!(
+            faces.iter().all(|face| face.dimensions() == (size, size)),
+            "cube map faces must be squares of the same size"
+        );
+        let format = if srgb {
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        } else {
+            wgpu::TextureFormat::Rgba8Unorm
+        };
+        let texture = state.device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth: 6,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+        for (layer, face) in faces.iter().enumerate() {
+            state.queue.write_texture(
+                wgpu::TextureCopyView {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer as u32,
+                    },
+                },
+                face,
+                wgpu::TextureDataLayout {
+                    offset: 0,
+                    bytes_per_row: 4 * size,
+                    rows_per_image: size,
+                },
+                wgpu::Extent3d {
+                    width: size,
+                    height: size,
+                    depth: 1,
+                },
+            );
+        }
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            array_layer_count: NonZeroU32::new(6),
+            ..Default::default()
+        });
+
+        CubeTexture {
+            texture,
+            view,
+            format,
+            size,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unpad_rows() {
+        let mut data = vec![0u8; 2 * 256];
+        data[..8].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        data[256..264].copy_from_slice(&[9, 10, 11, 12, 13, 14, 15, 16]);
+        assert_eq!(
+            Texture::unpad_rows(&data, 2, 256, false),
+            (1..=16).collect::<Vec<u8>>()
+        );
+        assert_eq!(
+            Texture::unpad_rows(&data[..8], 2, 256, true),
+            vec![3, 2, 1, 4, 7, 6, 5, 8]
+        );
+    }
+}
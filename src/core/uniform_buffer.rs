@@ -0,0 +1,53 @@
+use std::cell::Cell;
+
+use wgpu::util::DeviceExt;
+
+use crate::State;
+
+/// A uniform buffer with a CPU side copy. Changes only mark the copy as dirty;
+/// it is uploaded at most once per frame by `flush`, however many times it was
+/// modified.
+pub struct UniformBuffer<T: bytemuck::Pod> {
+    buffer: wgpu::Buffer,
+    data: T,
+    dirty: Cell<bool>,
+}
+
+impl<T: bytemuck::Pod> UniformBuffer<T> {
+    pub fn new(data: T, label: Option<&str>, state: &State) -> UniformBuffer<T> {
+        let buffer = state
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label,
+                contents: bytemuck::cast_slice(&[data]),
+                usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            });
+
+        UniformBuffer {
+            buffer,
+            data,
+            dirty: Cell::new(false),
+        }
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    pub fn data(&self) -> &T {
+        &self.data
+    }
+
+    pub fn data_mut(&mut self) -> &mut T {
+        self.dirty.set(true);
+        &mut self.data
+    }
+
+    pub fn flush(&self, state: &State) {
+        if self.dirty.replace(false) {
+            state
+                .queue
+                .write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.data]));
+        }
+    }
+}
@@ -0,0 +1,93 @@
+/// Rectangle of a render target a camera draws into, in fractions of the
+/// target's size from its top left corner.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Viewport::FULL
+    }
+}
+
+impl Viewport {
+    pub const FULL: Viewport = Viewport {
+        x: 0.0,
+        y: 0.0,
+        width: 1.0,
+        height: 1.0,
+    };
+
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Viewport {
+        Viewport {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Cell `column`, `row` of a grid of equally sized viewports, for split
+    /// screen.
+    pub fn grid(column: u32, row: u32, columns: u32, rows: u32) -> Viewport {
+        let width = 1.0 / columns as f32;
+        let height = 1.0 / rows as f32;
+        Viewport::new(column as f32 * width, row as f32 * height, width, height)
+    }
+
+    /// `[x, y, width, height]` in pixels of a target of `size`, clipped to
+    /// it. Edges are rounded so that adjacent viewports share them.
+    pub fn pixels(&self, size: [u32; 2]) -> [u32; 4] {
+        let edge = |fraction: f32, size: u32| {
+            ((0.0f32.max(fraction.min(1.0)) * size as f32).round() as u32).min(size)
+        };
+        let left = edge(self.x, size[0]);
+        let top = edge(self.y, size[1]);
+        let right = edge(self.x + self.width, size[0]);
+        let bottom = edge(self.y + self.height, size[1]);
+        [
+            left,
+            top,
+            right.saturating_sub(left),
+            bottom.saturating_sub(top),
+        ]
+    }
+
+    /// Covers the whole target.
+    pub fn is_full(&self) -> bool {
+        self.x <= 0.0 && self.y <= 0.0 && self.x + self.width >= 1.0 && self.y + self.height >= 1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_pixels() {
+        let size = [801, 601];
+        let mut covered = 0;
+        for row in 0..2 {
+            for column in 0..2 {
+                let [x, y, width, height] = Viewport::grid(column, row, 2, 2).pixels(size);
+                if column == 1 {
+                    assert_eq!(x + width, size[0]);
+                }
+                if row == 1 {
+                    assert_eq!(y + height, size[1]);
+                }
+                covered += width * height;
+            }
+        }
+        assert_eq!(covered, size[0] * size[1]);
+
+        let outside = Viewport::new(0.75, -0.5, 0.5, 1.0).pixels([100, 100]);
+        assert_eq!(outside, [75, 0, 25, 50]);
+        assert!(Viewport::FULL.is_full());
+        assert!(!Viewport::grid(0, 0, 2, 1).is_full());
+    }
+}
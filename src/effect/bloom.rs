@@ -0,0 +1,156 @@
+use crate::{FullscreenPass, PostEffect, PostOutput, RenderTargetFormat, State, Texture};
+
+/// Makes bright parts glow: keeps what is brighter than a threshold, blurs it
+/// at half resolution and adds it back onto the scene.
+pub struct Bloom {
+    /// Brightness from which pixels glow; scenes tonemapped into the range of
+    /// the target only glow below 1.
+    pub threshold: f32,
+    /// Width of the soft transition below the threshold.
+    pub knee: f32,
+    /// Factor of the glow added to the scene.
+    pub intensity: f32,
+    /// Texels at half resolution between the taps of the blur; larger values
+    /// spread the glow further but coarser.
+    pub radius: f32,
+    /// Times the blur is repeated.
+    pub iterations: u32,
+    threshold_pass: FullscreenPass,
+    blur_pass: FullscreenPass,
+    composite_pass: FullscreenPass,
+    /// Half resolution ping-pong textures, recreated for new inputs.
+    textures: Option<[Texture; 2]>,
+}
+
+impl Bloom {
+    pub fn new(state: &State) -> Bloom {
+        Bloom {
+            threshold: 0.8,
+            knee: 0.2,
+            intensity: 0.8,
+            radius: 1.5,
+            iterations: 2,
+            threshold_pass: FullscreenPass::new(
+                wgpu::include_spirv!(concat!(env!("OUT_DIR"), "/effect/bloom_threshold.frag.spv")),
+                &[],
+                "bloom_threshold",
+                state,
+            ),
+            blur_pass: FullscreenPass::new(
+                wgpu::include_spirv!(concat!(env!("OUT_DIR"), "/effect/bloom_blur.frag.spv")),
+                &[],
+                "bloom_blur",
+                state,
+            ),
+            composite_pass: FullscreenPass::new(
+                wgpu::include_spirv!(concat!(env!("OUT_DIR"), "/effect/bloom_composite.frag.spv")),
+                &[wgpu::TextureViewDimension::D2],
+                "bloom_composite",
+                state,
+            ),
+            textures: None,
+        }
+    }
+
+    fn prepare_textures(&mut self, input: &Texture, state: &State) {
+        let width = (input.size.width / 2).max(1);
+        let height = (input.size.height / 2).max(1);
+        if let Some([ref texture, _]) = self.textures {
+            if texture.size.width == width
+                && texture.size.height == height
+                && texture.format == input.format
+            {
+                return;
+            }
+        }
+        let create = || {
+            Texture::new(
+                width,
+                height,
+                input.format,
+                wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+                Some("Bloom Texture"),
+                state,
+            )
+        };
+        self.textures = Some([create(), create()]);
+    }
+
+    fn half_output(texture: &Texture, viewport: [u32; 4]) -> PostOutput<'_> {
+        PostOutput {
+            view: &texture.view,
+            resolve_target: None,
+            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+            format: RenderTargetFormat {
+                color_format: Some(texture.format),
+                depth_format: None,
+                reversed_z: false,
+                sample_count: 1,
+            },
+            blend: false,
+            size: [texture.size.width, texture.size.height],
+            viewport,
+            scissor: viewport,
+        }
+    }
+}
+
+impl PostEffect for Bloom {
+    fn render(
+        &mut self,
+        input: &Texture,
+        output: &PostOutput,
+        encoder: &mut wgpu::CommandEncoder,
+        state: &State,
+    ) {
+        self.prepare_textures(input, state);
+        let textures = self.textures.as_ref().unwrap();
+
+        let viewport = [
+            output.viewport[0] / 2,
+            output.viewport[1] / 2,
+            (output.viewport[2] / 2).max(1),
+            (output.viewport[3] / 2).max(1),
+        ];
+
+        let parameters = [[self.threshold, self.knee, 0.0, 0.0], [0.0; 4]];
+        self.threshold_pass.draw(
+            input,
+            &[],
+            parameters,
+            &Bloom::half_output(&textures[0], viewport),
+            encoder,
+            state,
+        );
+        for _ in 0..self.iterations {
+            let horizontal = [[self.radius, 0.0, 0.0, 0.0], [0.0; 4]];
+            self.blur_pass.draw(
+                &textures[0],
+                &[],
+                horizontal,
+                &Bloom::half_output(&textures[1], viewport),
+                encoder,
+                state,
+            );
+            let vertical = [[0.0, self.radius, 0.0, 0.0], [0.0; 4]];
+            self.blur_pass.draw(
+                &textures[1],
+                &[],
+                vertical,
+                &Bloom::half_output(&textures[0], viewport),
+                encoder,
+                state,
+            );
+        }
+
+        let parameters = [[self.intensity, 0.0, 0.0, 0.0], [0.0; 4]];
+        self.composite_pass.draw(
+            input,
+            &[&textures[0].view],
+            parameters,
+            output,
+            encoder,
+            state,
+        );
+    }
+}
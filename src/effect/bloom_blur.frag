@@ -0,0 +1,32 @@
+#version 450
+
+layout(push_constant) uniform PostConstants {
+  vec4 viewport;
+  vec2 output_size;
+  vec2 texel_size;
+  vec4 parameters[2];
+} post;
+
+layout(location=0) in vec2 v_uv;
+layout(location=1) in vec2 v_position;
+layout(location=2) flat in vec4 v_uv_bounds;
+
+layout(set=0, binding=0) uniform texture2D post_input;
+layout(set=0, binding=1) uniform sampler post_sampler;
+
+layout(location=0) out vec4 f_color;
+
+const float WEIGHTS[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+// One direction of a separable Gaussian blur, given in texels.
+void main() {
+  vec2 step_size = post.parameters[0].xy * post.texel_size;
+
+  vec3 color = texture(sampler2D(post_input, post_sampler), v_uv).rgb * WEIGHTS[0];
+  for (int i = 1; i < 5; i++) {
+    vec2 offset = step_size * float(i);
+    color += texture(sampler2D(post_input, post_sampler), clamp(v_uv + offset, v_uv_bounds.xy, v_uv_bounds.zw)).rgb * WEIGHTS[i];
+    color += texture(sampler2D(post_input, post_sampler), clamp(v_uv - offset, v_uv_bounds.xy, v_uv_bounds.zw)).rgb * WEIGHTS[i];
+  }
+  f_color = vec4(color, 1.0);
+}
@@ -0,0 +1,28 @@
+#version 450
+
+layout(push_constant) uniform PostConstants {
+  vec4 viewport;
+  vec2 output_size;
+  vec2 texel_size;
+  vec4 parameters[2];
+} post;
+
+layout(location=0) in vec2 v_uv;
+layout(location=1) in vec2 v_position;
+layout(location=2) flat in vec4 v_uv_bounds;
+
+layout(set=0, binding=0) uniform texture2D post_input;
+layout(set=0, binding=1) uniform sampler post_sampler;
+
+layout(set=1, binding=0) uniform texture2D bloom;
+
+layout(location=0) out vec4 f_color;
+
+// Adds the blurred bright parts onto the scene.
+void main() {
+  float intensity = post.parameters[0].x;
+
+  vec4 scene = texture(sampler2D(post_input, post_sampler), v_uv);
+  vec3 glow = texture(sampler2D(bloom, post_sampler), v_uv).rgb;
+  f_color = vec4(scene.rgb + glow * intensity, scene.a);
+}
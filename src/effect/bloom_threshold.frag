@@ -0,0 +1,31 @@
+#version 450
+
+layout(push_constant) uniform PostConstants {
+  vec4 viewport;
+  vec2 output_size;
+  vec2 texel_size;
+  vec4 parameters[2];
+} post;
+
+layout(location=0) in vec2 v_uv;
+layout(location=1) in vec2 v_position;
+layout(location=2) flat in vec4 v_uv_bounds;
+
+layout(set=0, binding=0) uniform texture2D post_input;
+layout(set=0, binding=1) uniform sampler post_sampler;
+
+layout(location=0) out vec4 f_color;
+
+// Keeps what is brighter than the threshold, with a soft knee below it, at
+// half resolution.
+void main() {
+  float threshold = post.parameters[0].x;
+  float knee = post.parameters[0].y;
+
+  vec3 color = texture(sampler2D(post_input, post_sampler), clamp(v_uv, v_uv_bounds.xy, v_uv_bounds.zw)).rgb;
+  float brightness = max(color.r, max(color.g, color.b));
+  float soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+  soft = soft * soft / (4.0 * knee + 1e-5);
+  float contribution = max(soft, brightness - threshold) / max(brightness, 1e-5);
+  f_color = vec4(color * contribution, 1.0);
+}
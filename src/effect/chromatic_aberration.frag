@@ -0,0 +1,36 @@
+#version 450
+
+layout(push_constant) uniform PostConstants {
+  vec4 viewport;
+  vec2 output_size;
+  vec2 texel_size;
+  vec4 parameters[2];
+} post;
+
+layout(location=0) in vec2 v_uv;
+layout(location=1) in vec2 v_position;
+layout(location=2) flat in vec4 v_uv_bounds;
+
+layout(set=0, binding=0) uniform texture2D post_input;
+layout(set=0, binding=1) uniform sampler post_sampler;
+
+layout(location=0) out vec4 f_color;
+
+vec4 sample_input(vec2 uv) {
+  return texture(sampler2D(post_input, post_sampler), clamp(uv, v_uv_bounds.xy, v_uv_bounds.zw));
+}
+
+// Shifts the red and blue channels apart from the center of the viewport,
+// the more the farther out.
+void main() {
+  float intensity = post.parameters[0].x;
+  vec2 center = (post.viewport.xy + post.viewport.zw * 0.5) / post.output_size;
+  vec2 offset = (v_uv - center) * intensity;
+  vec4 scene = sample_input(v_uv);
+  f_color = vec4(
+    sample_input(v_uv + offset).r,
+    scene.g,
+    sample_input(v_uv - offset).b,
+    scene.a
+  );
+}
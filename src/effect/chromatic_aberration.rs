@@ -0,0 +1,40 @@
+use crate::{FullscreenPass, PostEffect, PostOutput, State, Texture};
+
+/// Splits the red and blue channels apart towards the edges of the viewport,
+/// like a cheap lens.
+pub struct ChromaticAberration {
+    /// Offset of the channels as a fraction of the distance from the center.
+    pub intensity: f32,
+    pass: FullscreenPass,
+}
+
+impl ChromaticAberration {
+    pub fn new(state: &State) -> ChromaticAberration {
+        ChromaticAberration {
+            intensity: 0.01,
+            pass: FullscreenPass::new(
+                wgpu::include_spirv!(concat!(
+                    env!("OUT_DIR"),
+                    "/effect/chromatic_aberration.frag.spv"
+                )),
+                &[],
+                "chromatic_aberration",
+                state,
+            ),
+        }
+    }
+}
+
+impl PostEffect for ChromaticAberration {
+    fn render(
+        &mut self,
+        input: &Texture,
+        output: &PostOutput,
+        encoder: &mut wgpu::CommandEncoder,
+        state: &State,
+    ) {
+        let parameters = [[self.intensity, 0.0, 0.0, 0.0], [0.0; 4]];
+        self.pass
+            .draw(input, &[], parameters, output, encoder, state);
+    }
+}
@@ -0,0 +1,42 @@
+#version 450
+
+layout(push_constant) uniform PostConstants {
+  vec4 viewport;
+  vec2 output_size;
+  vec2 texel_size;
+  vec4 parameters[2];
+} post;
+
+layout(location=0) in vec2 v_uv;
+layout(location=1) in vec2 v_position;
+layout(location=2) flat in vec4 v_uv_bounds;
+
+layout(set=0, binding=0) uniform texture2D post_input;
+layout(set=0, binding=1) uniform sampler post_sampler;
+
+layout(set=1, binding=0) uniform texture3D lut;
+
+layout(location=0) out vec4 f_color;
+
+vec3 srgb_encode(vec3 color) {
+  return mix(12.92 * color, 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, color));
+}
+
+vec3 srgb_decode(vec3 color) {
+  return mix(color / 12.92, pow((color + 0.055) / 1.055, vec3(2.4)), step(0.04045, color));
+}
+
+// The table is an Rgba8Unorm texture holding the strip's sRGB encoded bytes
+// as they are, so it is indexed by the sRGB encoded color and its entries are
+// decoded after the lookup. Texel centers sit half a texel in from the edges.
+// The graded color is blended with the original by the intensity.
+void main() {
+  float size = post.parameters[0].x;
+  float intensity = post.parameters[0].y;
+
+  vec4 scene = texture(sampler2D(post_input, post_sampler), v_uv);
+  vec3 encoded = srgb_encode(clamp(scene.rgb, 0.0, 1.0));
+  vec3 position = (encoded * (size - 1.0) + 0.5) / size;
+  vec3 graded = srgb_decode(texture(sampler3D(lut, post_sampler), position).rgb);
+  f_color = vec4(mix(scene.rgb, graded, intensity), scene.a);
+}
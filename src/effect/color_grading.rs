@@ -0,0 +1,160 @@
+use image::{
+    error::{ParameterError, ParameterErrorKind},
+    ImageError, ImageResult, RgbaImage,
+};
+
+use crate::{FullscreenPass, PostEffect, PostOutput, State, Texture};
+
+/// Remaps colors through a 3D lookup table. Tables come as strips of `size`
+/// square slices side by side, blue growing from slice to slice, red to the
+/// right and green downwards within one. Entries are the raw sRGB encoded
+/// bytes of the strip: the table is uploaded without conversion and indexed by
+/// sRGB encoded colors, so grading a screenshot with `identity_strip` pasted
+/// into it in an image editor gives a matching table.
+pub struct ColorGrading {
+    /// Blend between the original colors at 0 and the graded ones at 1.
+    pub intensity: f32,
+    lut: wgpu::Texture,
+    lut_view: wgpu::TextureView,
+    lut_size: u32,
+    pass: FullscreenPass,
+}
+
+impl ColorGrading {
+    /// Fails if `strip` is not `size * size` pixels wide and `size` high.
+    pub fn new(strip: &RgbaImage, state: &State) -> ImageResult<ColorGrading> {
+        let (lut, lut_view, lut_size) = ColorGrading::create_lut(strip, state)?;
+        Ok(ColorGrading {
+            intensity: 1.0,
+            lut,
+            lut_view,
+            lut_size,
+            pass: FullscreenPass::new(
+                wgpu::include_spirv!(concat!(env!("OUT_DIR"), "/effect/color_grading.frag.spv")),
+                &[wgpu::TextureViewDimension::D3],
+                "color_grading",
+                state,
+            ),
+        })
+    }
+
+    /// A table leaving colors as they are, `size` entries per channel.
+    pub fn identity_strip(size: u32) -> RgbaImage {
+        let max = (size.max(2) - 1) as f32;
+        RgbaImage::from_fn(size * size, size, |x, y| {
+            let channel = |value: u32| (value as f32 / max * 255.0).round() as u8;
+            image::Rgba([channel(x % size), channel(y), channel(x / size), 255])
+        })
+    }
+
+    pub fn lut(&self) -> &wgpu::Texture {
+        &self.lut
+    }
+
+    /// Keeps the current table if `strip` has the wrong size.
+    pub fn set_lut(&mut self, strip: &RgbaImage, state: &State) -> ImageResult<()> {
+        let (lut, lut_view, lut_size) = ColorGrading::create_lut(strip, state)?;
+        self.lut = lut;
+        self.lut_view = lut_view;
+        self.lut_size = lut_size;
+        Ok(())
+    }
+
+    fn create_lut(
+        strip: &RgbaImage,
+        state: &State,
+    ) -> ImageResult<(wgpu::Texture, wgpu::TextureView, u32)> {
+        let (size, data) = ColorGrading::volume_from_strip(strip)?;
+        let extent = wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth: size,
+        };
+        let texture = state.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Color Grading LUT"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D3,
+            // Not `Rgba8UnormSrgb`: the shader decodes the entries itself, after
+            // interpolating between them in sRGB like image editors do.
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+        state.queue.write_texture(
+            wgpu::TextureCopyView {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            &data,
+            wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row: 4 * size,
+                rows_per_image: size,
+            },
+            extent,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Ok((texture, view, size))
+    }
+
+    /// Rearranges a strip into the texel order of a 3D texture: red fastest,
+    /// then green, then blue.
+    fn volume_from_strip(strip: &RgbaImage) -> ImageResult<(u32, Vec<u8>)> {
+        let size = strip.height();
+        if size == 0 || strip.width() != size * size {
+            return Err(ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::DimensionMismatch,
+            )));
+        }
+        let mut data = Vec::with_capacity(4 * (size * size * size) as usize);
+        for blue in 0..size {
+            for green in 0..size {
+                for red in 0..size {
+                    data.extend_from_slice(&strip.get_pixel(blue * size + red, green).0);
+                }
+            }
+        }
+        Ok((size, data))
+    }
+}
+
+impl PostEffect for ColorGrading {
+    fn render(
+        &mut self,
+        input: &Texture,
+        output: &PostOutput,
+        encoder: &mut wgpu::CommandEncoder,
+        state: &State,
+    ) {
+        let parameters = [[self.lut_size as f32, self.intensity, 0.0, 0.0], [0.0; 4]];
+        self.pass
+            .draw(input, &[&self.lut_view], parameters, output, encoder, state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity_volume() {
+        let (size, data) =
+            ColorGrading::volume_from_strip(&ColorGrading::identity_strip(4)).unwrap();
+        assert_eq!(size, 4);
+        assert_eq!(data.len(), 4 * 4 * 4 * 4);
+        // Red at 1, green at 2 and blue at 3 of 3.
+        let texel = 4 * ((3 * 4 + 2) * 4 + 1);
+        assert_eq!(&data[texel..texel + 4], &[85, 170, 255, 255]);
+
+        // Entries keep their sRGB encoded bytes, mid gray is not linearized.
+        let mut strip = ColorGrading::identity_strip(4);
+        strip.put_pixel(4 * 3 + 1, 2, image::Rgba([128, 128, 128, 255]));
+        let (_, data) = ColorGrading::volume_from_strip(&strip).unwrap();
+        assert_eq!(&data[texel..texel + 4], &[128, 128, 128, 255]);
+
+        assert!(ColorGrading::volume_from_strip(&RgbaImage::new(16, 8)).is_err());
+        assert!(ColorGrading::volume_from_strip(&RgbaImage::new(0, 0)).is_err());
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+
+use crate::{PostOutput, RenderTargetFormat, State, Texture};
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct PostConstants {
+    viewport: [f32; 4],
+    output_size: [f32; 2],
+    texel_size: [f32; 2],
+    parameters: [[f32; 4]; 2],
+}
+
+unsafe impl bytemuck::Zeroable for PostConstants {}
+unsafe impl bytemuck::Pod for PostConstants {}
+
+/// A fragment shader drawn over the viewport of a `PostOutput`, the building
+/// block of post effects. The shader sees the push constants
+///
+/// ```glsl
+/// layout(push_constant) uniform PostConstants {
+///   vec4 viewport;      // of the output, in pixels
+///   vec2 output_size;
+///   vec2 texel_size;    // of the input
+///   vec4 parameters[2]; // of the effect
+/// } post;
+/// ```
+///
+/// the inputs `vec2 v_uv` at location 0, `vec2 v_position` within the
+/// viewport from 0 to 1 at location 1 and `flat vec4 v_uv_bounds` at location
+/// 2, the input texture and a linear sampler at bindings 0 and 1 of set 0, and
+/// the pass's own textures at the following bindings of set 1.
+pub struct FullscreenPass {
+    label: String,
+    vs_module: wgpu::ShaderModule,
+    fs_module: wgpu::ShaderModule,
+    input_layout: wgpu::BindGroupLayout,
+    texture_layout: Option<wgpu::BindGroupLayout>,
+    pipeline_layout: wgpu::PipelineLayout,
+    sampler: wgpu::Sampler,
+    pipelines: HashMap<(RenderTargetFormat, bool), wgpu::RenderPipeline>,
+}
+
+impl FullscreenPass {
+    /// `textures` are the dimensions of the pass's own textures.
+    pub fn new(
+        fragment_stage: wgpu::ShaderModuleSource,
+        textures: &[wgpu::TextureViewDimension],
+        label: &str,
+        state: &State,
+    ) -> FullscreenPass {
+        let device = &state.device;
+        let texture_entry = |binding, dimension| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStage::FRAGMENT,
+            ty: wgpu::BindingType::SampledTexture {
+                dimension,
+                component_type: wgpu::TextureComponentType::Float,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let input_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                texture_entry(0, wgpu::TextureViewDimension::D2),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler { comparison: false },
+                    count: None,
+                },
+            ],
+            label: Some("post_input_bind_group_layout"),
+        });
+        let texture_layout = if textures.is_empty() {
+            None
+        } else {
+            let entries: Vec<_> = textures
+                .iter()
+                .enumerate()
+                .map(|(binding, &dimension)| texture_entry(binding as u32, dimension))
+                .collect();
+            Some(
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    entries: &entries,
+                    label: Some("post_texture_bind_group_layout"),
+                }),
+            )
+        };
+
+        let mut bind_group_layouts = vec![&input_layout];
+        bind_group_layouts.extend(texture_layout.iter());
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            bind_group_layouts: &bind_group_layouts,
+            push_constant_ranges: &[wgpu::PushConstantRange {
+                stages: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+                range: 0..std::mem::size_of::<PostConstants>() as u32,
+            }],
+            label: Some("post_pipeline_layout"),
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("post_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        FullscreenPass {
+            label: label.to_string(),
+            vs_module: device.create_shader_module(wgpu::include_spirv!(concat!(
+                env!("OUT_DIR"),
+                "/effect/post.vert.spv"
+            ))),
+            fs_module: device.create_shader_module(fragment_stage),
+            input_layout,
+            texture_layout,
+            pipeline_layout,
+            sampler,
+            pipelines: HashMap::new(),
+        }
+    }
+
+    fn prepare(&mut self, output: &PostOutput, state: &State) {
+        let key = (output.format, output.blend);
+        if self.pipelines.contains_key(&key) {
+            return;
+        }
+
+        let pipeline = state
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                layout: Some(&self.pipeline_layout),
+                vertex_stage: wgpu::ProgrammableStageDescriptor {
+                    module: &self.vs_module,
+                    entry_point: "main",
+                },
+                fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+                    module: &self.fs_module,
+                    entry_point: "main",
+                }),
+                rasterization_state: Some(wgpu::RasterizationStateDescriptor::default()),
+                color_states: &[output.color_state()],
+                primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+                depth_stencil_state: None,
+                vertex_state: wgpu::VertexStateDescriptor {
+                    index_format: wgpu::IndexFormat::Uint16,
+                    vertex_buffers: &[],
+                },
+                sample_count: output.format.sample_count,
+                sample_mask: !0,
+                alpha_to_coverage_enabled: false,
+                label: Some(&self.label),
+            });
+        self.pipelines.insert(key, pipeline);
+    }
+
+    /// Draws the shader over the viewport of `output` in a pass of its own.
+    pub fn draw(
+        &mut self,
+        input: &Texture,
+        textures: &[&wgpu::TextureView],
+        parameters: [[f32; 4]; 2],
+        output: &PostOutput,
+        encoder: &mut wgpu::CommandEncoder,
+        state: &State,
+    ) {
+        self.prepare(output, state);
+
+        let input_bind_group = state.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.input_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&input.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+            label: Some("post_input_bind_group"),
+        });
+        let texture_bind_group = self.texture_layout.as_ref().map(|layout| {
+            let entries: Vec<_> = textures
+                .iter()
+                .enumerate()
+                .map(|(binding, view)| wgpu::BindGroupEntry {
+                    binding: binding as u32,
+                    resource: wgpu::BindingResource::TextureView(view),
+                })
+                .collect();
+            state.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout,
+                entries: &entries,
+                label: Some("post_texture_bind_group"),
+            })
+        });
+
+        let constants = PostConstants {
+            viewport: [
+                output.viewport[0] as f32,
+                output.viewport[1] as f32,
+                output.viewport[2] as f32,
+                output.viewport[3] as f32,
+            ],
+            output_size: [output.size[0] as f32, output.size[1] as f32],
+            texel_size: [
+                1.0 / input.size.width as f32,
+                1.0 / input.size.height as f32,
+            ],
+            parameters,
+        };
+
+        let mut render_pass = output.begin_render_pass(encoder);
+        render_pass.set_pipeline(&self.pipelines[&(output.format, output.blend)]);
+        render_pass.set_bind_group(0, &input_bind_group, &[]);
+        if let Some(ref texture_bind_group) = texture_bind_group {
+            render_pass.set_bind_group(1, texture_bind_group, &[]);
+        }
+        render_pass.set_push_constants(
+            wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+            0,
+            bytemuck::cast_slice(&[constants]),
+        );
+        render_pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,63 @@
+#version 450
+
+layout(push_constant) uniform PostConstants {
+  vec4 viewport;
+  vec2 output_size;
+  vec2 texel_size;
+  vec4 parameters[2];
+} post;
+
+layout(location=0) in vec2 v_uv;
+layout(location=1) in vec2 v_position;
+layout(location=2) flat in vec4 v_uv_bounds;
+
+layout(set=0, binding=0) uniform texture2D post_input;
+layout(set=0, binding=1) uniform sampler post_sampler;
+
+layout(location=0) out vec4 f_color;
+
+vec4 sample_input(vec2 uv) {
+  return texture(sampler2D(post_input, post_sampler), clamp(uv, v_uv_bounds.xy, v_uv_bounds.zw));
+}
+
+// Perceptual brightness of a linear color.
+float luma(vec3 color) {
+  return sqrt(dot(color, vec3(0.299, 0.587, 0.114)));
+}
+
+// The compact FXAA of Timothy Lottes: blurs along the edge through the pixel
+// when the blur stays within the contrast of its neighborhood.
+void main() {
+  float span_max = post.parameters[0].x;
+  float reduce_mul = post.parameters[0].y;
+  float reduce_min = post.parameters[0].z;
+  vec2 texel = post.texel_size;
+
+  vec4 center = sample_input(v_uv);
+  float luma_nw = luma(sample_input(v_uv + vec2(-1.0, -1.0) * texel).rgb);
+  float luma_ne = luma(sample_input(v_uv + vec2(1.0, -1.0) * texel).rgb);
+  float luma_sw = luma(sample_input(v_uv + vec2(-1.0, 1.0) * texel).rgb);
+  float luma_se = luma(sample_input(v_uv + vec2(1.0, 1.0) * texel).rgb);
+  float luma_m = luma(center.rgb);
+  float luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+  float luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+  vec2 direction = vec2(
+    (luma_sw + luma_se) - (luma_nw + luma_ne),
+    (luma_nw + luma_sw) - (luma_ne + luma_se)
+  );
+  float direction_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * reduce_mul, reduce_min);
+  float inverse_min_direction = 1.0 / (min(abs(direction.x), abs(direction.y)) + direction_reduce);
+  direction = clamp(direction * inverse_min_direction, -span_max, span_max) * texel;
+
+  vec3 color_a = 0.5 * (
+    sample_input(v_uv + direction * (1.0 / 3.0 - 0.5)).rgb +
+    sample_input(v_uv + direction * (2.0 / 3.0 - 0.5)).rgb
+  );
+  vec3 color_b = color_a * 0.5 + 0.25 * (
+    sample_input(v_uv - direction * 0.5).rgb +
+    sample_input(v_uv + direction * 0.5).rgb
+  );
+  float luma_b = luma(color_b);
+  f_color = vec4((luma_b < luma_min || luma_b > luma_max) ? color_a : color_b, center.a);
+}
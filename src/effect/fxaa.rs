@@ -0,0 +1,47 @@
+use crate::{FullscreenPass, PostEffect, PostOutput, State, Texture};
+
+/// Fast approximate anti-aliasing: blurs along the edges it finds by their
+/// contrast, much cheaper than multisampling but softer.
+pub struct Fxaa {
+    /// Longest blur along an edge, in texels.
+    pub span_max: f32,
+    /// Fraction of the neighborhood's brightness that damps the blur
+    /// direction, keeping bright areas sharper.
+    pub reduce_mul: f32,
+    /// Least damping of the blur direction.
+    pub reduce_min: f32,
+    pass: FullscreenPass,
+}
+
+impl Fxaa {
+    pub fn new(state: &State) -> Fxaa {
+        Fxaa {
+            span_max: 8.0,
+            reduce_mul: 1.0 / 8.0,
+            reduce_min: 1.0 / 128.0,
+            pass: FullscreenPass::new(
+                wgpu::include_spirv!(concat!(env!("OUT_DIR"), "/effect/fxaa.frag.spv")),
+                &[],
+                "fxaa",
+                state,
+            ),
+        }
+    }
+}
+
+impl PostEffect for Fxaa {
+    fn render(
+        &mut self,
+        input: &Texture,
+        output: &PostOutput,
+        encoder: &mut wgpu::CommandEncoder,
+        state: &State,
+    ) {
+        let parameters = [
+            [self.span_max, self.reduce_mul, self.reduce_min, 0.0],
+            [0.0; 4],
+        ];
+        self.pass
+            .draw(input, &[], parameters, output, encoder, state);
+    }
+}
@@ -0,0 +1,7 @@
+pub mod bloom;
+pub mod chromatic_aberration;
+pub mod color_grading;
+pub mod fullscreen_pass;
+pub mod fxaa;
+pub mod shader_effect;
+pub mod vignette;
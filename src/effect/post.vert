@@ -0,0 +1,25 @@
+#version 450
+
+layout(push_constant) uniform PostConstants {
+  vec4 viewport;
+  vec2 output_size;
+  vec2 texel_size;
+  vec4 parameters[2];
+} post;
+
+// Texture coordinates of the input, the position within the viewport from
+// its top left corner, and the texture coordinates of the first and last
+// texel centers of the viewport, to keep neighbor reads inside it.
+layout(location=0) out vec2 v_uv;
+layout(location=1) out vec2 v_position;
+layout(location=2) flat out vec4 v_uv_bounds;
+
+// A triangle covering the viewport.
+void main() {
+  vec2 clip = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2) * 2.0 - 1.0;
+  v_position = vec2(clip.x * 0.5 + 0.5, 0.5 - clip.y * 0.5);
+  v_uv = (post.viewport.xy + v_position * post.viewport.zw) / post.output_size;
+  v_uv_bounds = vec4(post.viewport.xy + 0.5, post.viewport.xy + post.viewport.zw - 0.5)
+    / post.output_size.xyxy;
+  gl_Position = vec4(clip, 0.0, 1.0);
+}
@@ -0,0 +1,63 @@
+use std::{borrow::Cow, path::Path};
+
+use cgmath::Vector4;
+
+use crate::{
+    load_shader, FullscreenPass, PostEffect, PostOutput, ShaderError, ShaderSource, State, Texture,
+};
+
+/// A post effect whose fragment shader is provided at runtime. It sees the
+/// interface described at `FullscreenPass`, with `post.parameters` set through
+/// `set_parameter`.
+pub struct ShaderEffect {
+    parameters: [Vector4<f32>; 2],
+    pass: FullscreenPass,
+}
+
+impl ShaderEffect {
+    pub fn new(source: ShaderSource, state: &State) -> Result<ShaderEffect, ShaderError> {
+        let spirv = source.to_spirv(shaderc::ShaderKind::Fragment, "shader_effect.frag")?;
+        Ok(ShaderEffect::from_spirv(&spirv, state))
+    }
+
+    /// Loads a GLSL (or `.spv`) fragment shader from disk.
+    pub fn from_file(path: impl AsRef<Path>, state: &State) -> Result<ShaderEffect, ShaderError> {
+        let spirv = load_shader(path.as_ref(), shaderc::ShaderKind::Fragment)?;
+        Ok(ShaderEffect::from_spirv(&spirv, state))
+    }
+
+    fn from_spirv(spirv: &[u32], state: &State) -> ShaderEffect {
+        ShaderEffect {
+            parameters: [Vector4::new(0.0, 0.0, 0.0, 0.0); 2],
+            pass: FullscreenPass::new(
+                wgpu::ShaderModuleSource::SpirV(Cow::Borrowed(spirv)),
+                &[],
+                "shader_effect",
+                state,
+            ),
+        }
+    }
+
+    pub fn parameter(&self, index: usize) -> Vector4<f32> {
+        self.parameters[index]
+    }
+
+    /// Sets `post.parameters[index]`, with `index` 0 or 1.
+    pub fn set_parameter(&mut self, index: usize, value: Vector4<f32>) {
+        self.parameters[index] = value;
+    }
+}
+
+impl PostEffect for ShaderEffect {
+    fn render(
+        &mut self,
+        input: &Texture,
+        output: &PostOutput,
+        encoder: &mut wgpu::CommandEncoder,
+        state: &State,
+    ) {
+        let parameters = [self.parameters[0].into(), self.parameters[1].into()];
+        self.pass
+            .draw(input, &[], parameters, output, encoder, state);
+    }
+}
@@ -0,0 +1,34 @@
+#version 450
+
+layout(push_constant) uniform PostConstants {
+  vec4 viewport;
+  vec2 output_size;
+  vec2 texel_size;
+  vec4 parameters[2];
+} post;
+
+layout(location=0) in vec2 v_uv;
+layout(location=1) in vec2 v_position;
+layout(location=2) flat in vec4 v_uv_bounds;
+
+layout(set=0, binding=0) uniform texture2D post_input;
+layout(set=0, binding=1) uniform sampler post_sampler;
+
+layout(location=0) out vec4 f_color;
+
+// Radial darkening towards the corners of the viewport, shaped to its aspect
+// ratio by the roundness.
+void main() {
+  vec4 color = post.parameters[0];
+  float intensity = post.parameters[1].x;
+  float radius = post.parameters[1].y;
+  float softness = post.parameters[1].z;
+  float roundness = post.parameters[1].w;
+
+  vec4 scene = texture(sampler2D(post_input, post_sampler), v_uv);
+  vec2 offset = v_position - 0.5;
+  offset.x *= mix(1.0, post.viewport.z / post.viewport.w, roundness);
+  float distance_to_center = length(offset) * 2.0;
+  float amount = smoothstep(radius - softness, radius, distance_to_center);
+  f_color = vec4(mix(scene.rgb, color.rgb, amount * intensity * color.a), scene.a);
+}
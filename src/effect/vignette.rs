@@ -0,0 +1,56 @@
+use crate::{FullscreenPass, PostEffect, PostOutput, State, Texture};
+
+/// Darkens, or tints, the viewport towards its corners.
+pub struct Vignette {
+    pub color: wgpu::Color,
+    /// How far towards `color` the corners go, from 0 to 1.
+    pub intensity: f32,
+    /// Distance from the center, where 1 reaches the middle of the edges, at
+    /// which the effect is complete.
+    pub radius: f32,
+    /// Distance over which the effect fades in before `radius`.
+    pub softness: f32,
+    /// 1 for a circle, 0 for an ellipse following the viewport's aspect.
+    pub roundness: f32,
+    pass: FullscreenPass,
+}
+
+impl Vignette {
+    pub fn new(state: &State) -> Vignette {
+        Vignette {
+            color: wgpu::Color::BLACK,
+            intensity: 0.6,
+            radius: 1.4,
+            softness: 0.9,
+            roundness: 1.0,
+            pass: FullscreenPass::new(
+                wgpu::include_spirv!(concat!(env!("OUT_DIR"), "/effect/vignette.frag.spv")),
+                &[],
+                "vignette",
+                state,
+            ),
+        }
+    }
+}
+
+impl PostEffect for Vignette {
+    fn render(
+        &mut self,
+        input: &Texture,
+        output: &PostOutput,
+        encoder: &mut wgpu::CommandEncoder,
+        state: &State,
+    ) {
+        let parameters = [
+            [
+                self.color.r as f32,
+                self.color.g as f32,
+                self.color.b as f32,
+                self.color.a as f32,
+            ],
+            [self.intensity, self.radius, self.softness, self.roundness],
+        ];
+        self.pass
+            .draw(input, &[], parameters, output, encoder, state);
+    }
+}
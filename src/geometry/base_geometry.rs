@@ -1,8 +1,8 @@
-use std::mem;
+use std::{mem, path::Path};
 
 use wgpu::util::DeviceExt;
 
-use cgmath::Vector3;
+use cgmath::{Vector2, Vector3};
 
 use crate::{Bindable, Geometry, State};
 
@@ -10,6 +10,8 @@ use crate::{Bindable, Geometry, State};
 #[derive(Copy, Clone, Debug)]
 struct Vertex {
     position: Vector3<f32>,
+    normal: Vector3<f32>,
+    tex_coord: Vector2<f32>,
 }
 
 unsafe impl bytemuck::Pod for Vertex {}
@@ -18,18 +20,28 @@ unsafe impl bytemuck::Zeroable for Vertex {}
 const VERTICES: &[Vertex] = &[
     Vertex {
         position: Vector3::<f32>::new(-0.0868241, 0.49240386, 0.0),
+        normal: Vector3::<f32>::new(0.0, 0.0, 1.0),
+        tex_coord: Vector2::<f32>::new(0.413176, 0.007596),
     },
     Vertex {
         position: Vector3::<f32>::new(-0.49513406, 0.06958647, 0.0),
+        normal: Vector3::<f32>::new(0.0, 0.0, 1.0),
+        tex_coord: Vector2::<f32>::new(0.004866, 0.430414),
     },
     Vertex {
         position: Vector3::<f32>::new(-0.21918549, -0.44939706, 0.0),
+        normal: Vector3::<f32>::new(0.0, 0.0, 1.0),
+        tex_coord: Vector2::<f32>::new(0.280815, 0.949397),
     },
     Vertex {
         position: Vector3::<f32>::new(0.35966998, -0.3473291, 0.0),
+        normal: Vector3::<f32>::new(0.0, 0.0, 1.0),
+        tex_coord: Vector2::<f32>::new(0.85967, 0.847329),
     },
     Vertex {
         position: Vector3::<f32>::new(0.44147372, 0.2347359, 0.0),
+        normal: Vector3::<f32>::new(0.0, 0.0, 1.0),
+        tex_coord: Vector2::<f32>::new(0.941474, 0.265264),
     },
 ];
 
@@ -71,7 +83,7 @@ impl Bindable for BaseGeometry {
 
 impl Geometry for BaseGeometry {
     fn vertex_stage(&self) -> wgpu::ShaderModuleSource<'static> {
-        wgpu::include_spirv!("base_geometry.vert.spv")
+        wgpu::include_spirv!(concat!(env!("OUT_DIR"), "/geometry/base_geometry.vert.spv"))
     }
     fn rasterization_state(&self) -> wgpu::RasterizationStateDescriptor {
         wgpu::RasterizationStateDescriptor {
@@ -94,11 +106,23 @@ impl Geometry for BaseGeometry {
         vec![wgpu::VertexBufferDescriptor {
             stride: mem::size_of::<Vertex>() as wgpu::BufferAddress,
             step_mode: wgpu::InputStepMode::Vertex,
-            attributes: &[wgpu::VertexAttributeDescriptor {
-                offset: 0,
-                shader_location: 0,
-                format: wgpu::VertexFormat::Float3,
-            }],
+            attributes: &[
+                wgpu::VertexAttributeDescriptor {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float3,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: mem::size_of::<Vector3<f32>>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float3,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: (2 * mem::size_of::<Vector3<f32>>()) as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float2,
+                },
+            ],
         }]
     }
 
@@ -111,4 +135,21 @@ impl Geometry for BaseGeometry {
     fn geometry_bind_group_layout(&self) -> Option<&wgpu::BindGroupLayout> {
         None
     }
+
+    fn triangles(&self) -> Vec<[Vector3<f32>; 3]> {
+        INDICES
+            .chunks(3)
+            .map(|triangle| {
+                [
+                    VERTICES[triangle[0] as usize].position,
+                    VERTICES[triangle[1] as usize].position,
+                    VERTICES[triangle[2] as usize].position,
+                ]
+            })
+            .collect()
+    }
+
+    fn vertex_shader_path(&self) -> Option<&Path> {
+        Some(Path::new("geometry/base_geometry.vert"))
+    }
 }
@@ -8,7 +8,17 @@ layout(push_constant) uniform ConstData {
 } PushConstant;
 
 layout(location=0) in vec3 a_position;
+layout(location=1) in vec3 a_normal;
+layout(location=2) in vec2 a_tex_coord;
+
+layout(location=0) out vec3 v_position;
+layout(location=1) out vec3 v_normal;
+layout(location=2) out vec2 v_tex_coord;
 
 void main() {
-  gl_Position = vec4(a_position, 1.0);
-}
\ No newline at end of file
+  vec4 world_position = PushConstant.mesh_transformation * vec4(a_position, 1.0);
+  v_position = world_position.xyz;
+  v_normal = transpose(inverse(mat3(PushConstant.mesh_transformation))) * a_normal;
+  v_tex_coord = a_tex_coord;
+  gl_Position = PushConstant.camera_projection * world_position;
+}
@@ -1 +1,2 @@
 pub mod base_geometry;
+pub mod wireframe_geometry;
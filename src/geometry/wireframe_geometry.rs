@@ -0,0 +1,122 @@
+use std::{mem, path::Path};
+
+use wgpu::util::DeviceExt;
+
+use cgmath::Vector3;
+
+use crate::{Bindable, Geometry, State};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct WireframeVertex {
+    position: Vector3<f32>,
+    barycentric: Vector3<f32>,
+}
+
+unsafe impl bytemuck::Pod for WireframeVertex {}
+unsafe impl bytemuck::Zeroable for WireframeVertex {}
+
+/// Unindexed copy of another geometry's triangles where every corner carries
+/// its barycentric coordinate, so a fragment shader can find triangle edges
+/// without line rasterization.
+pub struct WireframeGeometry {
+    vertex_buffer: wgpu::Buffer,
+    num_vertices: u32,
+}
+
+impl WireframeGeometry {
+    pub fn new(triangles: &[[Vector3<f32>; 3]], state: &State) -> WireframeGeometry {
+        let corners = [
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+        ];
+        let vertices: Vec<WireframeVertex> = triangles
+            .iter()
+            .flat_map(|triangle| {
+                triangle
+                    .iter()
+                    .zip(corners.iter())
+                    .map(|(position, barycentric)| WireframeVertex {
+                        position: *position,
+                        barycentric: *barycentric,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        WireframeGeometry {
+            vertex_buffer: state
+                .device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Wireframe Vertex Buffer"),
+                    contents: bytemuck::cast_slice(&vertices),
+                    usage: wgpu::BufferUsage::VERTEX,
+                }),
+            num_vertices: vertices.len() as u32,
+        }
+    }
+}
+
+impl Bindable for WireframeGeometry {
+    fn bind_group(&self) -> Option<&wgpu::BindGroup> {
+        None
+    }
+}
+
+impl Geometry for WireframeGeometry {
+    fn vertex_stage(&self) -> wgpu::ShaderModuleSource<'static> {
+        wgpu::include_spirv!(concat!(
+            env!("OUT_DIR"),
+            "/geometry/wireframe_geometry.vert.spv"
+        ))
+    }
+    fn rasterization_state(&self) -> wgpu::RasterizationStateDescriptor {
+        wgpu::RasterizationStateDescriptor {
+            front_face: wgpu::FrontFace::Ccw,
+            clamp_depth: true,
+            cull_mode: wgpu::CullMode::None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            depth_bias: 0,
+            depth_bias_slope_scale: 0.0,
+            depth_bias_clamp: 0.0,
+        }
+    }
+    fn primitive_topology(&self) -> wgpu::PrimitiveTopology {
+        wgpu::PrimitiveTopology::TriangleList
+    }
+    fn index_format(&self) -> wgpu::IndexFormat {
+        wgpu::IndexFormat::Uint16
+    }
+    fn vertex_buffer(&self) -> Vec<wgpu::VertexBufferDescriptor> {
+        vec![wgpu::VertexBufferDescriptor {
+            stride: mem::size_of::<WireframeVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttributeDescriptor {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float3,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: mem::size_of::<Vector3<f32>>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float3,
+                },
+            ],
+        }]
+    }
+
+    fn draw_geometry<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.num_vertices, 0..1);
+    }
+
+    fn geometry_bind_group_layout(&self) -> Option<&wgpu::BindGroupLayout> {
+        None
+    }
+
+    fn vertex_shader_path(&self) -> Option<&Path> {
+        Some(Path::new("geometry/wireframe_geometry.vert"))
+    }
+}
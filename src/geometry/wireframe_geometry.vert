@@ -0,0 +1,25 @@
+#version 450
+
+layout(push_constant) uniform ConstData {
+  mat4 camera_projection;
+  vec3 camera_position;
+  mat4 mesh_transformation;
+  mat3 normal_transformation;
+} PushConstant;
+
+layout(location=0) in vec3 a_position;
+layout(location=1) in vec3 a_barycentric;
+
+layout(location=0) out vec3 v_position;
+layout(location=1) out vec3 v_normal;
+layout(location=2) out vec2 v_tex_coord;
+layout(location=3) out vec3 v_barycentric;
+
+void main() {
+  vec4 world_position = PushConstant.mesh_transformation * vec4(a_position, 1.0);
+  v_position = world_position.xyz;
+  v_normal = vec3(0.0);
+  v_tex_coord = vec2(0.0);
+  v_barycentric = a_barycentric;
+  gl_Position = PushConstant.camera_projection * world_position;
+}
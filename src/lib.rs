@@ -1,25 +1,74 @@
 mod camera;
+mod controller;
 mod core;
+mod effect;
 mod geometry;
 mod light;
 mod material;
 
 pub use crate::core::{
-    bindable::Bindable,
+    bindable::{Bindable, GEOMETRY_BIND_GROUP, LIGHT_BIND_GROUP, MATERIAL_BIND_GROUP},
     camera::{Camera, CameraUniform},
+    camera_controller::{smoothing_factor, CameraController, ControllerInput},
     component::Component,
+    environment::Environment,
     geometry::{Geometry, GeometryUniformBase},
     group::Group,
     hitbox::{Hitbox, HitboxType},
-    light::{Light, LightUniform},
-    material::Material,
-    mesh::{Mesh, MeshUniform},
+    light::{Light, LightType, LightUniform},
+    light_array::LightArray,
+    light_clusters::{ClusterGrid, LightBins},
+    material::{AsAny, BlendMode, DepthState, Material},
+    mesh::{DebugPipeline, Mesh, MeshUniform},
+    post_effect::{PostEffect, PostOutput},
+    projection::{Frustum, Projection, REVERSE_Z_MATRIX},
+    reflection::{
+        reflect_bindings, ReflectedBinding, ReflectedBindingType, ReflectedMember, UniformType,
+    },
     render_pass_wrapper::RenderPassWrapper,
+    render_target::{
+        FrameAttachment, RenderTarget, RenderTargetFormat, DEFAULT_COLOR_FORMAT,
+        DEFAULT_DEPTH_FORMAT,
+    },
+    render_texture::{RenderTexture, DEFAULT_RENDER_TEXTURE_FORMAT},
+    render_window::RenderWindow,
     scene::Scene,
+    shader::{compile_glsl, load_shader, ShaderError, ShaderSource},
+    shadow_cascades::ShadowCascades,
+    shadow_maps::{ShadowLayer, ShadowMaps, SHADOW_MAP_FORMAT},
     state::State,
+    texture::{CubeTexture, Texture},
     transformation::Transformation,
+    uniform_buffer::UniformBuffer,
+    viewport::Viewport,
 };
 
-pub use crate::camera::render_camera::RenderCamera;
+#[cfg(feature = "hot-reload")]
+pub use crate::core::hot_reload::ShaderHotReload;
+
+pub use crate::camera::background::Background;
+pub use crate::camera::frame_recorder::FrameRecorder;
+pub use crate::camera::post_stack::PostStack;
+pub use crate::camera::render_camera::{InvalidSampleCount, RenderCamera, DEFAULT_CLEAR_COLOR};
+pub use crate::camera::tonemapper::{AutoExposure, Exposure, Tonemapping, HDR_FORMAT};
+pub use crate::controller::first_person_controller::FirstPersonController;
+pub use crate::controller::fly_controller::FlyController;
+pub use crate::controller::orbit_controller::OrbitController;
+pub use crate::effect::bloom::Bloom;
+pub use crate::effect::chromatic_aberration::ChromaticAberration;
+pub use crate::effect::color_grading::ColorGrading;
+pub use crate::effect::fullscreen_pass::FullscreenPass;
+pub use crate::effect::fxaa::Fxaa;
+pub use crate::effect::shader_effect::ShaderEffect;
+pub use crate::effect::vignette::Vignette;
 pub use crate::geometry::base_geometry::BaseGeometry;
+pub use crate::geometry::wireframe_geometry::WireframeGeometry;
+pub use crate::light::ambient_light::AmbientLight;
+pub use crate::light::directional_light::DirectionalLight;
+pub use crate::light::hemisphere_light::HemisphereLight;
+pub use crate::light::point_light::PointLight;
+pub use crate::light::spot_light::SpotLight;
+pub use crate::material::debug_material::{DebugMaterial, DebugView};
+pub use crate::material::pbr_material::{PbrMaterial, PbrMaterialDescriptor};
+pub use crate::material::shader_material::{ShaderMaterial, UniformValue};
 pub use crate::material::solid_color_material::SolidColorMaterial;
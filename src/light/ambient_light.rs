@@ -0,0 +1,98 @@
+use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+};
+
+use cgmath::Vector3;
+
+use crate::{Component, Light, LightType, LightUniform, Scene, Transformation};
+
+/// Light of the same color reaching every surface from every direction, so
+/// that sides facing away from all other lights are not black.
+pub struct AmbientLight {
+    data: LightUniform,
+    has_parent: bool,
+    scene: Weak<RefCell<Scene>>,
+    transformation: Transformation,
+    children: Vec<Rc<RefCell<dyn Component>>>,
+    this: Weak<RefCell<AmbientLight>>,
+}
+
+impl AmbientLight {
+    pub fn new_ref(color: Vector3<f32>, intensity: f32) -> Rc<RefCell<AmbientLight>> {
+        let data = LightUniform {
+            light_type: LightType::Ambient as u32,
+            light_color: color,
+            light_intensity: intensity,
+            ..Default::default()
+        };
+        let light = Rc::new(RefCell::new(AmbientLight {
+            data,
+            has_parent: false,
+            scene: Weak::new(),
+            transformation: Transformation::new(),
+            children: Vec::new(),
+            this: Weak::new(),
+        }));
+
+        light.borrow_mut().this = Rc::downgrade(&light);
+        light
+    }
+
+    pub fn color(&self) -> Vector3<f32> {
+        self.data.light_color
+    }
+
+    pub fn set_color(&mut self, color: Vector3<f32>) {
+        self.data.light_color = color;
+    }
+
+    pub fn intensity(&self) -> f32 {
+        self.data.light_intensity
+    }
+
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.data.light_intensity = intensity;
+    }
+}
+
+impl Component for AmbientLight {
+    fn has_parent(&self) -> bool {
+        self.has_parent
+    }
+    fn has_parent_mut(&mut self) -> &mut bool {
+        &mut self.has_parent
+    }
+    fn scene(&self) -> &Weak<RefCell<Scene>> {
+        &self.scene
+    }
+    fn scene_mut(&mut self) -> &mut Weak<RefCell<Scene>> {
+        &mut self.scene
+    }
+    fn children(&self) -> &Vec<Rc<RefCell<dyn Component>>> {
+        &self.children
+    }
+    fn children_mut(&mut self) -> &mut Vec<Rc<RefCell<dyn Component>>> {
+        &mut self.children
+    }
+    fn transformation(&self) -> &Transformation {
+        &self.transformation
+    }
+    fn transformation_mut(&mut self) -> &mut Transformation {
+        &mut self.transformation
+    }
+    fn added_to_scene(&mut self) {
+        self.scene
+            .upgrade()
+            .unwrap()
+            .borrow_mut()
+            .lights
+            .push(self.this.upgrade().unwrap());
+    }
+}
+
+impl Light for AmbientLight {
+    fn light_uniform(&self) -> LightUniform {
+        self.data
+    }
+}
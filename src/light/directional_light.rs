@@ -0,0 +1,188 @@
+use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+};
+
+use cgmath::{prelude::SquareMatrix, Matrix4, Vector3};
+
+use crate::{
+    camera::render_camera::OPENGL_TO_WGPU_MATRIX, Component, Light, LightType, LightUniform, Scene,
+    ShadowCascades, State, Transformation,
+};
+
+/// Light arriving from infinitely far away along the -z axis of its
+/// transformation, like sunlight.
+pub struct DirectionalLight {
+    data: LightUniform,
+    shadow_extent: f32,
+    has_parent: bool,
+    scene: Weak<RefCell<Scene>>,
+    transformation: Transformation,
+    children: Vec<Rc<RefCell<dyn Component>>>,
+    this: Weak<RefCell<DirectionalLight>>,
+}
+
+impl DirectionalLight {
+    pub fn new_ref(
+        color: Vector3<f32>,
+        intensity: f32,
+        _state: &State,
+    ) -> Rc<RefCell<DirectionalLight>> {
+        let data = LightUniform {
+            light_type: LightType::Directional as u32,
+            light_color: color,
+            light_intensity: intensity,
+            ..Default::default()
+        };
+
+        let light = Rc::new(RefCell::new(DirectionalLight {
+            data,
+            shadow_extent: 50.0,
+            has_parent: false,
+            scene: Weak::new(),
+            transformation: Transformation::new(),
+            children: Vec::new(),
+            this: Weak::new(),
+        }));
+
+        light.borrow_mut().this = Rc::downgrade(&light);
+        light
+    }
+
+    pub fn color(&self) -> Vector3<f32> {
+        self.data.light_color
+    }
+
+    pub fn set_color(&mut self, color: Vector3<f32>) {
+        self.data.light_color = color;
+    }
+
+    pub fn intensity(&self) -> f32 {
+        self.data.light_intensity
+    }
+
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.data.light_intensity = intensity;
+    }
+
+    pub fn cast_shadows(&self) -> bool {
+        self.data.shadow_map >= 0
+    }
+
+    /// Shadows are rendered while the camera has free shadow map layers.
+    pub fn set_cast_shadows(&mut self, cast_shadows: bool) {
+        self.data.shadow_map = if cast_shadows { 0 } else { -1 };
+    }
+
+    pub fn shadow_bias(&self) -> f32 {
+        self.data.shadow_bias
+    }
+
+    /// Depth offset against shadow acne; too large and shadows detach from casters.
+    pub fn set_shadow_bias(&mut self, shadow_bias: f32) {
+        self.data.shadow_bias = shadow_bias;
+    }
+
+    pub fn shadow_normal_bias(&self) -> f32 {
+        self.data.shadow_normal_bias
+    }
+
+    /// World space offset along the receiver's normal, for acne at grazing angles.
+    pub fn set_shadow_normal_bias(&mut self, shadow_normal_bias: f32) {
+        self.data.shadow_normal_bias = shadow_normal_bias;
+    }
+
+    pub fn shadow_extent(&self) -> f32 {
+        self.shadow_extent
+    }
+
+    /// Half the size of the box covered by the shadow map, centered on the
+    /// light's position and aligned with its direction.
+    pub fn set_shadow_extent(&mut self, shadow_extent: f32) {
+        self.shadow_extent = shadow_extent;
+    }
+
+    pub fn shadow_cascades(&self) -> Option<ShadowCascades> {
+        if self.data.shadow_cascades == 0 {
+            return None;
+        }
+        Some(ShadowCascades {
+            count: self.data.shadow_cascades,
+            split_lambda: self.data.cascade_split_lambda,
+            distance: self.data.cascade_distance,
+            blend: self.data.cascade_blend,
+        })
+    }
+
+    /// Fits the shadow maps to slices of each camera's frustum instead of the
+    /// box given by `set_shadow_extent`. Every cascade takes a shadow map layer.
+    pub fn set_shadow_cascades(&mut self, shadow_cascades: Option<ShadowCascades>) {
+        let cascades = shadow_cascades.unwrap_or(ShadowCascades {
+            count: 0,
+            ..Default::default()
+        });
+        self.data.shadow_cascades = cascades.count;
+        self.data.cascade_split_lambda = cascades.split_lambda;
+        self.data.cascade_distance = cascades.distance;
+        self.data.cascade_blend = cascades.blend;
+    }
+
+    /// World direction as of the last `update`.
+    pub fn direction(&self) -> Vector3<f32> {
+        self.data.light_direction
+    }
+}
+
+impl Component for DirectionalLight {
+    fn has_parent(&self) -> bool {
+        self.has_parent
+    }
+    fn has_parent_mut(&mut self) -> &mut bool {
+        &mut self.has_parent
+    }
+    fn scene(&self) -> &Weak<RefCell<Scene>> {
+        &self.scene
+    }
+    fn scene_mut(&mut self) -> &mut Weak<RefCell<Scene>> {
+        &mut self.scene
+    }
+    fn children(&self) -> &Vec<Rc<RefCell<dyn Component>>> {
+        &self.children
+    }
+    fn children_mut(&mut self) -> &mut Vec<Rc<RefCell<dyn Component>>> {
+        &mut self.children
+    }
+    fn transformation(&self) -> &Transformation {
+        &self.transformation
+    }
+    fn transformation_mut(&mut self) -> &mut Transformation {
+        &mut self.transformation
+    }
+    fn added_to_scene(&mut self) {
+        self.scene
+            .upgrade()
+            .unwrap()
+            .borrow_mut()
+            .lights
+            .push(self.this.upgrade().unwrap());
+    }
+    fn update(&mut self) {
+        self.data.set_transformation(&self.transformation);
+
+        let extent = self.shadow_extent;
+        let view_matrix = self
+            .transformation
+            .global_transformation()
+            .invert()
+            .unwrap_or_else(Matrix4::identity);
+        self.data.light_projection_matrix = OPENGL_TO_WGPU_MATRIX
+            * cgmath::ortho(-extent, extent, -extent, extent, -extent, extent)
+            * view_matrix;
+    }
+}
+
+impl Light for DirectionalLight {
+    fn light_uniform(&self) -> LightUniform {
+        self.data
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+};
+
+use cgmath::{InnerSpace, Vector3, Vector4};
+
+use crate::{Component, Light, LightType, LightUniform, Scene, Transformation};
+
+/// Ambient light blending from a sky color for surfaces facing the +y axis of
+/// its transformation to a ground color for surfaces facing away from it.
+pub struct HemisphereLight {
+    data: LightUniform,
+    has_parent: bool,
+    scene: Weak<RefCell<Scene>>,
+    transformation: Transformation,
+    children: Vec<Rc<RefCell<dyn Component>>>,
+    this: Weak<RefCell<HemisphereLight>>,
+}
+
+impl HemisphereLight {
+    pub fn new_ref(
+        sky_color: Vector3<f32>,
+        ground_color: Vector3<f32>,
+        intensity: f32,
+    ) -> Rc<RefCell<HemisphereLight>> {
+        let data = LightUniform {
+            light_type: LightType::Hemisphere as u32,
+            light_color: sky_color,
+            ground_color,
+            light_intensity: intensity,
+            light_direction: -Vector3::unit_y(),
+            ..Default::default()
+        };
+        let light = Rc::new(RefCell::new(HemisphereLight {
+            data,
+            has_parent: false,
+            scene: Weak::new(),
+            transformation: Transformation::new(),
+            children: Vec::new(),
+            this: Weak::new(),
+        }));
+
+        light.borrow_mut().this = Rc::downgrade(&light);
+        light
+    }
+
+    pub fn sky_color(&self) -> Vector3<f32> {
+        self.data.light_color
+    }
+
+    pub fn set_sky_color(&mut self, sky_color: Vector3<f32>) {
+        self.data.light_color = sky_color;
+    }
+
+    pub fn ground_color(&self) -> Vector3<f32> {
+        self.data.ground_color
+    }
+
+    pub fn set_ground_color(&mut self, ground_color: Vector3<f32>) {
+        self.data.ground_color = ground_color;
+    }
+
+    pub fn intensity(&self) -> f32 {
+        self.data.light_intensity
+    }
+
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.data.light_intensity = intensity;
+    }
+}
+
+impl Component for HemisphereLight {
+    fn has_parent(&self) -> bool {
+        self.has_parent
+    }
+    fn has_parent_mut(&mut self) -> &mut bool {
+        &mut self.has_parent
+    }
+    fn scene(&self) -> &Weak<RefCell<Scene>> {
+        &self.scene
+    }
+    fn scene_mut(&mut self) -> &mut Weak<RefCell<Scene>> {
+        &mut self.scene
+    }
+    fn children(&self) -> &Vec<Rc<RefCell<dyn Component>>> {
+        &self.children
+    }
+    fn children_mut(&mut self) -> &mut Vec<Rc<RefCell<dyn Component>>> {
+        &mut self.children
+    }
+    fn transformation(&self) -> &Transformation {
+        &self.transformation
+    }
+    fn transformation_mut(&mut self) -> &mut Transformation {
+        &mut self.transformation
+    }
+    fn added_to_scene(&mut self) {
+        self.scene
+            .upgrade()
+            .unwrap()
+            .borrow_mut()
+            .lights
+            .push(self.this.upgrade().unwrap());
+    }
+    fn update(&mut self) {
+        self.data.set_transformation(&self.transformation);
+        // Shining down from the sky, along the -y axis.
+        self.data.light_direction = (self.transformation.global_transformation()
+            * Vector4::new(0.0, -1.0, 0.0, 0.0))
+        .truncate()
+        .normalize();
+    }
+}
+
+impl Light for HemisphereLight {
+    fn light_uniform(&self) -> LightUniform {
+        self.data
+    }
+}
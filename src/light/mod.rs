@@ -1 +1,5 @@
-mod point_light;
+pub mod ambient_light;
+pub mod directional_light;
+pub mod hemisphere_light;
+pub mod point_light;
+pub mod spot_light;
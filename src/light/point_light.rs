@@ -3,13 +3,112 @@ use std::{
     rc::{Rc, Weak},
 };
 
-use crate::{Component, Light, Scene, Transformation};
+use cgmath::{Deg, Vector3};
 
+use crate::{
+    camera::render_camera::OPENGL_TO_WGPU_MATRIX, Component, Light, LightType, LightUniform, Scene,
+    State, Transformation,
+};
+
+/// Light shining in every direction from the origin of its transformation.
 pub struct PointLight {
+    data: LightUniform,
     has_parent: bool,
     scene: Weak<RefCell<Scene>>,
     transformation: Transformation,
     children: Vec<Rc<RefCell<dyn Component>>>,
+    this: Weak<RefCell<PointLight>>,
+}
+
+impl PointLight {
+    /// Far plane of the shadow maps of a light without a range.
+    pub const SHADOW_FAR: f32 = 1000.0;
+    const SHADOW_NEAR_RATIO: f32 = 0.001;
+
+    /// The light falls off with the inverse square of the distance and is
+    /// smoothly cut off at `range`; a `range` of 0 disables the cutoff.
+    pub fn new_ref(
+        color: Vector3<f32>,
+        intensity: f32,
+        range: f32,
+        _state: &State,
+    ) -> Rc<RefCell<PointLight>> {
+        let data = LightUniform {
+            light_type: LightType::Point as u32,
+            light_color: color,
+            light_intensity: intensity,
+            light_range: range,
+            ..Default::default()
+        };
+        let light = Rc::new(RefCell::new(PointLight {
+            data,
+            has_parent: false,
+            scene: Weak::new(),
+            transformation: Transformation::new(),
+            children: Vec::new(),
+            this: Weak::new(),
+        }));
+
+        light.borrow_mut().this = Rc::downgrade(&light);
+        light
+    }
+
+    pub fn color(&self) -> Vector3<f32> {
+        self.data.light_color
+    }
+
+    pub fn set_color(&mut self, color: Vector3<f32>) {
+        self.data.light_color = color;
+    }
+
+    pub fn intensity(&self) -> f32 {
+        self.data.light_intensity
+    }
+
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.data.light_intensity = intensity;
+    }
+
+    pub fn range(&self) -> f32 {
+        self.data.light_range
+    }
+
+    pub fn set_range(&mut self, range: f32) {
+        self.data.light_range = range;
+    }
+
+    pub fn cast_shadows(&self) -> bool {
+        self.data.shadow_map >= 0
+    }
+
+    /// Shadows are rendered into six shadow map layers, one per cube face,
+    /// for the `LightArray::max_point_shadows` most important point lights.
+    pub fn set_cast_shadows(&mut self, cast_shadows: bool) {
+        self.data.shadow_map = if cast_shadows { 0 } else { -1 };
+    }
+
+    pub fn shadow_bias(&self) -> f32 {
+        self.data.shadow_bias
+    }
+
+    /// Depth offset against shadow acne; too large and shadows detach from casters.
+    pub fn set_shadow_bias(&mut self, shadow_bias: f32) {
+        self.data.shadow_bias = shadow_bias;
+    }
+
+    pub fn shadow_normal_bias(&self) -> f32 {
+        self.data.shadow_normal_bias
+    }
+
+    /// World space offset of receivers along their normal, also against acne.
+    pub fn set_shadow_normal_bias(&mut self, shadow_normal_bias: f32) {
+        self.data.shadow_normal_bias = shadow_normal_bias;
+    }
+
+    /// World position as of the last `update`.
+    pub fn position(&self) -> Vector3<f32> {
+        self.data.light_position
+    }
 }
 
 impl Component for PointLight {
@@ -37,10 +136,29 @@ impl Component for PointLight {
     fn transformation_mut(&mut self) -> &mut Transformation {
         &mut self.transformation
     }
+    fn added_to_scene(&mut self) {
+        self.scene
+            .upgrade()
+            .unwrap()
+            .borrow_mut()
+            .lights
+            .push(self.this.upgrade().unwrap());
+    }
+    fn update(&mut self) {
+        self.data.set_transformation(&self.transformation);
+
+        let far = if self.data.light_range > 0.0 {
+            self.data.light_range
+        } else {
+            PointLight::SHADOW_FAR
+        };
+        self.data.light_projection_matrix = OPENGL_TO_WGPU_MATRIX
+            * cgmath::perspective(Deg(90.0), 1.0, far * PointLight::SHADOW_NEAR_RATIO, far);
+    }
 }
 
 impl Light for PointLight {
-    fn bind_light(&self, _render_pass: &wgpu::RenderPass) {
-        unimplemented!();
+    fn light_uniform(&self) -> LightUniform {
+        self.data
     }
 }
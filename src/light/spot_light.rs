@@ -0,0 +1,197 @@
+use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+};
+
+use cgmath::{prelude::SquareMatrix, Matrix4, Rad, Vector3};
+
+use crate::{
+    camera::render_camera::OPENGL_TO_WGPU_MATRIX, Component, Light, LightType, LightUniform, Scene,
+    State, Transformation,
+};
+
+/// Light shining in a cone from the origin of its transformation along its -z axis.
+pub struct SpotLight {
+    data: LightUniform,
+    has_parent: bool,
+    scene: Weak<RefCell<Scene>>,
+    transformation: Transformation,
+    children: Vec<Rc<RefCell<dyn Component>>>,
+    this: Weak<RefCell<SpotLight>>,
+}
+
+impl SpotLight {
+    /// Far plane of the shadow map of a light without a range.
+    pub const SHADOW_FAR: f32 = 1000.0;
+    const SHADOW_NEAR_RATIO: f32 = 0.001;
+
+    /// Attenuates like a `PointLight`. The cone is fully lit up to
+    /// `inner_angle` and fades out towards `outer_angle`, both half-angles in
+    /// radians.
+    pub fn new_ref(
+        color: Vector3<f32>,
+        intensity: f32,
+        range: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+        _state: &State,
+    ) -> Rc<RefCell<SpotLight>> {
+        let data = LightUniform {
+            light_type: LightType::Spot as u32,
+            light_color: color,
+            light_intensity: intensity,
+            light_range: range,
+            spot_inner_cos: inner_angle.cos(),
+            spot_outer_cos: outer_angle.cos(),
+            ..Default::default()
+        };
+
+        let light = Rc::new(RefCell::new(SpotLight {
+            data,
+            has_parent: false,
+            scene: Weak::new(),
+            transformation: Transformation::new(),
+            children: Vec::new(),
+            this: Weak::new(),
+        }));
+
+        light.borrow_mut().this = Rc::downgrade(&light);
+        light
+    }
+
+    pub fn color(&self) -> Vector3<f32> {
+        self.data.light_color
+    }
+
+    pub fn set_color(&mut self, color: Vector3<f32>) {
+        self.data.light_color = color;
+    }
+
+    pub fn intensity(&self) -> f32 {
+        self.data.light_intensity
+    }
+
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.data.light_intensity = intensity;
+    }
+
+    pub fn range(&self) -> f32 {
+        self.data.light_range
+    }
+
+    pub fn set_range(&mut self, range: f32) {
+        self.data.light_range = range;
+    }
+
+    pub fn inner_angle(&self) -> f32 {
+        self.data.spot_inner_cos.acos()
+    }
+
+    pub fn set_inner_angle(&mut self, inner_angle: f32) {
+        self.data.spot_inner_cos = inner_angle.cos();
+    }
+
+    pub fn outer_angle(&self) -> f32 {
+        self.data.spot_outer_cos.acos()
+    }
+
+    pub fn set_outer_angle(&mut self, outer_angle: f32) {
+        self.data.spot_outer_cos = outer_angle.cos();
+    }
+
+    pub fn cast_shadows(&self) -> bool {
+        self.data.shadow_map >= 0
+    }
+
+    /// Shadows are rendered while the camera has free shadow map layers.
+    pub fn set_cast_shadows(&mut self, cast_shadows: bool) {
+        self.data.shadow_map = if cast_shadows { 0 } else { -1 };
+    }
+
+    pub fn shadow_bias(&self) -> f32 {
+        self.data.shadow_bias
+    }
+
+    /// Depth offset against shadow acne; too large and shadows detach from casters.
+    pub fn set_shadow_bias(&mut self, shadow_bias: f32) {
+        self.data.shadow_bias = shadow_bias;
+    }
+
+    pub fn shadow_normal_bias(&self) -> f32 {
+        self.data.shadow_normal_bias
+    }
+
+    /// World space offset along the receiver's normal, for acne at grazing angles.
+    pub fn set_shadow_normal_bias(&mut self, shadow_normal_bias: f32) {
+        self.data.shadow_normal_bias = shadow_normal_bias;
+    }
+
+    /// World position as of the last `update`.
+    pub fn position(&self) -> Vector3<f32> {
+        self.data.light_position
+    }
+
+    /// World direction as of the last `update`.
+    pub fn direction(&self) -> Vector3<f32> {
+        self.data.light_direction
+    }
+}
+
+impl Component for SpotLight {
+    fn has_parent(&self) -> bool {
+        self.has_parent
+    }
+    fn has_parent_mut(&mut self) -> &mut bool {
+        &mut self.has_parent
+    }
+    fn scene(&self) -> &Weak<RefCell<Scene>> {
+        &self.scene
+    }
+    fn scene_mut(&mut self) -> &mut Weak<RefCell<Scene>> {
+        &mut self.scene
+    }
+    fn children(&self) -> &Vec<Rc<RefCell<dyn Component>>> {
+        &self.children
+    }
+    fn children_mut(&mut self) -> &mut Vec<Rc<RefCell<dyn Component>>> {
+        &mut self.children
+    }
+    fn transformation(&self) -> &Transformation {
+        &self.transformation
+    }
+    fn transformation_mut(&mut self) -> &mut Transformation {
+        &mut self.transformation
+    }
+    fn added_to_scene(&mut self) {
+        self.scene
+            .upgrade()
+            .unwrap()
+            .borrow_mut()
+            .lights
+            .push(self.this.upgrade().unwrap());
+    }
+    fn update(&mut self) {
+        self.data.set_transformation(&self.transformation);
+
+        let far = if self.data.light_range > 0.0 {
+            self.data.light_range
+        } else {
+            SpotLight::SHADOW_FAR
+        };
+        let view_matrix = self
+            .transformation
+            .global_transformation()
+            .invert()
+            .unwrap_or_else(Matrix4::identity);
+        let fov = Rad((2.0 * self.outer_angle()).min(3.1));
+        self.data.light_projection_matrix = OPENGL_TO_WGPU_MATRIX
+            * cgmath::perspective(fov, 1.0, far * SpotLight::SHADOW_NEAR_RATIO, far)
+            * view_matrix;
+    }
+}
+
+impl Light for SpotLight {
+    fn light_uniform(&self) -> LightUniform {
+        self.data
+    }
+}
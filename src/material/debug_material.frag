@@ -0,0 +1,50 @@
+#version 450
+
+layout(push_constant) uniform ConstData {
+  mat4 camera_projection;
+  vec3 camera_position;
+  mat4 mesh_transformation;
+  mat3 normal_transformation;
+} PushConstant;
+
+layout(location=0) in vec3 v_position;
+layout(location=1) in vec3 v_normal;
+layout(location=2) in vec2 v_tex_coord;
+
+layout(location=0) out vec4 f_color;
+
+layout(std140, set=2, binding=0)
+uniform DebugParameters {
+    mat4 view_matrix;
+    vec3 wireframe_color;
+    uint mode;
+    float depth_range;
+};
+
+const uint MODE_WIREFRAME = 0;
+const uint MODE_WORLD_NORMALS = 1;
+const uint MODE_VIEW_NORMALS = 2;
+const uint MODE_UV_CHECKER = 3;
+const uint MODE_DEPTH = 4;
+const uint MODE_OVERDRAW = 5;
+
+void main() {
+    vec3 normal = normalize(v_normal);
+    if (mode == MODE_WIREFRAME) {
+        f_color = vec4(wireframe_color, 1.0);
+    } else if (mode == MODE_WORLD_NORMALS) {
+        f_color = vec4(normal * 0.5 + 0.5, 1.0);
+    } else if (mode == MODE_VIEW_NORMALS) {
+        f_color = vec4(normalize(mat3(view_matrix) * normal) * 0.5 + 0.5, 1.0);
+    } else if (mode == MODE_UV_CHECKER) {
+        vec2 cell = floor(fract(v_tex_coord) * 8.0);
+        float checker = mod(cell.x + cell.y, 2.0);
+        f_color = vec4(mix(vec3(fract(v_tex_coord), 0.0), vec3(1.0), checker * 0.5), 1.0);
+    } else if (mode == MODE_DEPTH) {
+        float depth = clamp(length(v_position - PushConstant.camera_position) / depth_range, 0.0, 1.0);
+        f_color = vec4(vec3(1.0 - depth), 1.0);
+    } else {
+        // Accumulated additively, so overlapping fragments heat up.
+        f_color = vec4(0.1, 0.04, 0.02, 1.0);
+    }
+}
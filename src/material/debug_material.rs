@@ -0,0 +1,189 @@
+use cgmath::{prelude::One, Matrix4, Vector3};
+
+use crate::{
+    Bindable, BlendMode, DepthState, Geometry, Light, Material, RenderPassWrapper, State,
+    UniformBuffer,
+};
+
+/// Diagnostic renderings, see `Mesh::debug_view` and `RenderCamera::set_debug_view`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DebugView {
+    /// Triangle edges drawn over the mesh's own material.
+    Wireframe,
+    WorldNormals,
+    ViewNormals,
+    UvChecker,
+    /// Distance to the camera, white near and black at the depth range.
+    Depth,
+    /// Every fragment adds a little heat, showing how often pixels are drawn.
+    Overdraw,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct DebugMaterialData {
+    view_matrix: Matrix4<f32>,
+    wireframe_color: Vector3<f32>,
+    mode: u32,
+    depth_range: f32,
+}
+
+unsafe impl bytemuck::Zeroable for DebugMaterialData {}
+unsafe impl bytemuck::Pod for DebugMaterialData {}
+
+pub struct DebugMaterial {
+    view: DebugView,
+    barycentric: bool,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    uniform: UniformBuffer<DebugMaterialData>,
+}
+
+impl DebugMaterial {
+    /// With `barycentric` set, the wireframe is found from the barycentric
+    /// coordinates of a `WireframeGeometry` rather than by drawing lines.
+    pub fn new(view: DebugView, barycentric: bool, state: &State) -> DebugMaterial {
+        let uniform = UniformBuffer::new(
+            DebugMaterialData {
+                view_matrix: Matrix4::one(),
+                wireframe_color: Vector3::new(1.0, 1.0, 1.0),
+                mode: view as u32,
+                depth_range: 100.0,
+            },
+            Some("Debug Material Buffer"),
+            state,
+        );
+
+        let bind_group_layout =
+            state
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::UniformBuffer {
+                            dynamic: false,
+                            min_binding_size: std::num::NonZeroU64::new(std::mem::size_of::<
+                                DebugMaterialData,
+                            >(
+                            )
+                                as u64),
+                        },
+                        count: None,
+                    }],
+                    label: Some("debug_material_bind_group_layout"),
+                });
+
+        let bind_group = state.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform.buffer().as_entire_binding(),
+            }],
+            label: Some("debug_material_bind_group"),
+        });
+
+        DebugMaterial {
+            view,
+            barycentric,
+            bind_group_layout,
+            bind_group,
+            uniform,
+        }
+    }
+
+    pub fn view(&self) -> DebugView {
+        self.view
+    }
+
+    pub fn set_view_matrix(&mut self, view_matrix: Matrix4<f32>) {
+        if self.uniform.data().view_matrix != view_matrix {
+            self.uniform.data_mut().view_matrix = view_matrix;
+        }
+    }
+
+    pub fn set_wireframe_color(&mut self, wireframe_color: Vector3<f32>) {
+        self.uniform.data_mut().wireframe_color = wireframe_color;
+    }
+
+    pub fn set_depth_range(&mut self, depth_range: f32) {
+        self.uniform.data_mut().depth_range = depth_range;
+    }
+}
+
+impl Bindable for DebugMaterial {
+    fn bind_group(&self) -> Option<&wgpu::BindGroup> {
+        Some(&self.bind_group)
+    }
+}
+
+impl Material for DebugMaterial {
+    fn fragment_stage(&self) -> wgpu::ShaderModuleSource<'static> {
+        if self.barycentric {
+            wgpu::include_spirv!(concat!(
+                env!("OUT_DIR"),
+                "/material/debug_wireframe.frag.spv"
+            ))
+        } else {
+            wgpu::include_spirv!(concat!(
+                env!("OUT_DIR"),
+                "/material/debug_material.frag.spv"
+            ))
+        }
+    }
+    fn color_states(&self, format: wgpu::TextureFormat) -> Vec<wgpu::ColorStateDescriptor> {
+        vec![wgpu::ColorStateDescriptor {
+            format,
+            color_blend: self.blend_mode().color_blend(),
+            alpha_blend: self.blend_mode().alpha_blend(),
+            write_mask: wgpu::ColorWrite::ALL,
+        }]
+    }
+    /// Overdraw counts every fragment and the wireframe lies on the surface
+    /// drawn just before it.
+    fn depth_state(&self) -> DepthState {
+        match self.view {
+            DebugView::Overdraw => DepthState::disabled(),
+            DebugView::Wireframe => DepthState {
+                write: false,
+                compare: wgpu::CompareFunction::LessEqual,
+                ..DepthState::for_blend_mode(self.blend_mode())
+            },
+            _ => DepthState::for_blend_mode(self.blend_mode()),
+        }
+    }
+    fn sample_mask(&self) -> u32 {
+        !0
+    }
+
+    fn alpha_to_coverage_enabled(&self) -> bool {
+        false
+    }
+
+    fn blend_mode(&self) -> BlendMode {
+        match self.view {
+            DebugView::Overdraw => BlendMode::Additive,
+            _ => BlendMode::Opaque,
+        }
+    }
+
+    fn update_buffers(&self, state: &State) {
+        self.uniform.flush(state);
+    }
+
+    fn draw_material<'a>(
+        &'a self,
+        geometry: &'a dyn Geometry,
+        _lights: &'a Vec<&dyn Light>,
+        pipeline: &'a wgpu::RenderPipeline,
+        render_pass: &mut RenderPassWrapper<'a, '_>,
+    ) {
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_groups(geometry, self);
+        geometry.draw_geometry(render_pass);
+    }
+
+    fn material_bind_group_layout(&self) -> Option<&wgpu::BindGroupLayout> {
+        Some(&self.bind_group_layout)
+    }
+}
@@ -0,0 +1,23 @@
+#version 450
+
+layout(location=3) in vec3 v_barycentric;
+
+layout(location=0) out vec4 f_color;
+
+layout(std140, set=2, binding=0)
+uniform DebugParameters {
+    mat4 view_matrix;
+    vec3 wireframe_color;
+    uint mode;
+    float depth_range;
+};
+
+void main() {
+    vec3 width = fwidth(v_barycentric);
+    vec3 edge = smoothstep(vec3(0.0), width * 1.5, v_barycentric);
+    float coverage = 1.0 - min(min(edge.x, edge.y), edge.z);
+    if (coverage < 0.5) {
+        discard;
+    }
+    f_color = vec4(wireframe_color, 1.0);
+}
@@ -1 +1,4 @@
+pub mod debug_material;
+pub mod pbr_material;
+pub mod shader_material;
 pub mod solid_color_material;
@@ -0,0 +1,338 @@
+#version 450
+
+layout(push_constant) uniform ConstData {
+  mat4 camera_projection;
+  vec3 camera_position;
+  mat4 mesh_transformation;
+  mat3 normal_transformation;
+  uint receive_shadows;
+} PushConstant;
+
+layout(location=0) in vec3 v_position;
+layout(location=1) in vec3 v_normal;
+layout(location=2) in vec2 v_tex_coord;
+
+layout(location=0) out vec4 f_color;
+
+struct Light {
+    vec3 light_position;
+    uint light_type;
+    vec3 light_color;
+    float light_intensity;
+    mat4 light_projection_matrix;
+    vec3 light_direction;
+    float light_range;
+    vec3 ground_color;
+    float spot_inner_cos;
+    float spot_outer_cos;
+    int shadow_map;
+    float shadow_bias;
+    float shadow_normal_bias;
+    uint shadow_cascades;
+    float cascade_split_lambda;
+    float cascade_distance;
+    float cascade_blend;
+};
+
+layout(std430, set=0, binding=0)
+readonly buffer LightInformation {
+    uint light_count;
+    Light lights[];
+};
+
+// Offset into `light_indices` and light count of each cluster.
+layout(std430, set=0, binding=1)
+readonly buffer LightClusters {
+    mat4 cluster_view_matrix;
+    uvec3 cluster_dimensions;
+    float cluster_near;
+    vec2 cluster_screen_size;
+    float cluster_far;
+    vec2 cluster_screen_offset;
+    uvec2 clusters[];
+};
+
+layout(std430, set=0, binding=2)
+readonly buffer LightIndices {
+    uint light_indices[];
+};
+
+layout(set=0, binding=3) uniform texture2DArray shadow_maps;
+layout(set=0, binding=4) uniform samplerShadow shadow_sampler;
+
+struct ShadowLayer {
+    mat4 projection_matrix;
+    float split_depth;
+};
+
+layout(std430, set=0, binding=5)
+readonly buffer ShadowLayers {
+    ShadowLayer shadow_layers[];
+};
+
+layout(std140, set=0, binding=6)
+uniform EnvironmentParameters {
+    float environment_intensity;
+    float specular_mip_levels;
+};
+
+layout(set=0, binding=7) uniform textureCube irradiance_map;
+layout(set=0, binding=8) uniform textureCube specular_map;
+layout(set=0, binding=9) uniform texture2D brdf_lut;
+layout(set=0, binding=10) uniform sampler environment_sampler;
+
+const uint POINT_LIGHT = 0;
+const uint DIRECTIONAL_LIGHT = 1;
+const uint SPOT_LIGHT = 2;
+const uint AMBIENT_LIGHT = 3;
+const uint HEMISPHERE_LIGHT = 4;
+
+layout(std140, set=2, binding=0)
+uniform MaterialParameters {
+    vec4 base_color_factor;
+    vec3 emissive_factor;
+    float metallic_factor;
+    float roughness_factor;
+    float occlusion_strength;
+    float normal_scale;
+    float alpha_cutoff;
+    uint premultiply;
+};
+
+layout(set=2, binding=1) uniform sampler material_sampler;
+layout(set=2, binding=2) uniform texture2D base_color_texture;
+layout(set=2, binding=3) uniform texture2D metallic_roughness_texture;
+layout(set=2, binding=4) uniform texture2D normal_texture;
+layout(set=2, binding=5) uniform texture2D occlusion_texture;
+layout(set=2, binding=6) uniform texture2D emissive_texture;
+
+const float PI = 3.14159265359;
+
+float distribution_ggx(float n_dot_h, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+float geometry_smith(float n_dot_v, float n_dot_l, float roughness) {
+    float r = roughness + 1.0;
+    float k = r * r / 8.0;
+    float g_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    float g_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return g_v * g_l;
+}
+
+// Inverse square falloff, smoothly reaching zero at the light's range.
+float range_attenuation(float distance, float range) {
+    float falloff = 1.0;
+    if (range > 0.0) {
+        float ratio = distance / range;
+        falloff = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+        falloff *= falloff;
+    }
+    return falloff / max(distance * distance, 1e-4);
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(1.0 - cos_theta, 5.0);
+}
+
+// Fresnel averaged over the microfacets of a rough surface, for light from
+// every direction.
+vec3 fresnel_schlick_roughness(float cos_theta, vec3 f0, float roughness) {
+    return f0 + (max(vec3(1.0 - roughness), f0) - f0) * pow(1.0 - cos_theta, 5.0);
+}
+
+// Tangent frame from screen-space derivatives, so geometries do not need to
+// provide tangents for normal mapping.
+vec3 perturb_normal(vec3 normal) {
+    vec3 tangent_normal = texture(sampler2D(normal_texture, material_sampler), v_tex_coord).xyz * 2.0 - 1.0;
+    tangent_normal.xy *= normal_scale;
+
+    vec3 dp1 = dFdx(v_position);
+    vec3 dp2 = dFdy(v_position);
+    vec2 duv1 = dFdx(v_tex_coord);
+    vec2 duv2 = dFdy(v_tex_coord);
+
+    vec3 dp2perp = cross(dp2, normal);
+    vec3 dp1perp = cross(normal, dp1);
+    vec3 tangent = dp2perp * duv1.x + dp1perp * duv2.x;
+    vec3 bitangent = dp2perp * duv1.y + dp1perp * duv2.y;
+    float inv_max = inversesqrt(max(dot(tangent, tangent), dot(bitangent, bitangent)));
+    mat3 tbn = mat3(tangent * inv_max, bitangent * inv_max, normal);
+
+    return normalize(tbn * tangent_normal);
+}
+
+// Same layout as `ClusterGrid::cluster_index`, with exponential depth slices.
+uint cluster_index() {
+    float depth = -(cluster_view_matrix * vec4(v_position, 1.0)).z;
+    uvec2 tile = uvec2(clamp((gl_FragCoord.xy - cluster_screen_offset) / cluster_screen_size, 0.0, 0.9999) * vec2(cluster_dimensions.xy));
+    float slice = clamp(log(depth / cluster_near) / log(cluster_far / cluster_near), 0.0, 0.9999);
+    uint z = uint(slice * float(cluster_dimensions.z));
+    return (z * cluster_dimensions.y + tile.y) * cluster_dimensions.x + tile.x;
+}
+
+// Fraction of a shadow map layer's light reaching `position`, filtered over 3x3 texels.
+float sample_shadow(uint layer, float bias, vec3 position) {
+    vec4 clip = shadow_layers[layer].projection_matrix * vec4(position, 1.0);
+    vec3 ndc = clip.xyz / clip.w;
+    if (clip.w <= 0.0 || ndc.z > 1.0 || any(greaterThan(abs(ndc.xy), vec2(1.0)))) {
+        return 1.0;
+    }
+    vec2 uv = vec2(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+    vec2 texel = 1.0 / vec2(textureSize(sampler2DArrayShadow(shadow_maps, shadow_sampler), 0).xy);
+    float lit = 0.0;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            vec4 coordinate = vec4(uv + vec2(x, y) * texel, layer, ndc.z - bias);
+            lit += texture(sampler2DArrayShadow(shadow_maps, shadow_sampler), coordinate);
+        }
+    }
+    return lit / 9.0;
+}
+
+// Cube face a direction points through, in the order of the point light
+// shadow layers: +x, -x, +y, -y, +z, -z.
+uint cube_face(vec3 direction) {
+    vec3 magnitude = abs(direction);
+    if (magnitude.x >= magnitude.y && magnitude.x >= magnitude.z) {
+        return direction.x > 0.0 ? 0 : 1;
+    }
+    if (magnitude.y >= magnitude.z) {
+        return direction.y > 0.0 ? 2 : 3;
+    }
+    return direction.z > 0.0 ? 4 : 5;
+}
+
+// Fraction of the light reaching the fragment. Point lights use the cube face
+// layer facing the fragment. Cascades are picked by view depth and fade into
+// the next cascade over the last part of their range.
+float shadow_factor(Light light, vec3 n) {
+    if (light.shadow_map < 0 || PushConstant.receive_shadows == 0) {
+        return 1.0;
+    }
+    vec3 position = v_position + n * light.shadow_normal_bias;
+    uint first_layer = uint(light.shadow_map);
+    if (light.light_type == POINT_LIGHT) {
+        return sample_shadow(first_layer + cube_face(v_position - light.light_position),
+                             light.shadow_bias, position);
+    }
+    if (light.shadow_cascades == 0) {
+        return sample_shadow(first_layer, light.shadow_bias, position);
+    }
+
+    float depth = -(cluster_view_matrix * vec4(v_position, 1.0)).z;
+    float cascade_near = cluster_near;
+    for (uint i = 0; i < light.shadow_cascades; i++) {
+        uint layer = first_layer + i;
+        float split = shadow_layers[layer].split_depth;
+        if (depth < split) {
+            float shadow = sample_shadow(layer, light.shadow_bias, position);
+            float blend_start = split - (split - cascade_near) * light.cascade_blend;
+            if (i + 1 < light.shadow_cascades && depth > blend_start) {
+                float next = sample_shadow(layer + 1, light.shadow_bias, position);
+                shadow = mix(shadow, next, (depth - blend_start) / (split - blend_start));
+            }
+            return shadow;
+        }
+        cascade_near = split;
+    }
+    return 1.0;
+}
+
+// Radiance reaching the fragment from every direction, as diffusely
+// reflected by a white surface.
+vec3 ambient_light(Light light, vec3 n) {
+    vec3 color = light.light_color;
+    if (light.light_type == HEMISPHERE_LIGHT) {
+        float sky = dot(n, -normalize(light.light_direction)) * 0.5 + 0.5;
+        color = mix(light.ground_color, light.light_color, sky);
+    }
+    return color * light.light_intensity;
+}
+
+// Diffuse and specular light from ambient lights and the environment maps,
+// with the split sum approximation for the specular part.
+vec3 environment_light(vec3 ambient, vec3 n, vec3 v, vec3 base_color, float metallic, float roughness) {
+    float n_dot_v = max(dot(n, v), 1e-4);
+    vec3 f0 = mix(vec3(0.04), base_color, metallic);
+    vec3 f = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+    vec3 irradiance = ambient;
+    vec3 specular = vec3(0.0);
+    if (environment_intensity > 0.0) {
+        irradiance += texture(samplerCube(irradiance_map, environment_sampler), n).rgb * environment_intensity;
+        float lod = roughness * (specular_mip_levels - 1.0);
+        vec3 prefiltered = textureLod(samplerCube(specular_map, environment_sampler), reflect(-v, n), lod).rgb;
+        vec2 brdf = texture(sampler2D(brdf_lut, environment_sampler), vec2(n_dot_v, roughness)).rg;
+        specular = prefiltered * (f * brdf.x + brdf.y) * environment_intensity;
+    }
+    vec3 diffuse = (1.0 - f) * (1.0 - metallic) * base_color * irradiance;
+    return diffuse + specular;
+}
+
+// Radiance reflected towards the viewer by a single light.
+vec3 shade(Light light, vec3 n, vec3 v, vec3 base_color, float metallic, float roughness) {
+    vec3 l;
+    float attenuation = 1.0;
+    if (light.light_type == DIRECTIONAL_LIGHT) {
+        l = normalize(-light.light_direction);
+    } else {
+        vec3 to_light = light.light_position - v_position;
+        l = normalize(to_light);
+        attenuation = range_attenuation(length(to_light), light.light_range);
+        if (light.light_type == SPOT_LIGHT) {
+            attenuation *= smoothstep(light.spot_outer_cos, light.spot_inner_cos, dot(-l, normalize(light.light_direction)));
+        }
+    }
+    vec3 h = normalize(v + l);
+
+    float n_dot_v = max(dot(n, v), 1e-4);
+    float n_dot_l = max(dot(n, l), 0.0);
+    float n_dot_h = max(dot(n, h), 0.0);
+    float h_dot_v = max(dot(h, v), 0.0);
+
+    vec3 f0 = mix(vec3(0.04), base_color, metallic);
+    vec3 f = fresnel_schlick(h_dot_v, f0);
+    float d = distribution_ggx(n_dot_h, roughness);
+    float g = geometry_smith(n_dot_v, n_dot_l, roughness);
+
+    vec3 specular = d * g * f / (4.0 * n_dot_v * max(n_dot_l, 1e-4));
+    vec3 diffuse = (1.0 - f) * (1.0 - metallic) * base_color / PI;
+
+    vec3 radiance = light.light_color * light.light_intensity * attenuation * shadow_factor(light, n);
+    return (diffuse + specular) * radiance * n_dot_l;
+}
+
+void main() {
+    vec4 base_color = base_color_factor * texture(sampler2D(base_color_texture, material_sampler), v_tex_coord);
+    if (base_color.a < alpha_cutoff) {
+        discard;
+    }
+    vec4 metallic_roughness = texture(sampler2D(metallic_roughness_texture, material_sampler), v_tex_coord);
+    float metallic = clamp(metallic_factor * metallic_roughness.b, 0.0, 1.0);
+    float roughness = clamp(roughness_factor * metallic_roughness.g, 0.04, 1.0);
+    float occlusion = mix(1.0, texture(sampler2D(occlusion_texture, material_sampler), v_tex_coord).r, occlusion_strength);
+    vec3 emissive = emissive_factor * texture(sampler2D(emissive_texture, material_sampler), v_tex_coord).rgb;
+
+    vec3 n = perturb_normal(normalize(v_normal));
+    vec3 v = normalize(PushConstant.camera_position - v_position);
+    vec3 color = vec3(0.0);
+    vec3 ambient = vec3(0.0);
+    uvec2 cluster = clusters[cluster_index()];
+    for (uint i = cluster.x; i < cluster.x + cluster.y; i++) {
+        Light light = lights[light_indices[i]];
+        if (light.light_type == AMBIENT_LIGHT || light.light_type == HEMISPHERE_LIGHT) {
+            ambient += ambient_light(light, n);
+        } else {
+            color += shade(light, n, v, base_color.rgb, metallic, roughness);
+        }
+    }
+    color += environment_light(ambient, n, v, base_color.rgb, metallic, roughness);
+    color = color * occlusion + emissive;
+
+    // The result stays linear: sRGB targets encode it on write, HDR cameras
+    // draw into a float texture and their tonemapping pass encodes it.
+    f_color = premultiply != 0 ? vec4(color * base_color.a, base_color.a) : vec4(color, base_color.a);
+}
@@ -0,0 +1,265 @@
+use std::path::Path;
+
+use cgmath::{Vector3, Vector4};
+
+use crate::{
+    Bindable, BlendMode, Geometry, Light, Material, RenderPassWrapper, State, Texture,
+    UniformBuffer,
+};
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct PbrMaterialData {
+    base_color_factor: Vector4<f32>,
+    emissive_factor: Vector3<f32>,
+    metallic_factor: f32,
+    roughness_factor: f32,
+    occlusion_strength: f32,
+    normal_scale: f32,
+    alpha_cutoff: f32,
+    /// 1 when the blend mode expects the color multiplied by the alpha.
+    premultiply: u32,
+    /// Pads the struct to its std140 size.
+    _padding: [u32; 3],
+}
+
+unsafe impl bytemuck::Zeroable for PbrMaterialData {}
+unsafe impl bytemuck::Pod for PbrMaterialData {}
+
+/// Parameters of a glTF metallic-roughness material. Factors are linear and
+/// multiply the matching texture; missing textures behave as if they were white
+/// (or a flat normal).
+pub struct PbrMaterialDescriptor<'a> {
+    pub base_color_factor: Vector4<f32>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub emissive_factor: Vector3<f32>,
+    pub occlusion_strength: f32,
+    pub normal_scale: f32,
+    /// sRGB encoded.
+    pub base_color_texture: Option<&'a image::RgbaImage>,
+    /// Linear, roughness in the green channel and metalness in the blue channel.
+    pub metallic_roughness_texture: Option<&'a image::RgbaImage>,
+    /// Linear, tangent space.
+    pub normal_texture: Option<&'a image::RgbaImage>,
+    /// Linear, occlusion in the red channel.
+    pub occlusion_texture: Option<&'a image::RgbaImage>,
+    /// sRGB encoded.
+    pub emissive_texture: Option<&'a image::RgbaImage>,
+    pub blend_mode: BlendMode,
+}
+
+impl Default for PbrMaterialDescriptor<'_> {
+    fn default() -> Self {
+        PbrMaterialDescriptor {
+            base_color_factor: Vector4::new(1.0, 1.0, 1.0, 1.0),
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            emissive_factor: Vector3::new(0.0, 0.0, 0.0),
+            occlusion_strength: 1.0,
+            normal_scale: 1.0,
+            base_color_texture: None,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            occlusion_texture: None,
+            emissive_texture: None,
+            blend_mode: BlendMode::Opaque,
+        }
+    }
+}
+
+pub struct PbrMaterial {
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    uniform: UniformBuffer<PbrMaterialData>,
+    blend_mode: BlendMode,
+}
+
+impl PbrMaterial {
+    pub fn new(descriptor: &PbrMaterialDescriptor, state: &State) -> PbrMaterial {
+        let uniform = UniformBuffer::new(
+            PbrMaterialData {
+                base_color_factor: descriptor.base_color_factor,
+                emissive_factor: descriptor.emissive_factor,
+                metallic_factor: descriptor.metallic_factor,
+                roughness_factor: descriptor.roughness_factor,
+                occlusion_strength: descriptor.occlusion_strength,
+                normal_scale: descriptor.normal_scale,
+                alpha_cutoff: descriptor.blend_mode.alpha_cutoff(),
+                premultiply: (descriptor.blend_mode == BlendMode::Premultiplied) as u32,
+                _padding: [0; 3],
+            },
+            Some("PBR Material Buffer"),
+            state,
+        );
+
+        let load = |image: Option<&image::RgbaImage>, fallback: [u8; 4], srgb: bool| match image {
+            Some(image) => Texture::from_image(image, srgb, None, state),
+            None => Texture::from_color(fallback, srgb, None, state),
+        };
+        let textures = [
+            load(descriptor.base_color_texture, [255, 255, 255, 255], true),
+            load(descriptor.metallic_roughness_texture, [255, 255, 255, 255], false),
+            load(descriptor.normal_texture, [128, 128, 255, 255], false),
+            load(descriptor.occlusion_texture, [255, 255, 255, 255], false),
+            load(descriptor.emissive_texture, [255, 255, 255, 255], true),
+        ];
+
+        let sampler = state.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("pbr_material_sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let mut layout_entries = vec![
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::UniformBuffer {
+                    dynamic: false,
+                    min_binding_size: std::num::NonZeroU64::new(
+                        std::mem::size_of::<PbrMaterialData>() as u64,
+                    ),
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::Sampler { comparison: false },
+                count: None,
+            },
+        ];
+        let mut entries = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform.buffer().as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&sampler),
+            },
+        ];
+        for (i, texture) in textures.iter().enumerate() {
+            layout_entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 2 + i as u32,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::SampledTexture {
+                    dimension: wgpu::TextureViewDimension::D2,
+                    component_type: wgpu::TextureComponentType::Float,
+                    multisampled: false,
+                },
+                count: None,
+            });
+            entries.push(wgpu::BindGroupEntry {
+                binding: 2 + i as u32,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            });
+        }
+
+        let bind_group_layout =
+            state
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    entries: &layout_entries,
+                    label: Some("pbr_material_bind_group_layout"),
+                });
+
+        let bind_group = state.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &entries,
+            label: Some("pbr_material_bind_group"),
+        });
+
+        PbrMaterial {
+            bind_group_layout,
+            bind_group,
+            uniform,
+            blend_mode: descriptor.blend_mode,
+        }
+    }
+
+    pub fn set_base_color_factor(&mut self, base_color_factor: Vector4<f32>) {
+        self.uniform.data_mut().base_color_factor = base_color_factor;
+    }
+
+    pub fn set_metallic_factor(&mut self, metallic_factor: f32) {
+        self.uniform.data_mut().metallic_factor = metallic_factor;
+    }
+
+    pub fn set_roughness_factor(&mut self, roughness_factor: f32) {
+        self.uniform.data_mut().roughness_factor = roughness_factor;
+    }
+
+    pub fn set_emissive_factor(&mut self, emissive_factor: Vector3<f32>) {
+        self.uniform.data_mut().emissive_factor = emissive_factor;
+    }
+
+    pub fn set_occlusion_strength(&mut self, occlusion_strength: f32) {
+        self.uniform.data_mut().occlusion_strength = occlusion_strength;
+    }
+
+    pub fn set_normal_scale(&mut self, normal_scale: f32) {
+        self.uniform.data_mut().normal_scale = normal_scale;
+    }
+}
+
+impl Bindable for PbrMaterial {
+    fn bind_group(&self) -> Option<&wgpu::BindGroup> {
+        Some(&self.bind_group)
+    }
+}
+
+impl Material for PbrMaterial {
+    fn fragment_stage(&self) -> wgpu::ShaderModuleSource<'static> {
+        wgpu::include_spirv!(concat!(env!("OUT_DIR"), "/material/pbr_material.frag.spv"))
+    }
+    fn color_states(&self, format: wgpu::TextureFormat) -> Vec<wgpu::ColorStateDescriptor> {
+        vec![wgpu::ColorStateDescriptor {
+            format,
+            color_blend: self.blend_mode.color_blend(),
+            alpha_blend: self.blend_mode.alpha_blend(),
+            write_mask: wgpu::ColorWrite::ALL,
+        }]
+    }
+    fn sample_mask(&self) -> u32 {
+        !0
+    }
+
+    fn alpha_to_coverage_enabled(&self) -> bool {
+        false
+    }
+
+    fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    fn update_buffers(&self, state: &State) {
+        self.uniform.flush(state);
+    }
+
+    fn draw_material<'a>(
+        &'a self,
+        geometry: &'a dyn Geometry,
+        _lights: &'a Vec<&dyn Light>,
+        pipeline: &'a wgpu::RenderPipeline,
+        render_pass: &mut RenderPassWrapper<'a, '_>,
+    ) {
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_groups(geometry, self);
+        geometry.draw_geometry(render_pass);
+    }
+
+    fn material_bind_group_layout(&self) -> Option<&wgpu::BindGroupLayout> {
+        Some(&self.bind_group_layout)
+    }
+
+    fn fragment_shader_path(&self) -> Option<&Path> {
+        Some(Path::new("material/pbr_material.frag"))
+    }
+}
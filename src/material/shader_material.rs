@@ -0,0 +1,466 @@
+use std::{
+    cell::Cell,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use cgmath::{Matrix3, Matrix4, Vector2, Vector3, Vector4};
+
+use crate::{
+    load_shader, reflect_bindings, Bindable, BlendMode, Geometry, Light, Material,
+    ReflectedBinding, ReflectedBindingType, ReflectedMember, RenderPassWrapper, ShaderError,
+    ShaderSource, State, Texture, UniformType, MATERIAL_BIND_GROUP,
+};
+
+/// A Rust value that can be written into a std140 uniform block member.
+pub trait UniformValue {
+    fn uniform_type(&self) -> UniformType;
+    fn std140_bytes(&self) -> Vec<u8>;
+}
+
+impl UniformValue for f32 {
+    fn uniform_type(&self) -> UniformType {
+        UniformType::Float
+    }
+    fn std140_bytes(&self) -> Vec<u8> {
+        bytemuck::bytes_of(self).to_vec()
+    }
+}
+
+impl UniformValue for i32 {
+    fn uniform_type(&self) -> UniformType {
+        UniformType::Int
+    }
+    fn std140_bytes(&self) -> Vec<u8> {
+        bytemuck::bytes_of(self).to_vec()
+    }
+}
+
+impl UniformValue for u32 {
+    fn uniform_type(&self) -> UniformType {
+        UniformType::Uint
+    }
+    fn std140_bytes(&self) -> Vec<u8> {
+        bytemuck::bytes_of(self).to_vec()
+    }
+}
+
+impl UniformValue for bool {
+    fn uniform_type(&self) -> UniformType {
+        UniformType::Bool
+    }
+    fn std140_bytes(&self) -> Vec<u8> {
+        bytemuck::bytes_of(&(*self as u32)).to_vec()
+    }
+}
+
+impl UniformValue for Vector2<f32> {
+    fn uniform_type(&self) -> UniformType {
+        UniformType::Vec2
+    }
+    fn std140_bytes(&self) -> Vec<u8> {
+        bytemuck::cast_slice(&[self.x, self.y]).to_vec()
+    }
+}
+
+impl UniformValue for Vector3<f32> {
+    fn uniform_type(&self) -> UniformType {
+        UniformType::Vec3
+    }
+    fn std140_bytes(&self) -> Vec<u8> {
+        bytemuck::cast_slice(&[self.x, self.y, self.z]).to_vec()
+    }
+}
+
+impl UniformValue for Vector4<f32> {
+    fn uniform_type(&self) -> UniformType {
+        UniformType::Vec4
+    }
+    fn std140_bytes(&self) -> Vec<u8> {
+        bytemuck::cast_slice(&[self.x, self.y, self.z, self.w]).to_vec()
+    }
+}
+
+impl UniformValue for Matrix3<f32> {
+    fn uniform_type(&self) -> UniformType {
+        UniformType::Mat3
+    }
+    fn std140_bytes(&self) -> Vec<u8> {
+        // std140 pads every column of a mat3 to a vec4.
+        let columns = [self.x, self.y, self.z];
+        let padded: Vec<f32> = columns
+            .iter()
+            .flat_map(|column| vec![column.x, column.y, column.z, 0.0])
+            .collect();
+        bytemuck::cast_slice(&padded).to_vec()
+    }
+}
+
+impl UniformValue for Matrix4<f32> {
+    fn uniform_type(&self) -> UniformType {
+        UniformType::Mat4
+    }
+    fn std140_bytes(&self) -> Vec<u8> {
+        let matrix: &[f32; 16] = self.as_ref();
+        bytemuck::cast_slice(matrix).to_vec()
+    }
+}
+
+struct UniformBlock {
+    binding: u32,
+    name: String,
+    members: Vec<ReflectedMember>,
+    buffer: wgpu::Buffer,
+    data: Vec<u8>,
+    dirty: Cell<bool>,
+}
+
+/// A material whose fragment shader is provided at runtime. The bind group
+/// layout of the material slot (set 2) is derived from the shader itself:
+/// uniform blocks get a buffer settable through `set_uniform`, textures default
+/// to white until `set_texture` is called and samplers are linear.
+pub struct ShaderMaterial {
+    spirv: Vec<u32>,
+    path: Option<PathBuf>,
+    bindings: Vec<ReflectedBinding>,
+    uniform_blocks: Vec<UniformBlock>,
+    textures: Vec<(u32, Rc<Texture>)>,
+    samplers: Vec<(u32, wgpu::Sampler)>,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    blend_mode: BlendMode,
+}
+
+impl ShaderMaterial {
+    /// The blend mode is part of the render pipelines built from the
+    /// material, so it cannot change afterwards.
+    pub fn new(
+        source: ShaderSource,
+        blend_mode: BlendMode,
+        state: &State,
+    ) -> Result<ShaderMaterial, ShaderError> {
+        let spirv = source.to_spirv(shaderc::ShaderKind::Fragment, "shader_material.frag")?;
+        ShaderMaterial::from_spirv(spirv, None, blend_mode, state)
+    }
+
+    /// Loads a GLSL (or `.spv`) fragment shader from disk. The file is watched
+    /// by `ShaderHotReload`.
+    pub fn from_file(
+        path: impl AsRef<Path>,
+        blend_mode: BlendMode,
+        state: &State,
+    ) -> Result<ShaderMaterial, ShaderError> {
+        let path = path.as_ref();
+        let spirv = load_shader(path, shaderc::ShaderKind::Fragment)?;
+        // Absolute, so that `ShaderHotReload` does not take it for a built-in shader.
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        ShaderMaterial::from_spirv(spirv, Some(path), blend_mode, state)
+    }
+
+    fn from_spirv(
+        spirv: Vec<u32>,
+        path: Option<PathBuf>,
+        blend_mode: BlendMode,
+        state: &State,
+    ) -> Result<ShaderMaterial, ShaderError> {
+        let bindings = reflect_bindings(&spirv)?
+            .into_iter()
+            .filter(|binding| binding.set >= MATERIAL_BIND_GROUP)
+            .collect::<Vec<_>>();
+
+        let mut uniform_blocks = Vec::new();
+        let mut textures = Vec::new();
+        let mut samplers = Vec::new();
+        let mut layout_entries = Vec::new();
+
+        for binding in bindings.iter() {
+            let unsupported = |reason: &str| ShaderError::UnsupportedBinding {
+                set: binding.set,
+                binding: binding.binding,
+                reason: reason.to_string(),
+            };
+            if binding.set != MATERIAL_BIND_GROUP {
+                return Err(unsupported("materials can only declare bindings in set 2"));
+            }
+
+            let ty = match &binding.ty {
+                ReflectedBindingType::UniformBlock { size, members } => {
+                    uniform_blocks.push(UniformBlock {
+                        binding: binding.binding,
+                        name: binding.name.clone(),
+                        members: members.clone(),
+                        buffer: state.device.create_buffer(&wgpu::BufferDescriptor {
+                            label: Some(&binding.name),
+                            size: *size as u64,
+                            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+                            mapped_at_creation: false,
+                        }),
+                        data: vec![0; *size as usize],
+                        dirty: Cell::new(true),
+                    });
+                    wgpu::BindingType::UniformBuffer {
+                        dynamic: false,
+                        min_binding_size: std::num::NonZeroU64::new(*size as u64),
+                    }
+                }
+                ReflectedBindingType::Texture {
+                    dimension,
+                    multisampled,
+                } => {
+                    if *dimension != wgpu::TextureViewDimension::D2 || *multisampled {
+                        return Err(unsupported("only single sampled 2D textures are supported"));
+                    }
+                    textures.push((
+                        binding.binding,
+                        Rc::new(Texture::from_color([255, 255, 255, 255], true, None, state)),
+                    ));
+                    wgpu::BindingType::SampledTexture {
+                        dimension: *dimension,
+                        component_type: wgpu::TextureComponentType::Float,
+                        multisampled: false,
+                    }
+                }
+                ReflectedBindingType::StorageBuffer => {
+                    return Err(unsupported("storage buffers are not supported"))
+                }
+                ReflectedBindingType::Sampler => {
+                    samplers.push((
+                        binding.binding,
+                        state.device.create_sampler(&wgpu::SamplerDescriptor {
+                            label: Some(&binding.name),
+                            address_mode_u: wgpu::AddressMode::Repeat,
+                            address_mode_v: wgpu::AddressMode::Repeat,
+                            address_mode_w: wgpu::AddressMode::Repeat,
+                            mag_filter: wgpu::FilterMode::Linear,
+                            min_filter: wgpu::FilterMode::Linear,
+                            mipmap_filter: wgpu::FilterMode::Nearest,
+                            ..Default::default()
+                        }),
+                    ));
+                    wgpu::BindingType::Sampler { comparison: false }
+                }
+            };
+
+            layout_entries.push(wgpu::BindGroupLayoutEntry {
+                binding: binding.binding,
+                visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+                ty,
+                count: None,
+            });
+        }
+
+        let bind_group_layout =
+            state
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    entries: &layout_entries,
+                    label: Some("shader_material_bind_group_layout"),
+                });
+
+        let bind_group = ShaderMaterial::create_bind_group(
+            &bind_group_layout,
+            &uniform_blocks,
+            &textures,
+            &samplers,
+            state,
+        );
+
+        Ok(ShaderMaterial {
+            spirv,
+            path,
+            bindings,
+            uniform_blocks,
+            textures,
+            samplers,
+            bind_group_layout,
+            bind_group,
+            blend_mode,
+        })
+    }
+
+    fn create_bind_group(
+        layout: &wgpu::BindGroupLayout,
+        uniform_blocks: &[UniformBlock],
+        textures: &[(u32, Rc<Texture>)],
+        samplers: &[(u32, wgpu::Sampler)],
+        state: &State,
+    ) -> wgpu::BindGroup {
+        let mut entries = Vec::new();
+        for block in uniform_blocks.iter() {
+            entries.push(wgpu::BindGroupEntry {
+                binding: block.binding,
+                resource: block.buffer.as_entire_binding(),
+            });
+        }
+        for (binding, texture) in textures.iter() {
+            entries.push(wgpu::BindGroupEntry {
+                binding: *binding,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            });
+        }
+        for (binding, sampler) in samplers.iter() {
+            entries.push(wgpu::BindGroupEntry {
+                binding: *binding,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            });
+        }
+
+        state.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &entries,
+            label: Some("shader_material_bind_group"),
+        })
+    }
+
+    pub fn bindings(&self) -> &[ReflectedBinding] {
+        &self.bindings
+    }
+
+    /// Sets a member of one of the shader's uniform blocks, either by its name or
+    /// as `Block.member`. The value is uploaded the next time the material is drawn.
+    pub fn set_uniform<T: UniformValue>(
+        &mut self,
+        name: &str,
+        value: T,
+    ) -> Result<(), ShaderError> {
+        let (block_name, member_name) = match name.find('.') {
+            Some(dot) => (Some(&name[..dot]), &name[dot + 1..]),
+            None => (None, name),
+        };
+
+        let block = self
+            .uniform_blocks
+            .iter_mut()
+            .filter(|block| block_name.is_none() || block_name == Some(block.name.as_str()))
+            .find(|block| {
+                block
+                    .members
+                    .iter()
+                    .any(|member| member.name == member_name)
+            })
+            .ok_or_else(|| ShaderError::UnknownUniform(name.to_string()))?;
+        let member = block
+            .members
+            .iter()
+            .find(|member| member.name == member_name)
+            .unwrap();
+
+        if member.ty != value.uniform_type() {
+            return Err(ShaderError::UniformTypeMismatch {
+                name: name.to_string(),
+                expected: member.ty,
+                found: value.uniform_type(),
+            });
+        }
+
+        let bytes = value.std140_bytes();
+        let offset = member.offset as usize;
+        let end = offset + bytes.len();
+        if end > block.data.len() {
+            return Err(ShaderError::UniformOutOfBounds {
+                name: name.to_string(),
+                offset: member.offset,
+                block_size: block.data.len() as u32,
+            });
+        }
+        block.data[offset..end].copy_from_slice(&bytes);
+        block.dirty.set(true);
+        Ok(())
+    }
+
+    /// Takes a `Texture` or a shared one, like the texture of a `RenderTexture`.
+    pub fn set_texture(
+        &mut self,
+        name: &str,
+        texture: impl Into<Rc<Texture>>,
+        state: &State,
+    ) -> Result<(), ShaderError> {
+        let binding = self
+            .bindings
+            .iter()
+            .find(|binding| {
+                binding.name == name && matches!(binding.ty, ReflectedBindingType::Texture { .. })
+            })
+            .ok_or_else(|| ShaderError::UnknownUniform(name.to_string()))?
+            .binding;
+
+        for (texture_binding, current) in self.textures.iter_mut() {
+            if *texture_binding == binding {
+                *current = texture.into();
+                break;
+            }
+        }
+
+        self.bind_group = ShaderMaterial::create_bind_group(
+            &self.bind_group_layout,
+            &self.uniform_blocks,
+            &self.textures,
+            &self.samplers,
+            state,
+        );
+        Ok(())
+    }
+}
+
+impl Bindable for ShaderMaterial {
+    fn bind_group(&self) -> Option<&wgpu::BindGroup> {
+        Some(&self.bind_group)
+    }
+}
+
+impl Material for ShaderMaterial {
+    fn fragment_stage(&self) -> wgpu::ShaderModuleSource<'_> {
+        wgpu::ShaderModuleSource::SpirV(std::borrow::Cow::Borrowed(&self.spirv))
+    }
+    fn color_states(&self, format: wgpu::TextureFormat) -> Vec<wgpu::ColorStateDescriptor> {
+        vec![wgpu::ColorStateDescriptor {
+            format,
+            color_blend: self.blend_mode.color_blend(),
+            alpha_blend: self.blend_mode.alpha_blend(),
+            write_mask: wgpu::ColorWrite::ALL,
+        }]
+    }
+    fn sample_mask(&self) -> u32 {
+        !0
+    }
+
+    fn alpha_to_coverage_enabled(&self) -> bool {
+        false
+    }
+
+    fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    fn sampled_textures(&self) -> Vec<&Rc<Texture>> {
+        self.textures.iter().map(|(_, texture)| texture).collect()
+    }
+    fn update_buffers(&self, state: &State) {
+        for block in self.uniform_blocks.iter() {
+            if block.dirty.replace(false) {
+                state.queue.write_buffer(&block.buffer, 0, &block.data);
+            }
+        }
+    }
+
+    fn draw_material<'a>(
+        &'a self,
+        geometry: &'a dyn Geometry,
+        _lights: &'a Vec<&dyn Light>,
+        pipeline: &'a wgpu::RenderPipeline,
+        render_pass: &mut RenderPassWrapper<'a, '_>,
+    ) {
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_groups(geometry, self);
+        geometry.draw_geometry(render_pass);
+    }
+
+    fn material_bind_group_layout(&self) -> Option<&wgpu::BindGroupLayout> {
+        Some(&self.bind_group_layout)
+    }
+
+    fn fragment_shader_path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+}
@@ -9,11 +9,17 @@ layout( push_constant ) uniform ConstData {
 
 layout(location=0) out vec4 f_color;
 
-layout(binding=2)
+layout(set=2, binding=0)
 uniform Uniforms {
     vec3 color;
+    float opacity;
+    float alpha_cutoff;
+    uint premultiply;
 };
 
 void main() {
-    f_color = vec4(color, 1.0);
-}
\ No newline at end of file
+    if (opacity < alpha_cutoff) {
+        discard;
+    }
+    f_color = premultiply != 0 ? vec4(color * opacity, opacity) : vec4(color, opacity);
+}
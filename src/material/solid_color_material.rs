@@ -1,9 +1,20 @@
-use wgpu::util::DeviceExt;
+use std::path::Path;
 
-use crate::{Bindable, Geometry, Light, Material, RenderPassWrapper, State};
+use crate::{
+    Bindable, BlendMode, Geometry, Light, Material, RenderPassWrapper, State, UniformBuffer,
+};
 
+#[repr(C)]
 #[derive(Copy, Clone)]
-struct SolidColorMaterialData(cgmath::Vector3<f32>);
+struct SolidColorMaterialData {
+    color: cgmath::Vector3<f32>,
+    opacity: f32,
+    alpha_cutoff: f32,
+    /// 1 when the blend mode expects the color multiplied by the opacity.
+    premultiply: u32,
+    /// Pads the struct to its std140 size.
+    _padding: [u32; 2],
+}
 
 unsafe impl bytemuck::Zeroable for SolidColorMaterialData {}
 unsafe impl bytemuck::Pod for SolidColorMaterialData {}
@@ -11,24 +22,39 @@ unsafe impl bytemuck::Pod for SolidColorMaterialData {}
 pub struct SolidColorMaterial {
     bind_group_layout: wgpu::BindGroupLayout,
     bind_group: wgpu::BindGroup,
+    uniform: UniformBuffer<SolidColorMaterialData>,
+    blend_mode: BlendMode,
 }
 
 impl SolidColorMaterial {
     pub fn new(color: cgmath::Vector3<f32>, state: &State) -> SolidColorMaterial {
-        let buffer = state
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Color Buffer"),
-                contents: bytemuck::cast_slice(&[SolidColorMaterialData(color)]),
-                usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
-            });
+        SolidColorMaterial::transparent(color, 1.0, BlendMode::Opaque, state)
+    }
+
+    pub fn transparent(
+        color: cgmath::Vector3<f32>,
+        opacity: f32,
+        blend_mode: BlendMode,
+        state: &State,
+    ) -> SolidColorMaterial {
+        let uniform = UniformBuffer::new(
+            SolidColorMaterialData {
+                color,
+                opacity,
+                alpha_cutoff: blend_mode.alpha_cutoff(),
+                premultiply: (blend_mode == BlendMode::Premultiplied) as u32,
+                _padding: [0; 2],
+            },
+            Some("Color Buffer"),
+            state,
+        );
 
         let bind_group_layout =
             state
                 .device
                 .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                     entries: &[wgpu::BindGroupLayoutEntry {
-                        binding: 2,
+                        binding: 0,
                         visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
                         ty: wgpu::BindingType::UniformBuffer {
                             dynamic: false,
@@ -40,23 +66,41 @@ impl SolidColorMaterial {
                         },
                         count: None,
                     }],
-                    label: Some("solid_color_material_uniform_bind_group_layout".into()),
+                    label: Some("solid_color_material_uniform_bind_group_layout"),
                 });
 
         let bind_group = state.device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: &bind_group_layout,
             entries: &[wgpu::BindGroupEntry {
-                binding: 2,
-                resource: buffer.as_entire_binding(),
+                binding: 0,
+                resource: uniform.buffer().as_entire_binding(),
             }],
-            label: Some("solid_color_material_uniform_bind_group".into()),
+            label: Some("solid_color_material_uniform_bind_group"),
         });
 
         SolidColorMaterial {
             bind_group_layout,
             bind_group,
+            uniform,
+            blend_mode,
         }
     }
+
+    pub fn color(&self) -> cgmath::Vector3<f32> {
+        self.uniform.data().color
+    }
+
+    pub fn set_color(&mut self, color: cgmath::Vector3<f32>) {
+        self.uniform.data_mut().color = color;
+    }
+
+    pub fn opacity(&self) -> f32 {
+        self.uniform.data().opacity
+    }
+
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.uniform.data_mut().opacity = opacity;
+    }
 }
 
 impl Bindable for SolidColorMaterial {
@@ -67,22 +111,19 @@ impl Bindable for SolidColorMaterial {
 
 impl Material for SolidColorMaterial {
     fn fragment_stage(&self) -> wgpu::ShaderModuleSource<'static> {
-        wgpu::include_spirv!("solid_color_material.frag.spv")
+        wgpu::include_spirv!(concat!(
+            env!("OUT_DIR"),
+            "/material/solid_color_material.frag.spv"
+        ))
     }
-    fn color_states(&self) -> Vec<wgpu::ColorStateDescriptor> {
+    fn color_states(&self, format: wgpu::TextureFormat) -> Vec<wgpu::ColorStateDescriptor> {
         vec![wgpu::ColorStateDescriptor {
-            format: wgpu::TextureFormat::Bgra8UnormSrgb,
-            color_blend: wgpu::BlendDescriptor::REPLACE,
-            alpha_blend: wgpu::BlendDescriptor::REPLACE,
+            format,
+            color_blend: self.blend_mode.color_blend(),
+            alpha_blend: self.blend_mode.alpha_blend(),
             write_mask: wgpu::ColorWrite::ALL,
         }]
     }
-    fn depth_stencil_state(&self) -> Option<wgpu::DepthStencilStateDescriptor> {
-        None
-    }
-    fn sample_count(&self) -> u32 {
-        1
-    }
     fn sample_mask(&self) -> u32 {
         !0
     }
@@ -91,6 +132,14 @@ impl Material for SolidColorMaterial {
         false
     }
 
+    fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    fn update_buffers(&self, state: &State) {
+        self.uniform.flush(state);
+    }
+
     fn draw_material<'a>(
         &'a self,
         geometry: &'a dyn Geometry,
@@ -99,16 +148,15 @@ impl Material for SolidColorMaterial {
         render_pass: &mut RenderPassWrapper<'a, '_>,
     ) {
         render_pass.set_pipeline(pipeline);
-        if let Some(ref bind_group) = geometry.bind_group() {
-            render_pass.set_bind_group(0, bind_group, &[]);
-        }
-        if let Some(ref bind_group) = self.bind_group() {
-            render_pass.set_bind_group(0, bind_group, &[]);
-        }
+        render_pass.set_bind_groups(geometry, self);
         geometry.draw_geometry(render_pass);
     }
 
     fn material_bind_group_layout(&self) -> Option<&wgpu::BindGroupLayout> {
         Some(&self.bind_group_layout)
     }
+
+    fn fragment_shader_path(&self) -> Option<&Path> {
+        Some(Path::new("material/solid_color_material.frag"))
+    }
 }